@@ -0,0 +1,236 @@
+use fnv::FnvHashSet;
+
+use game::*;
+use helpers::{catch_quietly, CardPossibilityTable, HandInfo};
+use json::Json;
+use strategy::*;
+
+// Wraps a strategy so that each player keeps track of how often partners break its conventions,
+// and stops relying on them once partners have done so more than `max_deviations` times: from
+// then on the player follows the fallback strategy, which asks less of partners (e.g. one that
+// takes every hint literally).
+//
+// A partner's move counts as unconventional if, read by the inner strategy's conventions, it
+// says something false about a card this player can see: afterwards the strategy's idea of
+// common knowledge rules that card out.  A move the strategy can't make sense of at all, so that
+// it panics trying, either to update its beliefs or to decide from them, means its beliefs are
+// lost, and the player falls back at once.  Only cards this player can see are checked, so
+// players may fall back on different turns.
+
+pub struct AdaptiveStrategyConfig {
+    inner: Box<GameStrategyConfig + Sync>,
+    fallback: Box<GameStrategyConfig + Sync>,
+    max_deviations: u32,
+}
+impl AdaptiveStrategyConfig {
+    pub fn new(inner: Box<GameStrategyConfig + Sync>, fallback: Box<GameStrategyConfig + Sync>,
+               max_deviations: u32) -> AdaptiveStrategyConfig {
+        AdaptiveStrategyConfig {
+            inner: inner,
+            fallback: fallback,
+            max_deviations: max_deviations,
+        }
+    }
+}
+impl GameStrategyConfig for AdaptiveStrategyConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        Box::new(AdaptiveStrategy {
+            inner: self.inner.initialize(opts),
+            fallback: self.fallback.initialize(opts),
+            max_deviations: self.max_deviations,
+        })
+    }
+
+    fn supports(&self, opts: &GameOptions) -> Result<(), String> {
+        try!(self.inner.supports(opts));
+        self.fallback.supports(opts)
+    }
+
+    fn on_game_end(&self, game: &GameState) {
+        self.inner.on_game_end(game)
+    }
+
+    fn version(&self) -> u32 {
+        self.inner.version()
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.inner.set_param(name, value)
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        self.inner.conventions().map(|conventions| {
+            let fallback = self.fallback.conventions().map_or(String::from("a simpler strategy"), |fallback| {
+                format!("the {} conventions", fallback.name)
+            });
+            conventions.section("Partners who deviate", vec![
+                format!("Once partners have made more than {} moves that, read by these conventions, say \
+                         something false about a card the player can see, the player follows {} instead.",
+                        self.max_deviations, fallback),
+                format!("A player who can't make sense of a partner's move follows {} at once.", fallback),
+            ])
+        })
+    }
+}
+
+pub struct AdaptiveStrategy {
+    inner: Box<GameStrategy>,
+    fallback: Box<GameStrategy>,
+    max_deviations: u32,
+}
+impl GameStrategy for AdaptiveStrategy {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(AdaptivePlayerStrategy {
+            me: context.player,
+            inner: self.inner.initialize(context, rng.clone()),
+            // follows the game from the start, so it's ready to take over at any point
+            fallback: self.fallback.initialize(context, rng),
+            max_deviations: self.max_deviations,
+            deviations: 0,
+            contradicted: FnvHashSet::default(),
+            fell_back: None,
+        })
+    }
+
+    fn seat_name(&self, player: Player) -> String {
+        self.inner.seat_name(player)
+    }
+}
+
+pub struct AdaptivePlayerStrategy {
+    me: Player,
+    inner: Box<PlayerStrategy>,
+    fallback: Box<PlayerStrategy>,
+    max_deviations: u32,
+    // the partners' moves found unconventional so far
+    deviations: u32,
+    // the ids of the cards the inner strategy's common knowledge has ruled out
+    contradicted: FnvHashSet<CardId>,
+    // the first turn played by the fallback strategy, if this player has switched to it
+    fell_back: Option<u32>,
+}
+impl AdaptivePlayerStrategy {
+    // the ids of the cards this player can see that the inner strategy believes everyone knows
+    // can't be what they are
+    fn find_contradictions(&self, view: &BorrowedGameView) -> Vec<CardId> {
+        let board = view.board;
+        board.get_players().filter(|&player| player != self.me).flat_map(|player| {
+            let probabilities = self.inner.public_card_probabilities(player).unwrap_or_else(Vec::new);
+            view.get_hand(&player).iter().zip(board.card_ids(&player).iter()).zip(probabilities.into_iter())
+                .filter(|&((card, _), ref possibilities)| {
+                    !possibilities.iter().any(|&(ref possible, p)| possible == card && p > 0.0)
+                })
+                .map(|((_, &card_id), _)| card_id)
+                .collect::<Vec<_>>()
+        }).collect()
+    }
+
+    fn fall_back(&mut self, turn: u32, why: &str) {
+        debug!("Player {} stops following its conventions from turn {}: {}", self.me, turn, why);
+        self.fell_back = Some(turn);
+    }
+}
+impl PlayerStrategy for AdaptivePlayerStrategy {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        if self.fell_back.is_none() {
+            let decided = {
+                let inner = &mut self.inner;
+                catch_quietly(|| inner.decide(view))
+            };
+            match decided {
+                Ok(choice) => return choice,
+                Err(why) => {
+                    let why = format!("couldn't decide by its conventions: {}", why);
+                    self.fall_back(view.board.turn, &why);
+                }
+            }
+        }
+        self.fallback.decide(view)
+    }
+
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.fallback.update(turn, view);
+        if self.fell_back.is_some() {
+            return;
+        }
+        let updated = {
+            let inner = &mut self.inner;
+            catch_quietly(|| inner.update(turn, view))
+        };
+        if let Err(why) = updated {
+            let why = format!("couldn't make sense of player {}'s move: {}", turn.player, why);
+            return self.fall_back(view.board.turn, &why);
+        }
+        let contradictions = self.find_contradictions(view);
+        let new = contradictions.iter().filter(|&&card_id| !self.contradicted.contains(&card_id)).count();
+        self.contradicted.extend(contradictions);
+        if turn.player != self.me && new > 0 {
+            self.deviations += 1;
+            if self.deviations > self.max_deviations {
+                let why = format!("{} unconventional moves", self.deviations);
+                self.fall_back(view.board.turn, &why);
+            }
+        }
+    }
+
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.fallback.replay(turn, view);
+        self.inner.replay(turn, view);
+    }
+
+    fn concede(&mut self, view: &BorrowedGameView) -> bool {
+        if self.fell_back.is_some() {
+            self.fallback.concede(view)
+        } else {
+            self.inner.concede(view)
+        }
+    }
+
+    fn public_info_digest(&self) -> Option<u64> {
+        if self.fell_back.is_some() {
+            self.fallback.public_info_digest()
+        } else {
+            self.inner.public_info_digest()
+        }
+    }
+
+    fn public_card_probabilities(&self, player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        if self.fell_back.is_some() {
+            self.fallback.public_card_probabilities(player)
+        } else {
+            self.inner.public_card_probabilities(player)
+        }
+    }
+
+    fn own_hand_knowledge(&self, view: &BorrowedGameView) -> Option<HandInfo<CardPossibilityTable>> {
+        if self.fell_back.is_some() {
+            self.fallback.own_hand_knowledge(view)
+        } else {
+            self.inner.own_hand_knowledge(view)
+        }
+    }
+
+    fn notes(&self) -> Vec<CardNote> {
+        if self.fell_back.is_some() {
+            self.fallback.notes()
+        } else {
+            self.inner.notes()
+        }
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        let mut metrics = self.inner.metrics();
+        metrics.push(("partner deviations", self.deviations as f64));
+        metrics.push(("players fallen back", if self.fell_back.is_some() { 1.0 } else { 0.0 }));
+        metrics
+    }
+
+    fn inspect(&self) -> Option<Json> {
+        let active = if self.fell_back.is_some() { &self.fallback } else { &self.inner };
+        Some(Json::object()
+             .with("deviations", self.deviations)
+             .with("max_deviations", self.max_deviations)
+             .with("fell_back_from_turn", self.fell_back)
+             .with("inner", active.inspect().unwrap_or(Json::Null)))
+    }
+}
@@ -0,0 +1,451 @@
+use fnv::FnvHashMap;
+use std::fs;
+use std::path::Path;
+
+use game::*;
+use json::Json;
+use observer::DecideTimes;
+use simulator::{DealFilter, DiscardRiskStats, HintStats, Histogram, PlayStats, SimResult, StackCurves, TempoStats};
+use version::ArtifactVersion;
+
+// Saves the progress of a long simulation, so that an interrupted run can pick up where it
+// left off instead of starting over.  A checkpoint holds the merged results of every seed
+// before `next_seed`, along with enough about the run to refuse to resume a different one.
+//
+// Decision timings aren't saved: they depend on the machine and its load, so mixing them
+// across processes would be misleading.  After resuming, they only cover the new games.
+
+const END_CAUSES: [EndCause; 7] = [
+    EndCause::PerfectScore,
+    EndCause::StruckOut,
+    EndCause::DeckExhausted,
+    EndCause::Stuck,
+    EndCause::Settled,
+    EndCause::Forfeited,
+    EndCause::Conceded,
+];
+
+pub struct Checkpoint {
+    pub version: ArtifactVersion,
+    // the game options, as described by describe_options
+    pub options: String,
+    // the strategy parameters set for the run, as NAME=VALUE, in the order they were set
+    pub strategy_params: Vec<String>,
+    // the deal filters, as DealFilter::from_str reads them
+    pub deal_filters: Vec<String>,
+    pub first_seed: u32,
+    pub n_trials: u32,
+    // the first seed whose results aren't in `result`
+    pub next_seed: u32,
+    pub result: SimResult,
+}
+
+// everything about the rules that could change a game's outcome
+fn describe_options(opts: &GameOptions) -> String {
+    format!("{} players, hand size {}, {} hints ({} at start, {}/{} half hints per discard/firework), \
+             {} lives, allow_empty_hints={}, endgame={:?}, misplays={:?}, strikeout_scores_zero={}, \
+             end_when_settled={}, illegal_moves={:?}",
+            opts.num_players, opts.hand_size, opts.num_hints, opts.starting_hints,
+            opts.discard_half_hints, opts.firework_half_hints, opts.num_lives,
+            opts.allow_empty_hints, opts.endgame_rule, opts.misplay_rule, opts.strikeout_scores_zero,
+            opts.end_when_settled, opts.illegal_moves)
+}
+
+fn describe_params(params: &[(String, String)]) -> Vec<String> {
+    params.iter().map(|&(ref name, ref value)| format!("{}={}", name, value)).collect()
+}
+
+fn describe_filters(filters: &[DealFilter]) -> Vec<String> {
+    filters.iter().map(|filter| filter.to_string()).collect()
+}
+
+impl Checkpoint {
+    pub fn new(version: &ArtifactVersion, opts: &GameOptions, strategy_params: &[(String, String)],
+               deal_filters: &[DealFilter], first_seed: u32, n_trials: u32) -> Checkpoint {
+        Checkpoint {
+            version: version.clone(),
+            options: describe_options(opts),
+            strategy_params: describe_params(strategy_params),
+            deal_filters: describe_filters(deal_filters),
+            first_seed: first_seed,
+            n_trials: n_trials,
+            next_seed: first_seed,
+            result: SimResult::new(),
+        }
+    }
+
+    // The checkpoint at `path` if there is one, or a fresh one if not.
+    // Fails if the saved checkpoint is for a different run.
+    pub fn resume(path: &str, version: &ArtifactVersion, opts: &GameOptions, strategy_params: &[(String, String)],
+                  deal_filters: &[DealFilter], first_seed: u32, n_trials: u32) -> Result<Checkpoint, String> {
+        if !Path::new(path).exists() {
+            return Ok(Checkpoint::new(version, opts, strategy_params, deal_filters, first_seed, n_trials));
+        }
+        let contents = try!(fs::read_to_string(path).map_err(|err| format!("Couldn't read {}: {}", path, err)));
+        let json = try!(Json::parse(&contents).map_err(|err| format!("{}: {}", path, err)));
+        let checkpoint = try!(Checkpoint::from_json(&json).map_err(|err| format!("{}: {}", path, err)));
+        try!(checkpoint.version.check_compatible(version).map_err(|err| format!("{}: {}", path, err)));
+        let options = describe_options(opts);
+        if checkpoint.options != options {
+            return Err(format!("{} was saved with different game options ({}), not {}",
+                               path, checkpoint.options, options));
+        }
+        let strategy_params = describe_params(strategy_params);
+        if checkpoint.strategy_params != strategy_params {
+            return Err(format!("{} was saved with strategy parameters [{}], not [{}]",
+                               path, checkpoint.strategy_params.join(", "), strategy_params.join(", ")));
+        }
+        let deal_filters = describe_filters(deal_filters);
+        if checkpoint.deal_filters != deal_filters {
+            return Err(format!("{} was saved with deal filters [{}], not [{}]",
+                               path, checkpoint.deal_filters.join(", "), deal_filters.join(", ")));
+        }
+        if (checkpoint.first_seed, checkpoint.n_trials) != (first_seed, n_trials) {
+            return Err(format!("{} is for {} games from seed {}, not {} games from seed {}",
+                               path, checkpoint.n_trials, checkpoint.first_seed, n_trials, first_seed));
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.first_seed + self.n_trials - self.next_seed
+    }
+
+    // adds the results of the next `n_seeds` seeds
+    pub fn record(&mut self, n_seeds: u32, result: SimResult) {
+        assert!(n_seeds <= self.remaining());
+        self.next_seed += n_seeds;
+        self.result.merge(result);
+    }
+
+    // Replaces the file at `path`, without ever leaving a partly written checkpoint behind
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", path);
+        try!(fs::write(&tmp_path, format!("{}\n", self.to_json()))
+            .map_err(|err| format!("Couldn't write {}: {}", tmp_path, err)));
+        fs::rename(&tmp_path, path).map_err(|err| format!("Couldn't replace {}: {}", path, err))
+    }
+
+    pub fn to_json(&self) -> Json {
+        let result = &self.result;
+        let mut end_causes = Json::object();
+        for end_cause in END_CAUSES.iter() {
+            if let Some(&count) = result.end_causes.get(end_cause) {
+                end_causes.insert(&end_cause.to_string(), count);
+            }
+        }
+        let mut incomplete_colors = Json::object();
+        for color in COLORS.iter() {
+            if let Some(&count) = result.incomplete_colors.get(color) {
+                incomplete_colors.insert(&color.to_string(), count);
+            }
+        }
+        let seed_scores = result.seed_scores.iter().map(|&(seed, score)| {
+            Json::Array(vec![seed.into(), score.into()])
+        }).collect::<Vec<_>>();
+        let play_stats = &result.play_stats;
+        Json::object()
+            .with("version", self.version.to_json())
+            .with("options", &self.options[..])
+            .with("strategy_params", self.strategy_params.clone())
+            .with("deal_filters", self.deal_filters.clone())
+            .with("first_seed", self.first_seed)
+            .with("n_trials", self.n_trials)
+            .with("next_seed", self.next_seed)
+            .with("result", Json::object()
+                  .with("scores", histogram_json(&result.scores))
+                  .with("lives", histogram_json(&result.lives))
+                  .with("non_perfect_seed", result.non_perfect_seed)
+                  .with("filtered_seeds", result.filtered_seeds)
+                  .with("seed_scores", seed_scores)
+                  .with("end_causes", end_causes)
+                  .with("incomplete_colors", incomplete_colors)
+                  .with("life_loss_turns", Json::Array(result.life_loss_turns.iter().map(histogram_json).collect()))
+                  .with("turns_saved", Json::Number(result.turns_saved as f64))
+                  .with("turns_conceded", Json::Number(result.turns_conceded as f64))
+                  .with("discard_loss", histogram_json(&result.discard_loss))
+                  .with("tempo", tempo_json(&result.tempo))
+                  .with("stack_curves", stack_curves_json(&result.stack_curves))
+                  .with("hint_stats", hint_stats_json(&result.hint_stats))
+                  .with("play_stats", Json::object()
+                        .with("hints", play_stats.hints)
+                        .with("plays", play_stats.plays)
+                        .with("bombs", play_stats.bombs)
+                        .with("discards", play_stats.discards)
+                        .with("critical_discards", play_stats.critical_discards)
+                        .with("redundant_hints", play_stats.redundant_hints))
+                  .with("discard_risk", discard_risk_json(&result.discard_risk))
+                  .with("illegal_move_seeds", result.illegal_move_seeds.clone())
+                  .with("strategy_metrics", strategy_metrics_json(&result.strategy_metrics)))
+    }
+
+    pub fn from_json(json: &Json) -> Result<Checkpoint, String> {
+        let version = try!(ArtifactVersion::from_json(try!(field(json, "version"))));
+        let options = try!(try!(field(json, "options")).as_str()
+                           .ok_or_else(|| String::from("`options` should be a string"))).to_string();
+        let strategy_params = try!(strings(try!(field(json, "strategy_params")), "strategy_params"));
+        let deal_filters = try!(strings(try!(field(json, "deal_filters")), "deal_filters"));
+        let result_json = try!(field(json, "result"));
+
+        let mut result = SimResult::new();
+        result.scores = try!(histogram_from_json(try!(field(result_json, "scores"))));
+        result.lives = try!(histogram_from_json(try!(field(result_json, "lives"))));
+        result.non_perfect_seed = match *try!(field(result_json, "non_perfect_seed")) {
+            Json::Null => None,
+            ref seed => Some(try!(number(seed, "non_perfect_seed"))),
+        };
+        result.filtered_seeds = try!(u32_field(result_json, "filtered_seeds"));
+        for pair in try!(array(try!(field(result_json, "seed_scores")), "seed_scores")) {
+            match pair.as_array().map(|pair| &pair[..]) {
+                Some(&[ref seed, ref score]) => {
+                    result.seed_scores.push((try!(number(seed, "seed")), try!(number(score, "score"))));
+                }
+                _ => return Err(format!("Expected a [seed, score] pair, got {}", pair)),
+            }
+        }
+        let end_causes = try!(field(result_json, "end_causes"));
+        for end_cause in END_CAUSES.iter() {
+            if let Some(count) = end_causes.get(&end_cause.to_string()) {
+                result.end_causes.insert(*end_cause, try!(number(count, "end cause count")));
+            }
+        }
+        let incomplete_colors = try!(field(result_json, "incomplete_colors"));
+        for color in COLORS.iter() {
+            if let Some(count) = incomplete_colors.get(&color.to_string()) {
+                result.incomplete_colors.insert(*color, try!(number(count, "incomplete color count")));
+            }
+        }
+        for hist in try!(array(try!(field(result_json, "life_loss_turns")), "life_loss_turns")) {
+            result.life_loss_turns.push(try!(histogram_from_json(hist)));
+        }
+        result.turns_saved = match *try!(field(result_json, "turns_saved")) {
+            Json::Number(n) if n >= 0.0 && n.fract() == 0.0 => n as u64,
+            ref other => return Err(format!("`turns_saved` should be a count, got {}", other)),
+        };
+        result.turns_conceded = match *try!(field(result_json, "turns_conceded")) {
+            Json::Number(n) if n >= 0.0 && n.fract() == 0.0 => n as u64,
+            ref other => return Err(format!("`turns_conceded` should be a count, got {}", other)),
+        };
+        result.discard_loss = try!(histogram_from_json(try!(field(result_json, "discard_loss"))));
+        result.tempo = try!(tempo_from_json(try!(field(result_json, "tempo"))));
+        result.stack_curves = try!(stack_curves_from_json(try!(field(result_json, "stack_curves"))));
+        result.hint_stats = try!(hint_stats_from_json(try!(field(result_json, "hint_stats"))));
+        let play_stats = try!(field(result_json, "play_stats"));
+        result.play_stats = PlayStats {
+            hints: try!(u32_field(play_stats, "hints")),
+            plays: try!(u32_field(play_stats, "plays")),
+            bombs: try!(u32_field(play_stats, "bombs")),
+            discards: try!(u32_field(play_stats, "discards")),
+            critical_discards: try!(u32_field(play_stats, "critical_discards")),
+            redundant_hints: try!(u32_field(play_stats, "redundant_hints")),
+        };
+        result.discard_risk = try!(discard_risk_from_json(try!(field(result_json, "discard_risk"))));
+        for seed in try!(array(try!(field(result_json, "illegal_move_seeds")), "illegal_move_seeds")) {
+            result.illegal_move_seeds.push(try!(number(seed, "seed")));
+        }
+        result.strategy_metrics = try!(strategy_metrics_from_json(try!(field(result_json, "strategy_metrics"))));
+        result.decide_times = DecideTimes::new();
+
+        let checkpoint = Checkpoint {
+            version: version,
+            options: options,
+            strategy_params: strategy_params,
+            deal_filters: deal_filters,
+            first_seed: try!(u32_field(json, "first_seed")),
+            n_trials: try!(u32_field(json, "n_trials")),
+            next_seed: try!(u32_field(json, "next_seed")),
+            result: result,
+        };
+        if checkpoint.next_seed < checkpoint.first_seed || checkpoint.remaining() > checkpoint.n_trials {
+            return Err(format!("Next seed {} is outside of the {} seeds from {}",
+                               checkpoint.next_seed, checkpoint.n_trials, checkpoint.first_seed));
+        }
+        Ok(checkpoint)
+    }
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, String> {
+    json.get(key).ok_or_else(|| format!("Checkpoint is missing `{}`", key))
+}
+
+fn number(json: &Json, what: &str) -> Result<u32, String> {
+    json.as_u32().ok_or_else(|| format!("Expected a count for {}, got {}", what, json))
+}
+
+fn u32_field(json: &Json, key: &str) -> Result<u32, String> {
+    number(try!(field(json, key)), key)
+}
+
+fn array<'a>(json: &'a Json, what: &str) -> Result<&'a Vec<Json>, String> {
+    json.as_array().ok_or_else(|| format!("Expected an array for {}, got {}", what, json))
+}
+
+fn strings(json: &Json, what: &str) -> Result<Vec<String>, String> {
+    try!(array(json, what)).iter().map(|item| {
+        item.as_str().map(String::from).ok_or_else(|| format!("Expected a string in {}, got {}", what, item))
+    }).collect()
+}
+
+fn histogram_json(hist: &Histogram) -> Json {
+    let mut values = hist.hist.iter().collect::<Vec<_>>();
+    values.sort();
+    Json::Array(values.into_iter().map(|(&value, &count)| {
+        Json::Array(vec![value.into(), count.into()])
+    }).collect())
+}
+
+fn tempo_json(tempo: &TempoStats) -> Json {
+    Json::object()
+        .with("lengths", histogram_json(&tempo.lengths))
+        .with("draws_remaining", histogram_json(&tempo.draws_remaining))
+        .with("min_paces", histogram_json(&tempo.min_paces))
+        .with("pace_by_turn", Json::Array(tempo.pace_by_turn.iter().map(|&(total, count)| {
+            Json::Array(vec![Json::Number(total as f64), count.into()])
+        }).collect()))
+}
+
+fn tempo_from_json(json: &Json) -> Result<TempoStats, String> {
+    let mut tempo = TempoStats::new();
+    tempo.lengths = try!(histogram_from_json(try!(field(json, "lengths"))));
+    tempo.draws_remaining = try!(histogram_from_json(try!(field(json, "draws_remaining"))));
+    tempo.min_paces = try!(histogram_from_json(try!(field(json, "min_paces"))));
+    for pair in try!(array(try!(field(json, "pace_by_turn")), "pace_by_turn")) {
+        match pair.as_array().map(|pair| &pair[..]) {
+            Some(&[Json::Number(total), ref count]) if total.fract() == 0.0 => {
+                tempo.pace_by_turn.push((total as i64, try!(number(count, "pace count"))));
+            }
+            _ => return Err(format!("Expected a [total pace, count] pair, got {}", pair)),
+        }
+    }
+    Ok(tempo)
+}
+
+fn total_count_json(pairs: &[(u64, u32)]) -> Json {
+    Json::Array(pairs.iter().map(|&(total, count)| {
+        Json::Array(vec![Json::Number(total as f64), count.into()])
+    }).collect())
+}
+
+fn total_counts_from_json(json: &Json, key: &str) -> Result<Vec<(u64, u32)>, String> {
+    let mut pairs = Vec::new();
+    for pair in try!(array(json, key)) {
+        match pair.as_array().map(|pair| &pair[..]) {
+            Some(&[Json::Number(total), ref count]) if total >= 0.0 && total.fract() == 0.0 => {
+                pairs.push((total as u64, try!(number(count, key))));
+            }
+            _ => return Err(format!("Expected a [total, count] pair in {}, got {}", key, pair)),
+        }
+    }
+    Ok(pairs)
+}
+
+fn stack_curves_json(curves: &StackCurves) -> Json {
+    Json::object()
+        .with("reached", Json::Array(curves.reached.iter().map(|by_value| total_count_json(by_value)).collect()))
+        .with("score_by_turn", total_count_json(&curves.score_by_turn))
+}
+
+fn stack_curves_from_json(json: &Json) -> Result<StackCurves, String> {
+    let mut curves = StackCurves::new();
+    let reached = try!(array(try!(field(json, "reached")), "reached"));
+    if reached.len() != COLORS.len() {
+        return Err(format!("Expected stack curves for {} colors, got {}", COLORS.len(), reached.len()));
+    }
+    for (i, by_value) in reached.iter().enumerate() {
+        let by_value = try!(total_counts_from_json(by_value, "reached"));
+        if by_value.len() != VALUES.len() {
+            return Err(format!("Expected a stack curve point per value, got {}", by_value.len()));
+        }
+        for (j, &pair) in by_value.iter().enumerate() {
+            curves.reached[i][j] = pair;
+        }
+    }
+    curves.score_by_turn = try!(total_counts_from_json(try!(field(json, "score_by_turn")), "score_by_turn"));
+    Ok(curves)
+}
+
+fn hint_stats_json(stats: &HintStats) -> Json {
+    Json::object()
+        .with("color_hints", stats.color_hints)
+        .with("value_hints", stats.value_hints)
+        .with("by_color", stats.by_color.clone())
+        .with("by_value", stats.by_value.clone())
+        .with("by_distance", stats.by_distance.clone())
+        .with("by_touched", stats.by_touched.clone())
+}
+
+fn hint_stats_from_json(json: &Json) -> Result<HintStats, String> {
+    let counts = |key: &str| -> Result<Vec<u32>, String> {
+        try!(array(try!(field(json, key)), key)).iter().map(|count| number(count, key)).collect()
+    };
+    let stats = HintStats {
+        color_hints: try!(u32_field(json, "color_hints")),
+        value_hints: try!(u32_field(json, "value_hints")),
+        by_color: try!(counts("by_color")),
+        by_value: try!(counts("by_value")),
+        by_distance: try!(counts("by_distance")),
+        by_touched: try!(counts("by_touched")),
+    };
+    if stats.by_color.len() != COLORS.len() || stats.by_value.len() != VALUES.len() {
+        return Err(String::from("Expected hint counts for every color and value"));
+    }
+    Ok(stats)
+}
+
+fn strategy_metrics_json(metrics: &FnvHashMap<String, f64>) -> Json {
+    let mut names = metrics.keys().collect::<Vec<_>>();
+    names.sort();
+    names.into_iter().fold(Json::object(), |json, name| json.with(name, metrics[name]))
+}
+
+fn strategy_metrics_from_json(json: &Json) -> Result<FnvHashMap<String, f64>, String> {
+    match *json {
+        Json::Object(ref fields) => fields.iter().map(|&(ref name, ref value)| match *value {
+            Json::Number(n) => Ok((name.clone(), n)),
+            ref other => Err(format!("Strategy metric `{}` should be a number, got {}", name, other)),
+        }).collect(),
+        ref other => Err(format!("`strategy_metrics` should be an object, got {}", other)),
+    }
+}
+
+fn discard_risk_json(risk: &DiscardRiskStats) -> Json {
+    let by_value = |counts: &[u32; 5]| Json::Array(counts.iter().map(|&count| count.into()).collect());
+    Json::object()
+        .with("dead", by_value(&risk.dead))
+        .with("copy_in_deck", by_value(&risk.copy_in_deck))
+        .with("copy_in_hand", by_value(&risk.copy_in_hand))
+        .with("last_copy", by_value(&risk.last_copy))
+}
+
+fn discard_risk_from_json(json: &Json) -> Result<DiscardRiskStats, String> {
+    let by_value = |key: &str| -> Result<[u32; 5], String> {
+        let counts = try!(array(try!(field(json, key)), key));
+        if counts.len() != VALUES.len() {
+            return Err(format!("Expected a count per value for {}, got {}", key, counts.len()));
+        }
+        let mut by_value = [0; 5];
+        for (i, count) in counts.iter().enumerate() {
+            by_value[i] = try!(number(count, key));
+        }
+        Ok(by_value)
+    };
+    Ok(DiscardRiskStats {
+        dead: try!(by_value("dead")),
+        copy_in_deck: try!(by_value("copy_in_deck")),
+        copy_in_hand: try!(by_value("copy_in_hand")),
+        last_copy: try!(by_value("last_copy")),
+    })
+}
+
+fn histogram_from_json(json: &Json) -> Result<Histogram, String> {
+    let mut hist = Histogram::new();
+    for pair in try!(array(json, "histogram")) {
+        match pair.as_array().map(|pair| &pair[..]) {
+            Some(&[ref value, ref count]) => {
+                hist.insert_many(try!(number(value, "histogram value")), try!(number(count, "histogram count")));
+            }
+            _ => return Err(format!("Expected a [value, count] pair, got {}", pair)),
+        }
+    }
+    Ok(hist)
+}
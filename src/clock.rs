@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// A source of time, so that timing statistics, timeouts, and the like
+// can be driven by a fake clock instead of real sleeps.
+pub trait Clock: Send + Sync {
+    // time elapsed since some fixed point (e.g. the creation of the clock)
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+
+    fn elapsed_since(&self, earlier: Duration) -> Duration {
+        let now = self.now();
+        if now > earlier { now - earlier } else { Duration::from_secs(0) }
+    }
+}
+
+// The real wall clock
+pub struct SystemClock {
+    start: Instant,
+}
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+
+    // a new system clock, for things that share a clock between threads, e.g. `simulate`
+    pub fn shared() -> Arc<Clock> {
+        Arc::new(SystemClock::new())
+    }
+}
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+// A clock that only moves when told to.  Sleeping advances it instantly.
+pub struct MockClock {
+    now: Mutex<Duration>,
+}
+impl MockClock {
+    pub fn new() -> MockClock {
+        MockClock {
+            now: Mutex::new(Duration::from_secs(0)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += duration;
+    }
+
+    pub fn set(&self, time: Duration) {
+        *self.now.lock().unwrap() = time;
+    }
+}
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
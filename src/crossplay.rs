@@ -0,0 +1,162 @@
+use clock::SystemClock;
+use game::*;
+use helpers::catch_quietly;
+use simulator;
+use strategy::*;
+
+// Measures how well strategies play with each other, rather than with themselves.
+// Conventions only work when both players share them, so a strategy that scores well
+// in self-play may fall apart with a partner that plays differently.
+
+// Plays each seat with one of several strategies.  Seats that share a strategy share
+// one GameStrategy, as in self-play, since some strategies coordinate through it.
+pub struct MixedStrategyConfig {
+    configs: Vec<Box<GameStrategyConfig + Sync>>,
+    // the index into `configs` of each player's strategy, in turn order
+    seats: Vec<usize>,
+}
+impl MixedStrategyConfig {
+    pub fn new(configs: Vec<Box<GameStrategyConfig + Sync>>, seats: Vec<usize>) -> MixedStrategyConfig {
+        assert!(seats.iter().all(|&index| index < configs.len()));
+        MixedStrategyConfig {
+            configs: configs,
+            seats: seats,
+        }
+    }
+}
+impl GameStrategyConfig for MixedStrategyConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        assert_eq!(self.seats.len() as u32, opts.num_players);
+        Box::new(MixedStrategy {
+            strategies: self.configs.iter().map(|config| config.initialize(opts)).collect(),
+            seats: self.seats.clone(),
+        })
+    }
+
+    fn supports(&self, opts: &GameOptions) -> Result<(), String> {
+        if self.seats.len() as u32 != opts.num_players {
+            return Err(format!("Have strategies for {} seats, but there are {} players",
+                               self.seats.len(), opts.num_players));
+        }
+        for config in self.configs.iter() {
+            try!(config.supports(opts));
+        }
+        Ok(())
+    }
+
+    fn on_game_end(&self, game: &GameState) {
+        for config in self.configs.iter() {
+            config.on_game_end(game);
+        }
+    }
+}
+
+pub struct MixedStrategy {
+    strategies: Vec<Box<GameStrategy>>,
+    seats: Vec<usize>,
+}
+impl GameStrategy for MixedStrategy {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        self.strategies[self.seats[context.player as usize]].initialize(context, rng)
+    }
+
+    fn seat_name(&self, player: Player) -> String {
+        self.strategies[self.seats[player as usize]].seat_name(player)
+    }
+}
+
+// How one pairing went
+#[derive(Debug,Clone)]
+pub enum Pairing {
+    Played { average_score: f32, score_stderr: f32 },
+    // one of the strategies can't play with these options
+    Unsupported(String),
+    // one of the strategies panicked, e.g. on a hint its conventions can't explain,
+    // with where and why
+    Crashed(String),
+}
+
+// Average scores of every ordered pair of strategies, on the same seeds
+pub struct Crossplay {
+    pub names: Vec<String>,
+    // pairings[i][j] is strategy i as the first player, with strategy j as the second
+    pub pairings: Vec<Vec<Pairing>>,
+}
+
+pub fn crossplay(
+        opts: &GameOptions,
+        names: &[&str],
+        make_config: &Fn(&str) -> Box<GameStrategyConfig + Sync>,
+        first_seed: u32,
+        n_trials: u32,
+        n_threads: u32,
+        deal_filters: &[simulator::DealFilter],
+    ) -> Crossplay {
+    assert_eq!(opts.num_players, 2, "Crossplay is only for 2-player games");
+    // every pairing plays the same deals
+    let decks = simulator::DeckCache::new();
+    let clock = SystemClock::shared();
+    let pairings = names.iter().map(|&first| {
+        names.iter().map(|&second| {
+            let config = if first == second {
+                MixedStrategyConfig::new(vec![make_config(first)], vec![0, 0])
+            } else {
+                MixedStrategyConfig::new(vec![make_config(first), make_config(second)], vec![0, 1])
+            };
+            if let Err(err) = config.supports(opts) {
+                return Pairing::Unsupported(err);
+            }
+            info!("Playing {} with {}", first, second);
+            // Most strategies assert that their partner follows their conventions, so mismatched
+            // pairs are expected to panic.  Keep the first panic of each pairing as the reason,
+            // rather than printing one for every thread.
+            let result = catch_quietly(|| {
+                simulator::simulate(opts, Box::new(config), Some(first_seed), n_trials, n_threads, None,
+                                    deal_filters, Some(&decks), None, simulator::Analyses::none(), &clock)
+            });
+            match result {
+                Ok(result) => Pairing::Played {
+                    average_score: result.average_score(),
+                    score_stderr: result.score_stderr(),
+                },
+                Err(reason) => {
+                    warn!("{} and {} couldn't finish their games together: {}", first, second, reason);
+                    Pairing::Crashed(reason)
+                }
+            }
+        }).collect()
+    }).collect();
+    Crossplay {
+        names: names.iter().map(|name| name.to_string()).collect(),
+        pairings: pairings,
+    }
+}
+
+impl Crossplay {
+    pub fn info(&self) {
+        let width = self.names.iter().map(|name| name.len()).max().unwrap_or(0).max(16);
+        info!("Average scores, with the first player's strategy on the left and the second's on top:");
+        let header = self.names.iter().map(|name| format!("{:>w$}", name, w = width)).collect::<Vec<_>>();
+        info!("{:w$} {}", "", header.join(" "), w = width);
+        for (name, row) in self.names.iter().zip(self.pairings.iter()) {
+            let cells = row.iter().map(|pairing| {
+                let cell = match *pairing {
+                    Pairing::Played { average_score, score_stderr } => {
+                        format!("{:.4} ± {:.4}", average_score, score_stderr)
+                    }
+                    Pairing::Unsupported(_) => String::from("unsupported"),
+                    Pairing::Crashed(_) => String::from("crashed"),
+                };
+                format!("{:>w$}", cell, w = width)
+            }).collect::<Vec<_>>();
+            info!("{:w$} {}", name, cells.join(" "), w = width);
+        }
+        for (first, row) in self.names.iter().zip(self.pairings.iter()) {
+            for (second, pairing) in self.names.iter().zip(row.iter()) {
+                if let Pairing::Unsupported(ref reason) = *pairing {
+                    info!("{} with {} is unsupported: {}", first, second, reason);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use fnv::FnvHashMap;
+use rand::{self, SeedableRng};
+use std::fs;
+use std::io::{BufRead, Write};
+
+use game::*;
+use html_replay::describe_turn;
+use json::Json;
+use render::{styled, RenderStyle};
+use simulator::new_deck;
+use spans;
+use strategy::*;
+
+// Steps through one game interactively, to debug a strategy's conventions without
+// sprinkling println!s through it: plays up to a breakpoint, then lets you look at what
+// each player's strategy knows (via PlayerStrategy::inspect), one turn at a time.
+// Plays exactly the same game as the simulator does for the seed.
+
+const HELP: &'static str = "\
+Commands:
+  s, step [N]       play N turns (default 1)
+  c, continue       play until the next breakpoint, or the end of the game
+  b, break TURN     stop before TURN is played
+  p, state          show the hands and the board
+  i, inspect [P]    show what player P's strategy knows (default: the player to move)
+  n, notes [P]      show player P's notes on the cards in every hand (default: the player to move)
+  d, dump FILE      write the state and every player's inspection and notes to FILE, as JSON
+  h, help           show this list
+  q, quit           stop debugging
+";
+
+pub struct Debugger {
+    seed: u32,
+    game: GameState,
+    strategies: FnvHashMap<Player, Box<PlayerStrategy>>,
+    // turns to stop before, in no particular order
+    breakpoints: Vec<u32>,
+    // how `state` shows cards
+    style: RenderStyle,
+}
+impl Debugger {
+    pub fn new(opts: &GameOptions, game_strategy: Box<GameStrategy>, seed: u32) -> Debugger {
+        let game = GameState::new(opts, new_deck(seed));
+        let strategies = game.get_players().map(|player| {
+            let rng = rand::ChaChaRng::from_seed(&[seed, player]);
+            let view = game.get_view(player);
+            (player, game_strategy.initialize(&InitContext::new(&*game_strategy, opts, &view).with_deck(&game.deck), rng))
+        }).collect();
+        Debugger {
+            seed: seed,
+            game: game,
+            strategies: strategies,
+            breakpoints: Vec::new(),
+            style: RenderStyle::Plain,
+        }
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    pub fn set_style(&mut self, style: RenderStyle) {
+        self.style = style;
+    }
+
+    pub fn add_breakpoint(&mut self, turn: u32) {
+        self.breakpoints.push(turn);
+    }
+
+    // plays one turn, as the simulator would, unless the game is over or the player concedes
+    pub fn step(&mut self) -> Option<TurnRecord> {
+        if self.game.is_over() {
+            return None;
+        }
+        let _game_span = spans::game_span(self.seed);
+        let player = self.game.board.player;
+        let _turn_span = spans::turn_span(self.game.board.turn, player);
+        if self.strategies.get_mut(&player).unwrap().concede(&self.game.get_view(player)) {
+            self.game.concede();
+            return None;
+        }
+        let choice = self.strategies.get_mut(&player).unwrap().decide(&self.game.get_view(player));
+        let turn = self.game.process_choice(choice);
+        for player in self.game.get_players() {
+            self.strategies.get_mut(&player).unwrap().update(&turn, &self.game.get_view(player));
+        }
+        Some(turn)
+    }
+
+    // plays until the game reaches a breakpoint or ends, returning the turns played
+    pub fn continue_to_breakpoint(&mut self) -> Vec<TurnRecord> {
+        let mut turns = Vec::new();
+        while let Some(turn) = self.step() {
+            turns.push(turn);
+            if self.breakpoints.contains(&self.game.board.turn) {
+                break;
+            }
+        }
+        turns
+    }
+
+    pub fn inspect(&self, player: Player) -> Option<Json> {
+        self.strategies[&player].inspect()
+    }
+
+    // player's notes, by the hand and slot of the card each is on, skipping cards no longer held
+    pub fn notes(&self, player: Player) -> Vec<(Player, usize, CardNote)> {
+        let board = &self.game.board;
+        let mut notes = self.strategies[&player].notes().into_iter().filter_map(|note| {
+            board.get_players().filter_map(|holder| {
+                board.slot_of(&holder, note.card_id).map(|slot| (holder, slot))
+            }).next().map(|(holder, slot)| (holder, slot, note))
+        }).collect::<Vec<_>>();
+        notes.sort_by_key(|&(holder, slot, _)| (holder, slot));
+        notes
+    }
+
+    pub fn dump(&self) -> Json {
+        let game = &self.game;
+        let hands = game.get_players().map(|player| {
+            let cards = game.hands[&player].iter().map(|card| card.to_string()).collect::<Vec<_>>();
+            cards.join(" ")
+        }).collect::<Vec<_>>();
+        let fireworks = COLORS.iter().map(|&color| {
+            format!("{}{}", color, game.board.get_firework(color).top)
+        }).collect::<Vec<_>>();
+        let discard = game.board.discard.cards.iter().map(|card| card.to_string()).collect::<Vec<_>>();
+        let inspections = game.get_players().map(|player| self.inspect(player)).collect::<Vec<_>>();
+        let notes = game.get_players().map(|player| {
+            Json::Array(self.notes(player).into_iter().map(|(holder, slot, note)| {
+                Json::object()
+                    .with("player", holder)
+                    .with("slot", slot)
+                    .with("note", note.to_string())
+            }).collect())
+        }).collect::<Vec<_>>();
+        Json::object()
+            .with("seed", self.seed)
+            .with("turn", game.board.turn)
+            .with("player", game.board.player)
+            .with("hints", game.board.hints_remaining)
+            .with("lives", game.board.lives_remaining)
+            .with("deck_size", game.board.deck_size)
+            .with("score", game.score())
+            .with("fireworks", fireworks.join(" "))
+            .with("discard", discard.join(" "))
+            .with("hands", hands)
+            .with("strategies", inspections)
+            .with("notes", notes)
+    }
+
+    fn prompt(&self) -> String {
+        if self.game.is_over() {
+            format!("(game over, score {}) ", self.game.score())
+        } else {
+            format!("(turn {}, player {}) ", self.game.board.turn, self.game.board.player)
+        }
+    }
+
+    // Reads commands from `input` until it runs out or says to quit
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> Result<(), String> {
+        let io_error = |err: ::std::io::Error| format!("Couldn't write debugger output: {}", err);
+        try!(write!(output, "{}", self.prompt()).map_err(&io_error));
+        try!(output.flush().map_err(&io_error));
+        for line in input.lines() {
+            let line = try!(line.map_err(|err| format!("Couldn't read a command: {}", err)));
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let message = match words.get(0).cloned() {
+                None => String::new(),
+                Some("q") | Some("quit") => return Ok(()),
+                Some(command) => match self.command(command, &words[1..]) {
+                    Ok(message) => message,
+                    Err(err) => format!("{}\n", err),
+                },
+            };
+            try!(write!(output, "{}{}", message, self.prompt()).map_err(&io_error));
+            try!(output.flush().map_err(&io_error));
+        }
+        Ok(())
+    }
+
+    // Carries out one command, returning what to show
+    fn command(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        let number = |what: &str, default: Option<u32>| -> Result<u32, String> {
+            match (args.get(0), default) {
+                (Some(arg), _) => arg.parse().map_err(|_| format!("Expected {}, got {}", what, arg)),
+                (None, Some(default)) => Ok(default),
+                (None, None) => Err(format!("Expected {}", what)),
+            }
+        };
+        let describe = |turns: Vec<TurnRecord>| {
+            turns.iter().map(|turn| format!("{}\n", describe_turn(turn))).collect::<String>()
+        };
+        match command {
+            "s" | "step" => {
+                let n = try!(number("a number of turns", Some(1)));
+                let turns = (0..n).filter_map(|_| self.step()).collect::<Vec<_>>();
+                Ok(describe(turns))
+            }
+            "c" | "continue" => Ok(describe(self.continue_to_breakpoint())),
+            "b" | "break" => {
+                let turn = try!(number("a turn", None));
+                self.add_breakpoint(turn);
+                Ok(format!("Will stop before turn {}\n", turn))
+            }
+            "p" | "state" => Ok(format!("{}\n", styled(&self.game, self.style))),
+            "i" | "inspect" => {
+                let player = try!(number("a player", Some(self.game.board.player)));
+                if player >= self.game.board.num_players {
+                    return Err(format!("There is no player {}", player));
+                }
+                Ok(match self.inspect(player) {
+                    Some(json) => format!("{}\n", json.pretty()),
+                    None => String::from("This strategy has nothing to show\n"),
+                })
+            }
+            "n" | "notes" => {
+                let player = try!(number("a player", Some(self.game.board.player)));
+                if player >= self.game.board.num_players {
+                    return Err(format!("There is no player {}", player));
+                }
+                let notes = self.notes(player);
+                if notes.is_empty() {
+                    return Ok(String::from("This strategy writes no notes\n"));
+                }
+                Ok(notes.into_iter().map(|(holder, slot, note)| {
+                    format!("player {} slot {}: {}\n", holder, slot, note)
+                }).collect())
+            }
+            "d" | "dump" => {
+                let path = try!(args.get(0).ok_or_else(|| String::from("Expected a file to dump to")));
+                try!(fs::write(path, format!("{}\n", self.dump().pretty()))
+                    .map_err(|err| format!("Couldn't write {}: {}", path, err)));
+                Ok(format!("Wrote the state to {}\n", path))
+            }
+            "h" | "help" => Ok(String::from(HELP)),
+            _ => Err(format!("Unknown command {}; try `help`", command)),
+        }
+    }
+}
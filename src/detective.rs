@@ -0,0 +1,177 @@
+use fnv::FnvHashMap;
+use rand::{self, SeedableRng};
+use std::panic::{self, AssertUnwindSafe};
+
+use game::*;
+use strategy::*;
+
+// Replays a recorded game with a strategy, to see the game through the strategy's eyes:
+// what its conventions say everyone knows about their own hand after each turn,
+// and which of the recorded moves the strategy would not have made.
+
+// What the strategy believes is common knowledge about one card
+#[derive(Debug,Clone)]
+pub struct CardKnowledge {
+    // the card actually held
+    pub card: Card,
+    // the possible cards and their probabilities, most likely first
+    pub probabilities: Vec<(Card, f32)>,
+}
+impl CardKnowledge {
+    pub fn probability_of_actual(&self) -> f32 {
+        self.probabilities.iter().find(|&&(ref card, _)| *card == self.card).map_or(0.0, |&(_, p)| p)
+    }
+}
+
+#[derive(Debug,Clone)]
+pub struct TurnReport {
+    pub turn: u32,
+    pub player: Player,
+    // the move made in the recorded game
+    pub choice: TurnChoice,
+    // the move the strategy would have made in its place
+    pub expected: TurnChoice,
+    // for each player, knowledge of their hand after the turn,
+    // if the strategy keeps track of common knowledge
+    pub knowledge: Vec<Option<Vec<CardKnowledge>>>,
+}
+impl TurnReport {
+    pub fn is_consistent(&self) -> bool {
+        self.choice == self.expected
+    }
+}
+
+pub struct Investigation {
+    pub turns: Vec<TurnReport>,
+    // set if the strategy couldn't make sense of a turn, and the analysis stopped there
+    pub stopped_at: Option<(u32, String)>,
+}
+impl Investigation {
+    // the first recorded move the strategy wouldn't have made, e.g. to check that a change to a
+    // strategy still makes the moves recorded from an earlier version of it
+    pub fn first_inconsistency(&self) -> Option<&TurnReport> {
+        self.turns.iter().find(|report| !report.is_consistent())
+    }
+}
+
+fn panic_message(err: Box<::std::any::Any + Send>) -> String {
+    if let Some(message) = err.downcast_ref::<String>() {
+        message.clone()
+    } else if let Some(message) = err.downcast_ref::<&str>() {
+        message.to_string()
+    } else {
+        String::from("unknown panic")
+    }
+}
+
+// Plays through `choices` on `deck`, asking the strategy what it would do before each recorded move.
+// Strategies assume everyone follows their conventions, so after an inconsistent move a strategy
+// may panic trying to interpret it; the analysis stops at that turn instead of failing outright.
+pub fn investigate(
+        opts: &GameOptions,
+        game_strategy: Box<GameStrategy>,
+        seed: u32,
+        deck: Cards,
+        choices: &[TurnChoice],
+    ) -> Investigation {
+    let mut game = GameState::new(opts, deck);
+    let mut strategies = game.get_players().map(|player| {
+        let rng = rand::ChaChaRng::from_seed(&[seed, player]);
+        let view = game.get_view(player);
+        (player, game_strategy.initialize(&InitContext::new(&*game_strategy, opts, &view).with_deck(&game.deck), rng))
+    }).collect::<FnvHashMap<Player, Box<PlayerStrategy>>>();
+
+    let mut turns = Vec::new();
+    for choice in choices {
+        assert!(!game.is_over(), "Recorded choices continue past the end of the game");
+        let turn_number = game.board.turn;
+        let player = game.board.player;
+
+        let expected = match panic::catch_unwind(AssertUnwindSafe(|| {
+            strategies.get_mut(&player).unwrap().decide(&game.get_view(player))
+        })) {
+            Ok(expected) => expected,
+            Err(err) => {
+                return Investigation {
+                    turns: turns,
+                    stopped_at: Some((turn_number, panic_message(err))),
+                };
+            }
+        };
+        let turn = game.process_choice(choice.clone());
+        let updated = panic::catch_unwind(AssertUnwindSafe(|| {
+            for player in game.get_players() {
+                strategies.get_mut(&player).unwrap().update(&turn, &game.get_view(player));
+            }
+        }));
+        if let Err(err) = updated {
+            // still report the move, though nothing can be said about the knowledge after it
+            turns.push(TurnReport {
+                turn: turn_number,
+                player: player,
+                choice: choice.clone(),
+                expected: expected,
+                knowledge: game.get_players().map(|_| None).collect(),
+            });
+            return Investigation {
+                turns: turns,
+                stopped_at: Some((turn_number, panic_message(err))),
+            };
+        }
+
+        // each player knows the common knowledge about their own hand
+        let knowledge = game.get_players().map(|holder| {
+            strategies[&holder].public_card_probabilities(holder).map(|hand| {
+                hand.into_iter().zip(game.hands[&holder].iter()).map(|(mut probabilities, card)| {
+                    probabilities.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+                    CardKnowledge { card: card.clone(), probabilities: probabilities }
+                }).collect()
+            })
+        }).collect();
+
+        turns.push(TurnReport {
+            turn: turn_number,
+            player: player,
+            choice: choice.clone(),
+            expected: expected,
+            knowledge: knowledge,
+        });
+    }
+    Investigation {
+        turns: turns,
+        stopped_at: None,
+    }
+}
+
+// Logs the investigation: every turn, with inconsistent moves flagged,
+// and the most likely possibilities for each card
+pub fn log_investigation(investigation: &Investigation, max_possibilities: usize) {
+    for report in &investigation.turns {
+        if report.is_consistent() {
+            info!("Turn {}, player {}: {:?}", report.turn, report.player, report.choice);
+        } else {
+            info!("Turn {}, player {}: {:?}  ** INCONSISTENT: the strategy would have made {:?}",
+                  report.turn, report.player, report.choice, report.expected);
+        }
+        for (holder, hand) in report.knowledge.iter().enumerate() {
+            let hand = match *hand {
+                Some(ref hand) => hand,
+                None => continue,
+            };
+            for (index, knowledge) in hand.iter().enumerate() {
+                let possibilities = knowledge.probabilities.iter().take(max_possibilities).map(|&(ref card, p)| {
+                    format!("{} {:.0}%", card, p * 100.0)
+                }).collect::<Vec<_>>();
+                info!("  Player {} card {} (actually {}, {:.0}% likely): {}{}",
+                      holder, index, knowledge.card, knowledge.probability_of_actual() * 100.0,
+                      possibilities.join(", "),
+                      if knowledge.probabilities.len() > max_possibilities { ", ..." } else { "" });
+            }
+        }
+    }
+    let inconsistent = investigation.turns.iter().filter(|report| !report.is_consistent()).count();
+    info!("{} of {} turns were inconsistent with the strategy", inconsistent, investigation.turns.len());
+    if let Some((turn, ref message)) = investigation.stopped_at {
+        warn!("Stopped at turn {}, where the strategy couldn't follow the game: {}", turn, message);
+    }
+}
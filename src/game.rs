@@ -1,6 +1,10 @@
 use fnv::FnvHashMap;
+use std::cmp;
 use std::fmt;
 use std::ops::Range;
+use std::str::FromStr;
+
+use render::{styled, RenderStyle};
 
 pub type Player = u32;
 
@@ -23,6 +27,11 @@ pub fn get_count_for_value(value: Value) -> u32 {
     }
 }
 
+// number of cards in a full deck
+pub fn get_total_cards() -> u32 {
+    VALUES.iter().map(|&value| get_count_for_value(value)).sum::<u32>() * NUM_COLORS as u32
+}
+
 #[derive(Clone,PartialEq,Eq,Hash,Ord,PartialOrd)]
 pub struct Card {
     pub color: Color,
@@ -38,6 +47,19 @@ impl fmt::Display for Card {
         write!(f, "{}{}", self.color, self.value)
     }
 }
+// parses cards as they are displayed, e.g. "r1"
+impl FromStr for Card {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Card, String> {
+        let mut chars = s.chars();
+        let color = chars.next().filter(|color| COLORS.contains(color));
+        let value = chars.as_str().parse::<Value>().ok().filter(|value| VALUES.contains(value));
+        match (color, value) {
+            (Some(color), Some(value)) => Ok(Card::new(color, value)),
+            _ => Err(format!("Invalid card: {}", s)),
+        }
+    }
+}
 impl fmt::Debug for Card {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}{}", self.color, self.value)
@@ -74,6 +96,12 @@ impl CardCounts {
         let count = self.counts.get_mut(card).unwrap();
         *count += 1;
     }
+
+    pub fn decrement(&mut self, card: &Card) {
+        let count = self.counts.get_mut(card).unwrap();
+        assert!(*count > 0, "Tried to remove a {} that wasn't counted", card);
+        *count -= 1;
+    }
 }
 impl fmt::Display for CardCounts {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -103,15 +131,24 @@ pub type Cards = Vec<Card>;
 pub struct Discard {
     pub cards: Cards,
     counts: CardCounts,
+    // for each color, one less than the lowest value with every copy discarded.
+    // updated as cards are placed, so it doesn't need recomputing
+    highest_attainable: FnvHashMap<Color, Value>,
 }
 impl Discard {
     pub fn new() -> Discard {
         Discard {
             cards: Cards::new(),
             counts: CardCounts::new(),
+            highest_attainable: COLORS.iter().map(|&color| (color, FINAL_VALUE)).collect(),
         }
     }
 
+    // the highest value that can still be played for a color, ignoring the fireworks
+    pub fn highest_attainable(&self, color: Color) -> Value {
+        self.highest_attainable[&color]
+    }
+
     pub fn has_all(&self, card: &Card) -> bool {
         self.counts.remaining(card) == 0
     }
@@ -122,8 +159,25 @@ impl Discard {
 
     pub fn place(&mut self, card: Card) {
         self.counts.increment(&card);
+        if self.has_all(&card) {
+            let highest = self.highest_attainable.get_mut(&card.color).unwrap();
+            if card.value - 1 < *highest {
+                *highest = card.value - 1;
+            }
+        }
         self.cards.push(card);
     }
+
+    // takes back the most recently placed card
+    pub fn take_last(&mut self) -> Card {
+        let card = self.cards.pop().expect("Tried to take a card from an empty discard");
+        self.counts.decrement(&card);
+        let counts = &self.counts;
+        let highest = VALUES.iter().find(|&&value| counts.remaining(&Card::new(card.color, value)) == 0)
+            .map_or(FINAL_VALUE, |&value| value - 1);
+        self.highest_attainable.insert(card.color, highest);
+        card
+    }
 }
 impl fmt::Display for Discard {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -173,6 +227,13 @@ impl Firework {
         );
         self.top = card.value;
     }
+
+    // takes back the card on top, which must be `card`
+    pub fn unplace(&mut self, card: &Card) {
+        assert!(card.color == self.color && card.value == self.top,
+                "Attempted to take {} off the top of the {} firework", card, self.color);
+        self.top -= 1;
+    }
 }
 impl fmt::Display for Firework {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -189,6 +250,21 @@ pub enum Hinted {
     Color(Color),
     Value(Value),
 }
+impl Hinted {
+    // every hint that can be given, colors first
+    pub fn all() -> impl Iterator<Item=Hinted> {
+        COLORS.iter().map(|&color| Hinted::Color(color))
+            .chain(VALUES.iter().map(|&value| Hinted::Value(value)))
+    }
+
+    // whether a hint of this would touch the card
+    pub fn matches(&self, card: &Card) -> bool {
+        match *self {
+            Hinted::Color(color) => card.color == color,
+            Hinted::Value(value) => card.value == value,
+        }
+    }
+}
 impl fmt::Display for Hinted {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -220,25 +296,176 @@ pub enum TurnResult {
     Play(Card, bool), // card played, whether it succeeded
 }
 
+// Identifies a card for the whole game, by the order it was dealt or drawn in:
+// the first card dealt is 0, and so on.  This is hanab.live's card "order".
+pub type CardId = u32;
+
 // represents a turn taken in the game
 #[derive(Debug,Clone,Eq,PartialEq)]
 pub struct TurnRecord {
     pub player: Player,
     pub choice: TurnChoice,
     pub result: TurnResult,
+    // the card played or discarded, if any
+    pub card_id: Option<CardId>,
 }
 pub type TurnHistory = Vec<TurnRecord>;
 
+// The turns taken so far, for strategies that look back further than the turn they are told
+// about in PlayerStrategy::update.  Turn `i` of the game (counting from 1, as BoardState::turn
+// does) is the record at index `i - 1`.
+#[derive(Debug,Clone,Copy)]
+pub struct TurnHistoryView<'a> {
+    turns: &'a [TurnRecord],
+}
+impl <'a> TurnHistoryView<'a> {
+    pub fn new(turns: &'a [TurnRecord]) -> TurnHistoryView<'a> {
+        TurnHistoryView { turns: turns }
+    }
+
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    pub fn iter(&self) -> ::std::slice::Iter<'a, TurnRecord> {
+        self.turns.iter()
+    }
+
+    // the record of turn `turn`, if it has been taken
+    pub fn turn(&self, turn: u32) -> Option<&'a TurnRecord> {
+        if turn == 0 { None } else { self.turns.get(turn as usize - 1) }
+    }
+
+    // the most recent turn
+    pub fn last(&self) -> Option<&'a TurnRecord> {
+        self.turns.last()
+    }
+
+    // the turns from turn `turn` on, e.g. those since a strategy last looked
+    pub fn since(&self, turn: u32) -> &'a [TurnRecord] {
+        let start = cmp::min(cmp::max(turn, 1) as usize - 1, self.turns.len());
+        &self.turns[start..]
+    }
+
+    // the turns `player` took
+    pub fn by_player(&self, player: Player) -> impl Iterator<Item=&'a TurnRecord> + 'a {
+        self.turns.iter().filter(move |turn| turn.player == player)
+    }
+
+    pub fn as_slice(&self) -> &'a [TurnRecord] {
+        self.turns
+    }
+}
+
+// what happens once the last card has been drawn
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub enum EndgameRule {
+    // every player gets one final turn, as in the official rules
+    FinalRound,
+    // play continues until no card in anyone's hand is playable,
+    // or until the player to move has no cards left and no hints to give
+    UntilStuck,
+}
+
+// see BoardState::pace
+pub fn pace(score: Score, deck_size: u32, num_players: u32, max_attainable_score: Score) -> i32 {
+    (score + deck_size + num_players) as i32 - max_attainable_score as i32
+}
+
+// what happens to a card that is played but isn't playable
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub enum MisplayRule {
+    // it goes to the discard pile, as in the official rules
+    Discard,
+    // it is shuffled back into the bottom of the deck, to be drawn again last.  So a misplay
+    // costs a life, but never a card.  If the deck was empty, the misplayer draws it right back
+    ReturnToDeckBottom,
+}
+
+// what the simulator does when a strategy makes a move the rules don't allow
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub enum IllegalMovePolicy {
+    // stop the whole run, to debug the strategy
+    Panic,
+    // end the game with a score of 0, and carry on with the next one
+    Forfeit,
+    // ask the player to decide again, a few times, before forfeiting.
+    // A player that makes an illegal move it already tried forfeits at once
+    Retry,
+}
+
+// a move the rules don't allow, and why
+#[derive(Debug,Clone)]
+pub struct IllegalMove {
+    pub player: Player,
+    pub choice: TurnChoice,
+    pub reason: String,
+}
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Player {} made an illegal move, {:?}: {}", self.player, self.choice, self.reason)
+    }
+}
+
+// why a game ended
+#[derive(Debug,Clone,Copy,Eq,PartialEq,Hash,PartialOrd,Ord)]
+pub enum EndCause {
+    PerfectScore,
+    StruckOut,
+    // the final round after the deck ran out was played
+    DeckExhausted,
+    // under EndgameRule::UntilStuck, nothing more could be played
+    Stuck,
+    // with GameOptions::end_when_settled, the score could no longer improve
+    Settled,
+    // a player made an illegal move, under IllegalMovePolicy::Forfeit or Retry
+    Forfeited,
+    // a player gave up on the game (see PlayerStrategy::concede)
+    Conceded,
+}
+impl fmt::Display for EndCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            EndCause::PerfectScore => "perfect score",
+            EndCause::StruckOut => "struck out",
+            EndCause::DeckExhausted => "deck exhausted",
+            EndCause::Stuck => "stuck",
+            EndCause::Settled => "score settled",
+            EndCause::Forfeited => "illegal move",
+            EndCause::Conceded => "conceded",
+        })
+    }
+}
+
 // represents possible settings for the game
 pub struct GameOptions {
     pub num_players: u32,
     pub hand_size: u32,
     // when hits 0, you cannot hint
     pub num_hints: u32,
+    // how many hints there are at the start of the game, at most num_hints
+    pub starting_hints: u32,
+    // how many hints are regained by discarding, and by completing a firework,
+    // in halves of a hint.  The standard rules regain one hint (2 halves) for each
+    pub discard_half_hints: u32,
+    pub firework_half_hints: u32,
     // when hits 0, you lose
     pub num_lives: u32,
     // whether to allow hints that reveal no cards
     pub allow_empty_hints: bool,
+    // what happens once the deck runs out
+    pub endgame_rule: EndgameRule,
+    // what happens to misplayed cards
+    pub misplay_rule: MisplayRule,
+    // whether losing the last life also loses all points
+    pub strikeout_scores_zero: bool,
+    // end the game as soon as the score can't improve, rather than playing out the deck
+    pub end_when_settled: bool,
+    pub illegal_moves: IllegalMovePolicy,
 }
 
 // State of everything except the player's hands
@@ -254,21 +481,49 @@ pub struct BoardState {
 
     // which turn is it?
     pub turn: u32,
-    pub turn_history: TurnHistory,
     // // whose turn is it?
     pub player: Player,
     pub hand_size: u32,
+    // the id of the card in each slot of each hand.  Everyone knows these, even for their own hand
+    pub card_ids: FnvHashMap<Player, Vec<CardId>>,
+    // the id the next card drawn will have
+    pub next_card_id: CardId,
 
     pub hints_total: u32,
     pub hints_remaining: u32,
+    // how many hints the game started with
+    pub starting_hints: u32,
+    // whether half a hint has been regained on top of hints_remaining.
+    // It can't be used until it becomes a whole hint
+    pub half_hint: bool,
+    pub discard_half_hints: u32,
+    pub firework_half_hints: u32,
     pub allow_empty_hints: bool,
     pub lives_total: u32,
     pub lives_remaining: u32,
     // only relevant when deck runs out
     pub deckless_turns_remaining: u32,
+    pub endgame_rule: EndgameRule,
+    pub misplay_rule: MisplayRule,
+    // whether the game has ended under EndgameRule::UntilStuck
+    pub stuck: bool,
+    pub strikeout_scores_zero: bool,
+    pub end_when_settled: bool,
+    // whether the game has ended under GameOptions::end_when_settled
+    pub settled: bool,
+    pub illegal_moves: IllegalMovePolicy,
+    // whether the game has ended with a score of 0 because of an illegal move
+    pub forfeited: bool,
+    // whether a player has ended the game early, keeping its score
+    pub conceded: bool,
+    // how much max_attainable_score has dropped because of cards discarded or misplayed,
+    // i.e. the points given up by the players rather than by running out of time
+    pub discard_loss: Score,
 }
 impl BoardState {
     pub fn new(opts: &GameOptions, deck_size: u32) -> BoardState {
+        assert!(opts.starting_hints <= opts.num_hints,
+                "Can't start with {} hints, out of {}", opts.starting_hints, opts.num_hints);
         let fireworks = COLORS.iter().map(|&color| {
             (color, Firework::new(color))
         }).collect::<FnvHashMap<_, _>>();
@@ -280,25 +535,63 @@ impl BoardState {
             discard: Discard::new(),
             num_players: opts.num_players,
             hand_size: opts.hand_size,
+            card_ids: FnvHashMap::default(),
+            next_card_id: 0,
             player: 0,
             turn: 1,
             allow_empty_hints: opts.allow_empty_hints,
             hints_total: opts.num_hints,
-            hints_remaining: opts.num_hints,
+            hints_remaining: opts.starting_hints,
+            starting_hints: opts.starting_hints,
+            half_hint: false,
+            discard_half_hints: opts.discard_half_hints,
+            firework_half_hints: opts.firework_half_hints,
             lives_total: opts.num_lives,
             lives_remaining: opts.num_lives,
-            turn_history: Vec::new(),
             // number of turns to play with deck length ran out
             deckless_turns_remaining: opts.num_players + 1,
+            endgame_rule: opts.endgame_rule,
+            misplay_rule: opts.misplay_rule,
+            stuck: false,
+            strikeout_scores_zero: opts.strikeout_scores_zero,
+            end_when_settled: opts.end_when_settled,
+            settled: false,
+            illegal_moves: opts.illegal_moves,
+            forfeited: false,
+            conceded: false,
+            discard_loss: 0,
         }
     }
 
-    fn try_add_hint(&mut self) {
-        if self.hints_remaining < self.hints_total {
-            self.hints_remaining += 1;
+    // the options the game was started with
+    pub fn options(&self) -> GameOptions {
+        GameOptions {
+            num_players: self.num_players,
+            hand_size: self.hand_size,
+            num_hints: self.hints_total,
+            starting_hints: self.starting_hints,
+            discard_half_hints: self.discard_half_hints,
+            firework_half_hints: self.firework_half_hints,
+            num_lives: self.lives_total,
+            allow_empty_hints: self.allow_empty_hints,
+            endgame_rule: self.endgame_rule,
+            misplay_rule: self.misplay_rule,
+            strikeout_scores_zero: self.strikeout_scores_zero,
+            end_when_settled: self.end_when_settled,
+            illegal_moves: self.illegal_moves,
         }
     }
 
+    // regains some halves of a hint, up to the maximum number of hints
+    fn try_add_hint(&mut self, half_hints: u32) {
+        let halves = cmp::min(
+            self.hints_remaining * 2 + self.half_hint as u32 + half_hints,
+            self.hints_total * 2
+        );
+        self.hints_remaining = halves / 2;
+        self.half_hint = halves % 2 == 1;
+    }
+
     pub fn get_firework(&self, color: Color) -> &Firework {
         self.fireworks.get(&color).unwrap()
     }
@@ -314,25 +607,61 @@ impl BoardState {
 
     // best possible value we can get for firework of that color,
     // based on looking at discard + fireworks
-    fn highest_attainable(&self, color: Color) -> Value {
+    pub fn highest_attainable(&self, color: Color) -> Value {
         let firework = self.fireworks.get(&color).unwrap();
         if firework.complete() {
             return FINAL_VALUE;
         }
-        let needed = firework.needed_value().unwrap();
+        // cards below the firework have been played, so they can't all be in the discard
+        self.discard.highest_attainable(color)
+    }
 
-        for &value in VALUES.iter() {
-            if value < needed {
-                // already have these cards
-                continue
-            }
-            let needed_card = Card::new(color, value);
-            if self.discard.has_all(&needed_card) {
-                // already discarded all of these
-                return value - 1;
-            }
-        }
-        return FINAL_VALUE;
+    // values that still need to be played on a firework, and still can be
+    pub fn useful_cards_remaining(&self, color: Color) -> Vec<Value> {
+        let top = self.get_firework(color).top;
+        (top + 1 .. self.highest_attainable(color) + 1).collect()
+    }
+
+    // the score if every card that can still be played is played
+    pub fn max_attainable_score(&self) -> Score {
+        COLORS.iter().map(|&color| self.highest_attainable(color)).sum()
+    }
+
+    // the cards played so far, which is the score unless GameOptions::strikeout_scores_zero applies
+    pub fn cards_played(&self) -> Score {
+        self.fireworks.values().map(|firework| firework.score()).sum()
+    }
+
+    // how many cards must still be played to reach the max attainable score
+    pub fn cards_left_to_play(&self) -> Score {
+        self.max_attainable_score() - self.cards_played()
+    }
+
+    // how many more cards can be discarded before the deck runs out too soon to play every card
+    // that's still attainable, with one final turn each.  Below 0, the max attainable score can't
+    // be reached (except under EndgameRule::UntilStuck)
+    pub fn pace(&self) -> i32 {
+        pace(self.cards_played(), self.deck_size, self.num_players, self.max_attainable_score())
+    }
+
+    // how many cards can be discarded in a game of these options, if every card is eventually
+    // played, before the last card is drawn
+    pub fn spare_discards(&self) -> u32 {
+        self.total_cards
+            .saturating_sub(PERFECT_SCORE)
+            .saturating_sub(self.num_players * self.hand_size)
+    }
+
+    // whether the deck has run out, and each player has one last turn
+    pub fn is_final_round(&self) -> bool {
+        self.endgame_rule == EndgameRule::FinalRound && self.deck_size == 0
+    }
+
+    // puts a discarded or misplayed card in the discard, counting any points lost
+    fn place_in_discard(&mut self, card: Card) {
+        let before = self.max_attainable_score();
+        self.discard.place(card);
+        self.discard_loss += before - self.max_attainable_score();
     }
 
     // is never going to play, based on discard + fireworks
@@ -369,11 +698,71 @@ impl BoardState {
         }
     }
 
+    // how much the max attainable score would drop if this card were discarded: nothing unless it's
+    // the last copy of a card that can still be played, and then it and every card above it
+    pub fn score_lost_by_discarding(&self, card: &Card) -> Score {
+        if self.is_dispensable(card) {
+            0
+        } else {
+            self.highest_attainable(card.color) - card.value + 1
+        }
+    }
+
     pub fn get_players(&self) -> Range<Player> {
         (0..self.num_players)
     }
 
+    // Every choice the player whose view this is could make, if it were their turn:
+    // playing or discarding any of their cards, and, if there is a hint left, any hint to
+    // another player that touches one of their cards (or any hint at all, if empty hints
+    // are allowed).  Discarding is always allowed, even with every hint still available.
+    pub fn legal_choices<'a, V: GameView>(&'a self, view: &'a V) -> impl Iterator<Item=TurnChoice> + 'a {
+        let me = view.me();
+        let cards = (0..view.my_hand_size()).flat_map(|index| {
+            vec![TurnChoice::Play(index), TurnChoice::Discard(index)]
+        });
+        let hint_targets = self.get_players().filter(move |&player| player != me && self.hints_remaining > 0);
+        let hints = hint_targets.flat_map(move |player| {
+            let hand = view.get_hand(&player);
+            Hinted::all().filter(move |hinted| {
+                self.allow_empty_hints || hand.iter().any(|card| hinted.matches(card))
+            }).map(move |hinted| TurnChoice::Hint(Hint { player: player, hinted: hinted }))
+        });
+        cards.chain(hints)
+    }
+
+    // Whether the player whose view this is could make this choice (as in legal_choices),
+    // and if not, why
+    pub fn check_choice<V: GameView>(&self, view: &V, choice: &TurnChoice) -> Result<(), String> {
+        match *choice {
+            TurnChoice::Play(index) | TurnChoice::Discard(index) => {
+                if index >= view.my_hand_size() {
+                    return Err(format!("chose card {} of a hand of {}", index, view.my_hand_size()));
+                }
+            }
+            TurnChoice::Hint(ref hint) => {
+                if self.hints_remaining == 0 {
+                    return Err(String::from("hinted with no hints remaining"));
+                }
+                if hint.player == view.me() {
+                    return Err(String::from("hinted themselves"));
+                }
+                if hint.player >= self.num_players {
+                    return Err(format!("hinted nonexistent player {}", hint.player));
+                }
+                let touches = view.get_hand(&hint.player).iter().any(|card| hint.hinted.matches(card));
+                if !touches && !self.allow_empty_hints {
+                    return Err(format!("gave hint {} matching no cards", hint.hinted));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn score(&self) -> Score {
+        if self.forfeited || (self.strikeout_scores_zero && self.lives_remaining == 0) {
+            return 0;
+        }
         self.fireworks.iter().map(|(_, firework)| firework.score()).fold(0, |a, b| a + b)
     }
 
@@ -381,6 +770,22 @@ impl BoardState {
         self.discard.cards.len() as u32
     }
 
+    pub fn card_ids(&self, player: &Player) -> &Vec<CardId> {
+        self.card_ids.get(player).unwrap()
+    }
+
+    // which slot of a player's hand holds a card, if they still hold it
+    pub fn slot_of(&self, player: &Player, card_id: CardId) -> Option<usize> {
+        self.card_ids(player).iter().position(|&id| id == card_id)
+    }
+
+    // gives a player's hand ids for the cards dealt to it, in slot order
+    pub fn deal_card_ids(&mut self, player: Player, num_cards: u32) {
+        let ids = (self.next_card_id .. self.next_card_id + num_cards).collect();
+        self.card_ids.insert(player, ids);
+        self.next_card_id += num_cards;
+    }
+
     pub fn player_to_left(&self, player: &Player) -> Player {
         (player + 1) % self.num_players
     }
@@ -389,43 +794,44 @@ impl BoardState {
     }
 
     pub fn is_over(&self) -> bool {
-        (self.lives_remaining == 0) || (self.deckless_turns_remaining == 0)
+        (self.lives_remaining == 0) || (self.deckless_turns_remaining == 0) || self.stuck || self.settled
+            || self.forfeited || self.conceded
     }
-}
-impl fmt::Display for BoardState {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.is_over() {
-            try!(f.write_str(&format!(
-                "Turn {} (GAME ENDED):\n", self.turn
-            )));
-        } else {
-            try!(f.write_str(&format!(
-                "Turn {} (Player {}'s turn):\n", self.turn, self.player
-            )));
-        }
 
-        try!(f.write_str(&format!(
-            "{} cards remaining in deck\n", self.deck_size
-        )));
-        if self.deck_size == 0 {
-            try!(f.write_str(&format!(
-                "Deck is empty.  {} turns remaining in game\n", self.deckless_turns_remaining
-            )));
-        }
-        try!(f.write_str(&format!(
-            "{}/{} hints remaining\n", self.hints_remaining, self.hints_total
-        )));
-        try!(f.write_str(&format!(
-            "{}/{} lives remaining\n", self.lives_remaining, self.lives_total
-        )));
-        try!(f.write_str("Fireworks:\n"));
-        for &color in COLORS.iter() {
-            try!(f.write_str(&format!("  {}\n", self.get_firework(color))));
+    // a lower bound on how many more turns the game would last, ignoring lost lives
+    // (and GameOptions::end_when_settled)
+    pub fn min_turns_remaining(&self) -> u32 {
+        match self.endgame_rule {
+            // the last card drawn starts the final round
+            EndgameRule::FinalRound if self.deck_size > 0 => self.deck_size + self.num_players,
+            EndgameRule::FinalRound => self.deckless_turns_remaining,
+            EndgameRule::UntilStuck => self.deck_size,
         }
-        try!(f.write_str("Discard:\n"));
-        try!(f.write_str(&format!("{}\n", self.discard)));
+    }
 
-        Ok(())
+    pub fn end_cause(&self) -> Option<EndCause> {
+        if !self.is_over() {
+            None
+        } else if self.forfeited {
+            Some(EndCause::Forfeited)
+        } else if self.lives_remaining == 0 {
+            Some(EndCause::StruckOut)
+        } else if self.score() == PERFECT_SCORE {
+            Some(EndCause::PerfectScore)
+        } else if self.conceded {
+            Some(EndCause::Conceded)
+        } else if self.stuck {
+            Some(EndCause::Stuck)
+        } else if self.settled {
+            Some(EndCause::Settled)
+        } else {
+            Some(EndCause::DeckExhausted)
+        }
+    }
+}
+impl fmt::Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", styled(self, RenderStyle::Plain))
     }
 }
 
@@ -437,6 +843,12 @@ pub trait GameView {
 
     fn my_hand_size(&self) -> usize;
 
+    // the ids of the cards in a player's hand, in slot order.  Unlike the cards themselves,
+    // these are known for every hand, including your own
+    fn card_ids(&self, player: &Player) -> &Vec<CardId> {
+        self.get_board().card_ids(player)
+    }
+
     fn hand_size(&self, player: &Player) -> usize {
         if self.me() == *player {
             self.my_hand_size()
@@ -482,6 +894,8 @@ pub struct BorrowedGameView<'a> {
     pub other_hands: FnvHashMap<Player, &'a Cards>,
     // board state
     pub board: &'a BoardState,
+    // the turns taken so far
+    pub history: TurnHistoryView<'a>,
 }
 impl <'a> GameView for BorrowedGameView<'a> {
     fn me(&self) -> Player {
@@ -541,43 +955,55 @@ impl GameView for OwnedGameView {
     }
 }
 
-// complete game state (known to nobody!)
+// What GameState::undo needs to take back a turn: the counters that can't be worked
+// out from the turn record alone.  The turn record itself stays at the end of the history
+// until the turn is undone.
 #[derive(Debug)]
+pub struct Undo {
+    // the turn this undoes
+    turn: u32,
+    hints_remaining: u32,
+    half_hint: bool,
+    lives_remaining: u32,
+    deckless_turns_remaining: u32,
+    stuck: bool,
+    settled: bool,
+    discard_loss: Score,
+    // whether the player drew a card after playing or discarding
+    drew: bool,
+}
+
+// complete game state (known to nobody!)
+#[derive(Debug,Clone,PartialEq)]
 pub struct GameState {
     pub hands: FnvHashMap<Player, Cards>,
     pub board: BoardState,
     pub deck: Cards,
+    // every turn taken so far.  Kept out of the board, which strategies copy
+    pub turn_history: TurnHistory,
+    // once the game is over, the totals over the players of what their strategies measured
+    // (see PlayerStrategy::metrics)
+    pub strategy_metrics: FnvHashMap<String, f64>,
 }
 impl fmt::Display for GameState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(f.write_str("\n"));
-        try!(f.write_str("======\n"));
-        try!(f.write_str("Hands:\n"));
-        try!(f.write_str("======\n"));
-        for player in self.board.get_players() {
-            let hand = &self.hands.get(&player).unwrap();
-            try!(f.write_str(&format!("player {}:", player)));
-            for card in hand.iter() {
-                try!(f.write_str(&format!("    {}", card)));
-            }
-            try!(f.write_str(&"\n"));
-        }
-        try!(f.write_str("======\n"));
-        try!(f.write_str("Board:\n"));
-        try!(f.write_str("======\n"));
-        try!(f.write_str(&format!("{}", self.board)));
-        Ok(())
+        write!(f, "{}", styled(self, RenderStyle::Plain))
     }
 }
 
 impl GameState {
     pub fn new(opts: &GameOptions, mut deck: Cards) -> GameState {
+        assert!((opts.num_players * opts.hand_size) as usize <= deck.len(),
+                "Not enough cards to deal {} hands of {}", opts.num_players, opts.hand_size);
         let mut board = BoardState::new(opts, deck.len() as u32);
+        for player in 0..opts.num_players {
+            board.deal_card_ids(player, opts.hand_size);
+        }
 
         let hands =
             (0..opts.num_players).map(|player| {
                 let hand = (0..opts.hand_size).map(|_| {
-                    // we can assume the deck is big enough to draw initial hands
+                    // we checked the deck is big enough to draw initial hands
                     board.deck_size -= 1;
                     deck.pop().unwrap()
                 }).collect::<Vec<_>>();
@@ -588,6 +1014,8 @@ impl GameState {
             hands: hands,
             board: board,
             deck: deck,
+            turn_history: Vec::new(),
+            strategy_metrics: FnvHashMap::default(),
         }
     }
 
@@ -616,13 +1044,15 @@ impl GameState {
             hand_size: self.hands.get(&player).unwrap().len(),
             other_hands: other_hands,
             board: &self.board,
+            history: TurnHistoryView::new(&self.turn_history),
         }
     }
 
     // takes a card from the player's hand, and replaces it if possible
-    fn take_from_hand(&mut self, index: usize) -> Card {
+    fn take_from_hand(&mut self, index: usize) -> (Card, CardId) {
         let ref mut hand = self.hands.get_mut(&self.board.player).unwrap();
-        hand.remove(index)
+        let card_id = self.board.card_ids.get_mut(&self.board.player).unwrap().remove(index);
+        (hand.remove(index), card_id)
     }
 
     fn replenish_hand(&mut self) {
@@ -632,11 +1062,37 @@ impl GameState {
                 self.board.deck_size -= 1;
                 debug!("Drew new card, {}", new_card);
                 hand.push(new_card);
+                self.board.card_ids.get_mut(&self.board.player).unwrap().push(self.board.next_card_id);
+                self.board.next_card_id += 1;
             }
         }
     }
 
+    // Like process_choice, but refuses an illegal move instead of panicking, leaving the game as it was
+    pub fn try_process_choice(&mut self, choice: TurnChoice) -> Result<TurnRecord, IllegalMove> {
+        let player = self.board.player;
+        if let Err(reason) = self.board.check_choice(&self.get_view(player), &choice) {
+            return Err(IllegalMove {
+                player: player,
+                choice: choice,
+                reason: reason,
+            });
+        }
+        Ok(self.process_choice(choice))
+    }
+
+    // ends the game with a score of 0, e.g. after an illegal move
+    pub fn forfeit(&mut self) {
+        self.board.forfeited = true;
+    }
+
+    // ends the game with the score it has now
+    pub fn concede(&mut self) {
+        self.board.conceded = true;
+    }
+
     pub fn process_choice(&mut self, choice: TurnChoice) -> TurnRecord {
+        let mut turn_card_id = None;
         let turn_result = {
             match choice {
                 TurnChoice::Hint(ref hint) => {
@@ -649,14 +1105,7 @@ impl GameState {
                             format!("Player {} gave a hint to himself", hint.player));
 
                     let hand = self.hands.get(&hint.player).unwrap();
-                    let results = match hint.hinted {
-                        Hinted::Color(color) => {
-                            hand.iter().map(|card| { card.color == color }).collect::<Vec<_>>()
-                        }
-                        Hinted::Value(value) => {
-                            hand.iter().map(|card| { card.value == value }).collect::<Vec<_>>()
-                        }
-                    };
+                    let results = hand.iter().map(|card| hint.hinted.matches(card)).collect::<Vec<_>>();
                     if !self.board.allow_empty_hints {
                         assert!(results.iter().any(|matched| *matched),
                                 "Tried hinting an empty hint");
@@ -665,15 +1114,18 @@ impl GameState {
                     TurnResult::Hint(results)
                 }
                 TurnChoice::Discard(index) => {
-                    let card = self.take_from_hand(index);
+                    let (card, card_id) = self.take_from_hand(index);
+                    turn_card_id = Some(card_id);
                     debug!("Discard card in position {}, which is {}", index, card);
-                    self.board.discard.place(card.clone());
+                    self.board.place_in_discard(card.clone());
 
-                    self.board.try_add_hint();
+                    let half_hints = self.board.discard_half_hints;
+                    self.board.try_add_hint(half_hints);
                     TurnResult::Discard(card)
                 }
                 TurnChoice::Play(index) => {
-                    let card = self.take_from_hand(index);
+                    let (card, card_id) = self.take_from_hand(index);
+                    turn_card_id = Some(card_id);
 
                     debug!(
                         "Playing card at position {}, which is {}",
@@ -688,10 +1140,18 @@ impl GameState {
                         }
                         if card.value == FINAL_VALUE {
                             debug!("Firework complete for {}!", card.color);
-                            self.board.try_add_hint();
+                            let half_hints = self.board.firework_half_hints;
+                            self.board.try_add_hint(half_hints);
                         }
                     } else {
-                        self.board.discard.place(card.clone());
+                        match self.board.misplay_rule {
+                            MisplayRule::Discard => self.board.place_in_discard(card.clone()),
+                            MisplayRule::ReturnToDeckBottom => {
+                                debug!("Returning {} to the bottom of the deck", card);
+                                self.deck.insert(0, card.clone());
+                                self.board.deck_size += 1;
+                            }
+                        }
                         self.board.lives_remaining -= 1;
                         debug!(
                             "Removing a life! Lives remaining: {}",
@@ -706,12 +1166,13 @@ impl GameState {
             player: self.board.player.clone(),
             result: turn_result,
             choice: choice,
+            card_id: turn_card_id,
         };
-        self.board.turn_history.push(turn_record.clone());
+        self.turn_history.push(turn_record.clone());
 
         self.replenish_hand();
 
-        if self.board.deck_size == 0 {
+        if self.board.is_final_round() {
             self.board.deckless_turns_remaining -= 1;
         }
         self.board.turn += 1;
@@ -721,6 +1182,93 @@ impl GameState {
         };
         assert_eq!((self.board.turn - 1) % self.board.num_players, self.board.player);
 
+        if self.board.deck_size == 0 && self.board.endgame_rule == EndgameRule::UntilStuck {
+            let board = &self.board;
+            let any_playable = self.hands.values().any(|hand| {
+                hand.iter().any(|card| board.is_playable(card))
+            });
+            // with a playable card in someone else's hand, there's always a hint to give
+            let can_move = !self.hands.get(&board.player).unwrap().is_empty() || board.hints_remaining > 0;
+            self.board.stuck = !any_playable || !can_move;
+        }
+
+        if self.board.end_when_settled && self.board.score() == self.board.max_attainable_score() {
+            self.board.settled = true;
+        }
+
         turn_record
     }
+
+    // Makes a choice in a way that can be taken back with `undo`, e.g. to explore moves
+    // without cloning the game (and its whole history) for each one.
+    // Turns must be undone in the reverse of the order they were applied.
+    pub fn apply(&mut self, choice: &TurnChoice) -> Undo {
+        let board = &self.board;
+        let mut undo = Undo {
+            turn: board.turn,
+            hints_remaining: board.hints_remaining,
+            half_hint: board.half_hint,
+            lives_remaining: board.lives_remaining,
+            deckless_turns_remaining: board.deckless_turns_remaining,
+            stuck: board.stuck,
+            settled: board.settled,
+            discard_loss: board.discard_loss,
+            drew: false,
+        };
+        let next_card_id = board.next_card_id;
+        self.process_choice(choice.clone());
+        undo.drew = self.board.next_card_id > next_card_id;
+        undo
+    }
+
+    // Restores the game to how it was before the turn `undo` came from
+    pub fn undo(&mut self, undo: Undo) {
+        assert_eq!(self.board.turn, undo.turn + 1, "Turns must be undone in reverse order");
+        let record = self.turn_history.pop().unwrap();
+        let player = record.player;
+        if undo.drew {
+            let card = self.hands.get_mut(&player).unwrap().pop().unwrap();
+            self.board.card_ids.get_mut(&player).unwrap().pop();
+            self.board.next_card_id -= 1;
+            self.board.deck_size += 1;
+            self.deck.push(card);
+        }
+        let returned = match record.result {
+            TurnResult::Hint(_) => None,
+            TurnResult::Play(ref card, false) if self.board.misplay_rule == MisplayRule::ReturnToDeckBottom => {
+                let returned = self.deck.remove(0);
+                self.board.deck_size -= 1;
+                assert_eq!(&returned, card);
+                Some(returned)
+            }
+            TurnResult::Discard(ref card) | TurnResult::Play(ref card, false) => {
+                let discarded = self.board.discard.take_last();
+                assert_eq!(&discarded, card);
+                Some(discarded)
+            }
+            TurnResult::Play(ref card, true) => {
+                self.board.get_firework_mut(card.color).unplace(card);
+                Some(card.clone())
+            }
+        };
+        if let Some(card) = returned {
+            let index = match record.choice {
+                TurnChoice::Play(index) | TurnChoice::Discard(index) => index,
+                TurnChoice::Hint(_) => panic!("A hint can't move a card"),
+            };
+            self.hands.get_mut(&player).unwrap().insert(index, card);
+            self.board.card_ids.get_mut(&player).unwrap().insert(index, record.card_id.unwrap());
+        }
+
+        let board = &mut self.board;
+        board.turn = undo.turn;
+        board.player = player;
+        board.hints_remaining = undo.hints_remaining;
+        board.half_hint = undo.half_hint;
+        board.lives_remaining = undo.lives_remaining;
+        board.deckless_turns_remaining = undo.deckless_turns_remaining;
+        board.stuck = undo.stuck;
+        board.settled = undo.settled;
+        board.discard_loss = undo.discard_loss;
+    }
 }
@@ -1,10 +1,13 @@
+use std::cell::RefCell;
 use std::cmp::Eq;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use std::fmt;
 use std::ops::{Index,IndexMut};
-use std::hash::Hash;
+use std::panic::{self, AssertUnwindSafe};
+use std::hash::{Hash, Hasher};
 use std::convert::From;
 use std::slice;
+use std::sync::{Arc, Mutex, Once};
 
 use game::*;
 
@@ -122,6 +125,14 @@ pub trait CardInfo {
             self.mark_value_false(value);
         }
     }
+
+    // mark what a hint said about the card: whether it touched it
+    fn mark_hint(&mut self, hinted: &Hinted, touched: bool) {
+        match *hinted {
+            Hinted::Color(color) => self.mark_color(color, touched),
+            Hinted::Value(value) => self.mark_value(value, touched),
+        }
+    }
 }
 
 
@@ -243,24 +254,43 @@ impl fmt::Display for SimpleCardInfo {
     }
 }
 
+// COLORS, in sorted order
+const SORTED_COLORS: [Color; NUM_COLORS] = ['b', 'g', 'r', 'w', 'y'];
+const NUM_CARD_TYPES: usize = NUM_COLORS * NUM_VALUES;
+
+// Position of a card in a CardPossibilityTable.
+// Visiting positions in increasing order visits cards in sorted order.
+fn card_index(card: &Card) -> usize {
+    let color_index = SORTED_COLORS.iter().position(|&color| color == card.color).unwrap();
+    let value_index = VALUES.iter().position(|&value| value == card.value).unwrap();
+    color_index * NUM_VALUES + value_index
+}
+
+fn card_at_index(index: usize) -> Card {
+    Card::new(SORTED_COLORS[index / NUM_VALUES], VALUES[index % NUM_VALUES])
+}
+
 // Can represent information of the form:
 // this card is/isn't possible
 // also, maintains integer weights for the cards
-#[derive(Clone,Debug,Eq,PartialEq)]
+#[derive(Clone,Debug,Eq,PartialEq,Hash)]
 pub struct CardPossibilityTable {
-    possible: HashMap<Card, u32>,
+    // weight of each card, indexed by card_index.  0 means impossible
+    weights: [u8; NUM_CARD_TYPES],
 }
 impl CardPossibilityTable {
+    // iterate over possible cards and their weights, in sorted order
+    pub fn iter_possibilities<'a>(&'a self) -> impl Iterator<Item=(Card, u32)> + 'a {
+        self.weights.iter().enumerate().filter(|&(_, &weight)| weight > 0).map(|(index, &weight)| {
+            (card_at_index(index), weight as u32)
+        })
+    }
+
     // mark a possible card as false
     pub fn mark_false(&mut self, card: &Card) {
-        self.possible.remove(card);
+        self.weights[card_index(card)] = 0;
     }
 
-    // a bit more efficient
-    // pub fn borrow_possibilities<'a>(&'a self) -> Vec<&'a Card> {
-    //     self.possible.keys().collect::<Vec<_>>()
-    // }
-
     pub fn decrement_weight_if_possible(&mut self, card: &Card) {
         if self.is_possible(card) {
             self.decrement_weight(card);
@@ -268,67 +298,47 @@ impl CardPossibilityTable {
     }
 
     pub fn decrement_weight(&mut self, card: &Card) {
-        let remove = {
-            let weight =
-                self.possible.get_mut(card)
-                    .expect(&format!("Decrementing weight for impossible card: {}", card));
-            *weight -= 1;
-            *weight == 0
-        };
-        if remove {
-            self.possible.remove(card);
-        }
+        let weight = &mut self.weights[card_index(card)];
+        assert!(*weight > 0, "Decrementing weight for impossible card: {}", card);
+        *weight -= 1;
     }
 
     pub fn get_card(&self) -> Option<Card> {
-        let possibilities = self.get_possibilities();
-        if possibilities.len() == 1 {
-            Some(possibilities[0].clone())
-        } else {
-            None
+        let mut possibilities = self.iter_possibilities();
+        match (possibilities.next(), possibilities.next()) {
+            (Some((card, _)), None) => Some(card),
+            _ => None,
         }
     }
 
     pub fn is_determined(&self) -> bool {
-        self.get_possibilities().len() == 1
+        self.weights.iter().filter(|&&weight| weight > 0).count() == 1
     }
 
     pub fn color_determined(&self) -> bool {
-        self.get_possibilities()
-            .iter().map(|card| card.color)
-            .collect::<HashSet<_>>()
-            .len() == 1
+        SORTED_COLORS.iter().filter(|&&color| self.can_be_color(color)).count() == 1
     }
 
     pub fn value_determined(&self) -> bool {
-        self.get_possibilities()
-            .iter().map(|card| card.value)
-            .collect::<HashSet<_>>()
-            .len() == 1
+        VALUES.iter().filter(|&&value| self.can_be_value(value)).count() == 1
     }
 
     pub fn can_be_color(&self, color: Color) -> bool {
-        self.get_possibilities().into_iter().any(|card| card.color == color)
+        VALUES.iter().any(|&value| self.is_possible(&Card::new(color, value)))
     }
 
     pub fn can_be_value(&self, value: Value) -> bool {
-        self.get_possibilities().into_iter().any(|card| card.value == value)
+        COLORS.iter().any(|&color| self.is_possible(&Card::new(color, value)))
     }
 }
 impl <'a> From<&'a CardCounts> for CardPossibilityTable {
     fn from(counts: &'a CardCounts) -> CardPossibilityTable {
-        let mut possible = HashMap::new();
-        for &color in COLORS.iter() {
-            for &value in VALUES.iter() {
-                let card = Card::new(color, value);
-                let count = counts.remaining(&card);
-                if count > 0 {
-                    possible.insert(card, count);
-                }
-            }
+        let mut weights = [0; NUM_CARD_TYPES];
+        for (index, weight) in weights.iter_mut().enumerate() {
+            *weight = counts.remaining(&card_at_index(index)) as u8;
         }
         CardPossibilityTable {
-            possible: possible,
+            weights: weights,
         }
     }
 }
@@ -338,12 +348,10 @@ impl CardInfo for CardPossibilityTable {
     }
 
     fn is_possible(&self, card: &Card) -> bool {
-        self.possible.contains_key(card)
+        self.weights[card_index(card)] > 0
     }
     fn get_possibilities(&self) -> Vec<Card> {
-        let mut cards = self.possible.keys().map(|card| {card.clone() }).collect::<Vec<_>>();
-        cards.sort();
-        cards
+        self.iter_possibilities().map(|(card, _)| card).collect()
     }
     fn mark_color_false(&mut self, color: Color) {
         for &value in VALUES.iter() {
@@ -356,13 +364,38 @@ impl CardInfo for CardPossibilityTable {
             self.mark_false(&Card::new(color, value));
         }
     }
+    // rules out exactly the cards the engine wouldn't agree with, by asking Hinted::matches
+    // rather than comparing colors and values, so the two can't disagree about what a hint touches
+    fn mark_hint(&mut self, hinted: &Hinted, touched: bool) {
+        for (index, weight) in self.weights.iter_mut().enumerate() {
+            if *weight > 0 && hinted.matches(&card_at_index(index)) != touched {
+                *weight = 0;
+            }
+        }
+    }
     fn get_weight(&self, card: &Card) -> f32 {
-        *self.possible.get(card).unwrap_or(&0) as f32
+        self.weights[card_index(card)] as f32
+    }
+    fn total_weight(&self) -> f32 {
+        self.iter_possibilities().map(|(_, weight)| weight as f32).fold(0.0, |a, b| a+b)
+    }
+    fn weighted_score<T>(&self, score_fn: &Fn(&Card) -> T) -> f32
+        where f32: From<T>
+    {
+        let mut total_score = 0.;
+        let mut total_weight = 0.;
+        for (card, weight) in self.iter_possibilities() {
+            let weight = weight as f32;
+            let score = f32::from(score_fn(&card));
+            total_weight += weight;
+            total_score += weight * score;
+        }
+        total_score / total_weight
     }
 }
 impl fmt::Display for CardPossibilityTable {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for (card, weight) in &self.possible {
+        for (card, weight) in self.iter_possibilities() {
             try!(f.write_str(&format!("{} {}, ", weight, card)));
         }
         Ok(())
@@ -383,17 +416,8 @@ impl <T> HandInfo<T> where T: CardInfo {
 
     // update for hint to me
     pub fn update_for_hint(&mut self, hinted: &Hinted, matches: &Vec<bool>) {
-        match hinted {
-            &Hinted::Color(color) => {
-                for (card_info, &matched) in self.hand_info.iter_mut().zip(matches.iter()) {
-                    card_info.mark_color(color, matched);
-                }
-            }
-            &Hinted::Value(value) => {
-                for (card_info, &matched) in self.hand_info.iter_mut().zip(matches.iter()) {
-                    card_info.mark_value(value, matched);
-                }
-            }
+        for (card_info, &matched) in self.hand_info.iter_mut().zip(matches.iter()) {
+            card_info.mark_hint(hinted, matched);
         }
     }
 
@@ -414,3 +438,216 @@ impl <T> IndexMut<usize> for HandInfo<T> where T: CardInfo {
         &mut self.hand_info[index]
     }
 }
+
+// Tracks what is public knowledge about every hand, from the cards that have been played,
+// discarded, and hinted: each card's possibilities, weighted by how many copies nobody has
+// seen yet.  A strategy can get correct accounting just by calling `update` from its own
+// `PlayerStrategy::update`, and layer its conventions on top by narrowing down `hand_mut`.
+#[derive(Clone,Eq,PartialEq)]
+pub struct BeliefTracker {
+    hands: Vec<HandInfo<CardPossibilityTable>>,
+    card_counts: CardCounts, // what any newly drawn card should be
+}
+impl BeliefTracker {
+    pub fn new(board: &BoardState) -> BeliefTracker {
+        // the game may start from a position with cards already played or discarded
+        let mut card_counts = CardCounts::new();
+        for &color in COLORS.iter() {
+            for value in 1..board.get_firework(color).top + 1 {
+                card_counts.increment(&Card::new(color, value));
+            }
+        }
+        for card in board.discard.cards.iter() {
+            card_counts.increment(card);
+        }
+        let hands = board.get_players().map(|_| {
+            let mut hand = HandInfo::new(board.hand_size);
+            for card_table in hand.iter_mut() {
+                *card_table = CardPossibilityTable::from(&card_counts);
+            }
+            hand
+        }).collect();
+        BeliefTracker {
+            hands: hands,
+            card_counts: card_counts,
+        }
+    }
+
+    pub fn hand(&self, player: Player) -> &HandInfo<CardPossibilityTable> {
+        &self.hands[player as usize]
+    }
+
+    pub fn hand_mut(&mut self, player: Player) -> &mut HandInfo<CardPossibilityTable> {
+        &mut self.hands[player as usize]
+    }
+
+    // the cards everyone has seen leave the hands, by being played or discarded
+    pub fn card_counts(&self) -> &CardCounts {
+        &self.card_counts
+    }
+
+    // The probability that `player` holds at least one copy of `card`, as far as the player whose
+    // view this is can tell: certain for the hands they see, and for their own hand, judged by
+    // what everyone knows about it, less the copies they can see elsewhere.  Treats the cards in
+    // a hand as independent, so is only approximate when more than one could be the card.
+    pub fn probability_holds<V: GameView>(&self, player: Player, card: &Card, view: &V) -> f32 {
+        if player != view.me() {
+            return if view.has_card(&player, card) { 1.0 } else { 0.0 };
+        }
+        let seen = view.get_other_players().iter().map(|other| {
+            view.get_hand(other).iter().filter(|&other_card| other_card == card).count()
+        }).sum::<usize>();
+        let missing = self.hand(player).iter().map(|table| {
+            let mut table = table.clone();
+            for _ in 0..seen {
+                table.decrement_weight_if_possible(card);
+            }
+            if table.total_weight() == 0.0 {
+                1.0
+            } else {
+                1.0 - table.probability_of_predicate(&|other| other == card)
+            }
+        }).product::<f32>();
+        1.0 - missing
+    }
+
+    pub fn update(&mut self, turn_record: &TurnRecord, view: &BorrowedGameView) {
+        self.update_for_turn(turn_record, view.board);
+    }
+
+    // as `update`, given only the board after the turn, e.g. for an observer
+    pub fn update_for_turn(&mut self, turn_record: &TurnRecord, board: &BoardState) {
+        match (&turn_record.choice, &turn_record.result) {
+            (&TurnChoice::Hint(ref hint), &TurnResult::Hint(ref matches)) => {
+                self.update_for_hint(hint, matches);
+            }
+            (&TurnChoice::Play(index), &TurnResult::Play(ref card, false))
+                if board.misplay_rule == MisplayRule::ReturnToDeckBottom => {
+                self.update_for_returned_card(turn_record.player, index, card, board);
+            }
+            (&TurnChoice::Discard(index), &TurnResult::Discard(ref card)) |
+            (&TurnChoice::Play(index), &TurnResult::Play(ref card, _)) => {
+                self.update_for_removed_card(turn_record.player, index, card, board);
+            }
+            _ => panic!("Got turn choice {:?}, but turn result {:?}", turn_record.choice, turn_record.result),
+        }
+    }
+
+    pub fn update_for_hint(&mut self, hint: &Hint, matches: &Vec<bool>) {
+        self.hand_mut(hint.player).update_for_hint(&hint.hinted, matches);
+    }
+
+    // `player` played or discarded the card at `index`, which turned out to be `card`,
+    // and drew a replacement if there was one, as `board` (after the turn) shows
+    pub fn update_for_removed_card(&mut self, player: Player, index: usize, card: &Card, board: &BoardState) {
+        let new_card_table = CardPossibilityTable::from(&self.card_counts);
+        {
+            let hand = self.hand_mut(player);
+            assert!(hand[index].is_possible(card));
+            hand.remove(index);
+
+            // push *before* incrementing public counts
+            if hand.len() < board.card_ids(&player).len() {
+                hand.push(new_card_table);
+            }
+        }
+
+        // TODO: decrement weight counts for fully determined cards, ahead of time
+
+        for hand in self.hands.iter_mut() {
+            for card_table in hand.iter_mut() {
+                card_table.decrement_weight_if_possible(card);
+            }
+        }
+
+        self.card_counts.increment(card);
+    }
+
+    // `player` misplayed the card at `index`, which turned out to be `card`, and under
+    // MisplayRule::ReturnToDeckBottom it went back into the deck, so is still to be drawn
+    pub fn update_for_returned_card(&mut self, player: Player, index: usize, card: &Card, board: &BoardState) {
+        let hand = &mut self.hands[player as usize];
+        assert!(hand[index].is_possible(card));
+        hand.remove(index);
+        if hand.len() < board.card_ids(&player).len() {
+            hand.push(CardPossibilityTable::from(&self.card_counts));
+        }
+    }
+}
+impl Hash for BeliefTracker {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for hand in self.hands.iter() {
+            for card_table in hand.iter() {
+                card_table.hash(state);
+            }
+        }
+        for &color in COLORS.iter() {
+            for &value in VALUES.iter() {
+                self.card_counts.get_count(&Card::new(color, value)).hash(state);
+            }
+        }
+    }
+}
+
+// The first panic message of the code catch_quietly is running, if it has panicked
+type PanicMessage = Arc<Mutex<Option<String>>>;
+
+thread_local! {
+    // where this thread records its panics, if it is running code whose panics are expected,
+    // and not worth printing
+    static QUIET: RefCell<Option<PanicMessage>> = RefCell::new(None);
+}
+static QUIET_HOOK: Once = Once::new();
+
+// Runs `f`, or returns why it panicked, without printing the panic.  For strategies asked to
+// follow games they may not be able to make sense of, e.g. in a search's rollouts.
+pub fn catch_quietly<T, F: FnOnce() -> T>(f: F) -> Result<T, String> {
+    QUIET_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let message = QUIET.with(|quiet| quiet.borrow().clone());
+            match message {
+                Some(message) => {
+                    let mut message = message.lock().unwrap();
+                    if message.is_none() {
+                        *message = Some(info.to_string().replace('\n', " "));
+                    }
+                }
+                None => default_hook(info),
+            }
+        }));
+    });
+    let message = Arc::new(Mutex::new(None));
+    let result = {
+        let _quiet = QuietScope(Some(message.clone())).enter();
+        panic::catch_unwind(AssertUnwindSafe(f))
+    };
+    result.map_err(|_| {
+        let message = message.lock().unwrap().take();
+        message.unwrap_or(String::from("unknown panic"))
+    })
+}
+
+// Whether panics on this thread are being caught quietly, to carry over to threads it spawns:
+// their panics are then recorded as the reason the calling catch_quietly fails, not printed
+pub struct QuietScope(Option<PanicMessage>);
+impl QuietScope {
+    pub fn current() -> QuietScope {
+        QuietScope(QUIET.with(|quiet| quiet.borrow().clone()))
+    }
+
+    // puts this thread in this scope until the guard is dropped.  Scopes may be nested, e.g. in
+    // a rollout of a strategy that catches its own panics
+    pub fn enter(&self) -> QuietGuard {
+        QuietGuard(QUIET.with(|quiet| quiet.replace(self.0.clone())))
+    }
+}
+
+// Restores the scope a thread was in before QuietScope::enter
+pub struct QuietGuard(Option<PanicMessage>);
+impl Drop for QuietGuard {
+    fn drop(&mut self) {
+        let outer = self.0.take();
+        QUIET.with(|quiet| *quiet.borrow_mut() = outer);
+    }
+}
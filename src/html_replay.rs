@@ -0,0 +1,192 @@
+use game::*;
+
+// The state of a game after each turn, reconstructed from its deck and turn history
+pub struct GameTrace {
+    pub num_players: u32,
+    // steps[0] is the initial deal, steps[i] is the state after the i-th turn
+    pub steps: Vec<TraceStep>,
+}
+
+pub struct TraceStep {
+    // the turn that led to this state, if any
+    pub turn: Option<TurnRecord>,
+    pub hands: Vec<Cards>,
+    pub fireworks: Vec<(Color, Value)>,
+    pub discard: Cards,
+    pub hints_remaining: u32,
+    pub lives_remaining: u32,
+    pub deck_size: u32,
+    pub score: Score,
+}
+impl TraceStep {
+    fn new(game: &GameState, turn: Option<TurnRecord>) -> TraceStep {
+        TraceStep {
+            turn: turn,
+            hands: game.get_players().map(|player| game.hands[&player].clone()).collect(),
+            fireworks: COLORS.iter().map(|&color| (color, game.board.get_firework(color).top)).collect(),
+            discard: game.board.discard.cards.clone(),
+            hints_remaining: game.board.hints_remaining,
+            lives_remaining: game.board.lives_remaining,
+            deck_size: game.board.deck_size,
+            score: game.score(),
+        }
+    }
+}
+
+impl GameTrace {
+    // replay the given turns on a fresh deal of `deck`
+    pub fn new(opts: &GameOptions, deck: Cards, history: &TurnHistory) -> GameTrace {
+        let mut game = GameState::new(opts, deck);
+        let mut steps = vec![TraceStep::new(&game, None)];
+        for turn in history {
+            let record = game.process_choice(turn.choice.clone());
+            assert!(record == *turn, "Turn history is inconsistent with the deck");
+            steps.push(TraceStep::new(&game, Some(record)));
+        }
+        GameTrace {
+            num_players: opts.num_players,
+            steps: steps,
+        }
+    }
+}
+
+pub fn describe_turn(turn: &TurnRecord) -> String {
+    match (&turn.choice, &turn.result) {
+        (&TurnChoice::Hint(ref hint), &TurnResult::Hint(ref matches)) => {
+            let touched = matches.iter().enumerate().filter(|&(_, &matched)| matched)
+                .map(|(i, _)| i.to_string()).collect::<Vec<_>>();
+            format!("Player {} hints player {} about {} (touching slots {})",
+                    turn.player, hint.player, hint.hinted, touched.join(", "))
+        }
+        (&TurnChoice::Discard(index), &TurnResult::Discard(ref card)) => {
+            format!("Player {} discards {} from slot {}", turn.player, card, index)
+        }
+        (&TurnChoice::Play(index), &TurnResult::Play(ref card, playable)) => {
+            if playable {
+                format!("Player {} plays {} from slot {}", turn.player, card, index)
+            } else {
+                format!("Player {} bombs {} from slot {}", turn.player, card, index)
+            }
+        }
+        _ => panic!("Got turn choice {:?}, but turn result {:?}", turn.choice, turn.result),
+    }
+}
+
+fn js_string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '<' => escaped.push_str("\\u003c"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// text to put in the page's HTML, as opposed to its script
+fn html_escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn js_cards(cards: &Cards) -> String {
+    let cards = cards.iter().map(|card| js_string(&card.to_string())).collect::<Vec<_>>();
+    format!("[{}]", cards.join(","))
+}
+
+fn js_step(step: &TraceStep) -> String {
+    let action = match step.turn {
+        Some(ref turn) => describe_turn(turn),
+        None => String::from("Initial deal"),
+    };
+    let hands = step.hands.iter().map(js_cards).collect::<Vec<_>>();
+    let fireworks = step.fireworks.iter().map(|&(color, top)| {
+        format!("[{},{}]", js_string(&color.to_string()), top)
+    }).collect::<Vec<_>>();
+    format!("{{action:{},hands:[{}],fireworks:[{}],discard:{},hints:{},lives:{},deck:{},score:{}}}",
+            js_string(&action), hands.join(","), fireworks.join(","), js_cards(&step.discard),
+            step.hints_remaining, step.lives_remaining, step.deck_size, step.score)
+}
+
+const PAGE_TEMPLATE: &'static str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>TITLE</title>
+<style>
+  body { font-family: sans-serif; background: #222; color: #eee; }
+  .card { display: inline-block; width: 2.2em; padding: 0.3em 0; margin: 0.1em; text-align: center;
+          border-radius: 0.3em; font-weight: bold; color: #111; }
+  .r { background: #e55; } .y { background: #ee5; } .g { background: #5c5; }
+  .b { background: #59e; } .w { background: #eee; }
+  .row { margin: 0.4em 0; }
+  .label { display: inline-block; width: 6em; }
+  #controls { margin: 1em 0; }
+  #slider { width: 30em; }
+</style>
+</head>
+<body>
+<h2>TITLE</h2>
+<div id="controls">
+  <button onclick="show(step - 1)">&larr;</button>
+  <input id="slider" type="range" min="0" value="0" oninput="show(+this.value)">
+  <button onclick="show(step + 1)">&rarr;</button>
+  <span id="counter"></span>
+</div>
+<div id="action" class="row"></div>
+<div id="status" class="row"></div>
+<div id="fireworks" class="row"></div>
+<div id="hands"></div>
+<div id="discard" class="row"></div>
+<script>
+var steps = [STEPS];
+var step = 0;
+function card(name) { return '<span class="card ' + name[0] + '">' + name + '</span>'; }
+function show(i) {
+  step = Math.max(0, Math.min(steps.length - 1, i));
+  var s = steps[step];
+  document.getElementById('slider').value = step;
+  document.getElementById('counter').textContent = 'Turn ' + step + ' / ' + (steps.length - 1);
+  document.getElementById('action').textContent = s.action;
+  document.getElementById('status').textContent =
+    'Score ' + s.score + ', ' + s.hints + ' hints, ' + s.lives + ' lives, ' + s.deck + ' cards in deck';
+  document.getElementById('fireworks').innerHTML = '<span class="label">Fireworks</span>' +
+    s.fireworks.map(function (f) { return f[1] > 0 ? card(f[0] + f[1]) : card(f[0] + '-'); }).join('');
+  document.getElementById('hands').innerHTML = s.hands.map(function (hand, player) {
+    return '<div class="row"><span class="label">Player ' + player + '</span>' + hand.map(card).join('') + '</div>';
+  }).join('');
+  document.getElementById('discard').innerHTML = '<span class="label">Discard</span>' + s.discard.map(card).join('');
+}
+document.getElementById('slider').max = steps.length - 1;
+document.onkeydown = function (e) {
+  if (e.key === 'ArrowLeft') { show(step - 1); }
+  if (e.key === 'ArrowRight') { show(step + 1); }
+};
+show(0);
+</script>
+</body>
+</html>
+"#;
+
+// Render a self-contained HTML page for stepping through the game
+pub fn render(trace: &GameTrace, title: &str) -> String {
+    let steps = trace.steps.iter().map(js_step).collect::<Vec<_>>();
+    // the steps first, so that a title can't add to them
+    PAGE_TEMPLATE
+        .replace("STEPS", &steps.join(",\n"))
+        .replace("TITLE", &html_escape(title))
+}
@@ -0,0 +1,356 @@
+use std::fmt;
+
+// A minimal JSON value, enough for reading and writing games and statistics
+#[derive(Debug,Clone,PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // keys are kept in insertion order
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object() -> Json {
+        Json::Object(Vec::new())
+    }
+
+    // add a field to an object, builder-style
+    pub fn with<T: Into<Json>>(mut self, key: &str, value: T) -> Json {
+        self.insert(key, value);
+        self
+    }
+
+    pub fn insert<T: Into<Json>>(&mut self, key: &str, value: T) {
+        match *self {
+            Json::Object(ref mut fields) => fields.push((key.to_string(), value.into())),
+            _ => panic!("Tried to insert field {} into non-object {}", key, self),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref fields) => {
+                fields.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref value)| value)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json { Json::Bool(b) }
+}
+impl From<u32> for Json {
+    fn from(n: u32) -> Json { Json::Number(n as f64) }
+}
+impl From<usize> for Json {
+    fn from(n: usize) -> Json { Json::Number(n as f64) }
+}
+impl From<f64> for Json {
+    fn from(n: f64) -> Json { Json::Number(n) }
+}
+impl<'a> From<&'a str> for Json {
+    fn from(s: &'a str) -> Json { Json::String(s.to_string()) }
+}
+impl From<String> for Json {
+    fn from(s: String) -> Json { Json::String(s) }
+}
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(items: Vec<T>) -> Json {
+        Json::Array(items.into_iter().map(|item| item.into()).collect())
+    }
+}
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(item: Option<T>) -> Json {
+        match item {
+            Some(item) => item.into(),
+            None => Json::Null,
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    try!(f.write_str("\""));
+    for c in s.chars() {
+        match c {
+            '"' => try!(f.write_str("\\\"")),
+            '\\' => try!(f.write_str("\\\\")),
+            '\n' => try!(f.write_str("\\n")),
+            '\r' => try!(f.write_str("\\r")),
+            '\t' => try!(f.write_str("\\t")),
+            c if (c as u32) < 0x20 => try!(write!(f, "\\u{:04x}", c as u32)),
+            c => try!(write!(f, "{}", c)),
+        }
+    }
+    f.write_str("\"")
+}
+
+impl Json {
+    // indented serialization, for people to read
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out
+    }
+
+    fn write_pretty(&self, out: &mut String, depth: usize) {
+        let indent = |out: &mut String, depth: usize| out.push_str(&"  ".repeat(depth));
+        match *self {
+            // arrays of numbers and such fit on one line
+            Json::Array(ref items) if !items.is_empty() && items.iter().any(|item| {
+                match *item { Json::Array(_) | Json::Object(_) | Json::String(_) => true, _ => false }
+            }) => {
+                out.push_str("[\n");
+                for (i, item) in items.iter().enumerate() {
+                    indent(out, depth + 1);
+                    item.write_pretty(out, depth + 1);
+                    out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                indent(out, depth);
+                out.push(']');
+            }
+            Json::Object(ref fields) if !fields.is_empty() => {
+                out.push_str("{\n");
+                for (i, &(ref key, ref value)) in fields.iter().enumerate() {
+                    indent(out, depth + 1);
+                    out.push_str(&format!("{}: ", Json::String(key.clone())));
+                    value.write_pretty(out, depth + 1);
+                    out.push_str(if i + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                indent(out, depth);
+                out.push('}');
+            }
+            _ => out.push_str(&self.to_string()),
+        }
+    }
+}
+
+// compact serialization
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => {
+                if !n.is_finite() {
+                    // JSON has no representation for these
+                    f.write_str("null")
+                } else if n.fract() == 0.0 && n.abs() < 1e15 {
+                    write!(f, "{}", n as i64)
+                } else {
+                    write!(f, "{}", n)
+                }
+            }
+            Json::String(ref s) => write_string(f, s),
+            Json::Array(ref items) => {
+                try!(f.write_str("["));
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(","));
+                    }
+                    try!(write!(f, "{}", item));
+                }
+                f.write_str("]")
+            }
+            Json::Object(ref fields) => {
+                try!(f.write_str("{"));
+                for (i, &(ref key, ref value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        try!(f.write_str(","));
+                    }
+                    try!(write_string(f, key));
+                    try!(write!(f, ":{}", value));
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl Json {
+    pub fn parse(s: &str) -> Result<Json, String> {
+        let mut parser = Parser { chars: s.chars().collect(), pos: 0 };
+        let value = try!(parser.parse_value());
+        parser.skip_whitespace();
+        if parser.pos != parser.chars.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match *self {
+            Json::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= u32::max_value() as f64 => Some(n as u32),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match *self {
+            Json::Array(ref items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+// A recursive descent parser for JSON text
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid JSON at character {}: {}", self.pos, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        for expected in literal.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("expected {}", literal)));
+            }
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.parse_literal("null", Json::Null),
+            Some('t') => self.parse_literal("true", Json::Bool(true)),
+            Some('f') => self.parse_literal("false", Json::Bool(false)),
+            Some('"') => self.parse_string().map(Json::String),
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(try!(self.parse_value()));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => { self.pos += 1; return Ok(Json::Array(items)); }
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = try!(self.parse_string());
+                    try!(self.expect(':'));
+                    fields.push((key, try!(self.parse_value())));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => { self.pos += 1; return Ok(Json::Object(fields)); }
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(c) if c == '-' || c.is_digit(10) => {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c.is_digit(10) || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let text = self.chars[start..self.pos].iter().collect::<String>();
+                text.parse().map(Json::Number).map_err(|_| self.error(&format!("bad number {}", text)))
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        if self.peek() != Some('"') {
+            return Err(self.error("expected a string"));
+        }
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some(c) => c,
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        '"' => s.push('"'),
+                        '\\' => s.push('\\'),
+                        '/' => s.push('/'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'u' => {
+                            let hex = self.chars.iter().skip(self.pos).take(4).collect::<String>();
+                            let code = try!(u32::from_str_radix(&hex, 16).map_err(|_| {
+                                self.error("bad unicode escape")
+                            }));
+                            self.pos += 4;
+                            // surrogate pairs aren't combined
+                            s.push(::std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("bad escape")),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+}
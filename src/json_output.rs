@@ -0,0 +1,496 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use game::*;
+use json::Json;
+use observer::GameObserver;
+use strategy::PlayerStrategy;
+use version::ArtifactVersion;
+
+// Exports games in the JSON format hanab.live accepts for replays
+// (a list of players, the deck in draw order, and the actions taken), and reads them back.
+// The format is spelled out by GameExport and ActionExport.
+// hanab.live's suit order for "No Variant" matches COLORS, with white as its fifth suit
+fn suit_index(color: Color) -> u32 {
+    COLORS.iter().position(|&c| c == color).unwrap() as u32
+}
+
+fn json_card(card: &Card) -> Json {
+    Json::object()
+        .with("suitIndex", suit_index(card.color))
+        .with("rank", card.value)
+}
+
+fn card_from_json(json: &Json) -> Result<Card, String> {
+    let suit = json.get("suitIndex").and_then(Json::as_u32);
+    let rank = json.get("rank").and_then(Json::as_u32);
+    match (suit, rank) {
+        (Some(suit), Some(rank)) if (suit as usize) < COLORS.len() && VALUES.contains(&rank) => {
+            Ok(Card::new(COLORS[suit as usize], rank))
+        }
+        _ => Err(format!("unsupported card {}", json)),
+    }
+}
+
+// hanab.live action types
+const ACTION_PLAY: u32 = 0;
+const ACTION_DISCARD: u32 = 1;
+const ACTION_COLOR_CLUE: u32 = 2;
+const ACTION_RANK_CLUE: u32 = 3;
+
+// One action, in hanab.live's format.  Plays and discards target a card, by its order
+// (see CardId), and have no value.  Clues target a player, and their value is a suit index
+// or a rank.  hanab.live has other types, e.g. to end a game on a timeout.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ActionExport {
+    pub action_type: u32,
+    pub target: u32,
+    pub value: Option<u32>,
+}
+impl ActionExport {
+    pub fn from_turn(turn: &TurnRecord) -> ActionExport {
+        match turn.choice {
+            TurnChoice::Hint(ref hint) => {
+                let (action_type, value) = match hint.hinted {
+                    Hinted::Color(color) => (ACTION_COLOR_CLUE, suit_index(color)),
+                    Hinted::Value(value) => (ACTION_RANK_CLUE, value),
+                };
+                ActionExport { action_type: action_type, target: hint.player, value: Some(value) }
+            }
+            TurnChoice::Play(_) | TurnChoice::Discard(_) => {
+                let action_type = match turn.choice {
+                    TurnChoice::Play(_) => ACTION_PLAY,
+                    _ => ACTION_DISCARD,
+                };
+                let target = turn.card_id.expect("Plays and discards have a card");
+                ActionExport { action_type: action_type, target: target, value: None }
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let json = Json::object()
+            .with("type", self.action_type)
+            .with("target", self.target);
+        match self.value {
+            Some(value) => json.with("value", value),
+            None => json,
+        }
+    }
+
+    pub fn from_json(json: &Json) -> Result<ActionExport, String> {
+        let action_type = json.get("type").and_then(Json::as_u32);
+        let target = json.get("target").and_then(Json::as_u32);
+        match (action_type, target) {
+            (Some(action_type), Some(target)) => Ok(ActionExport {
+                action_type: action_type,
+                target: target,
+                value: json.get("value").and_then(Json::as_u32),
+            }),
+            _ => Err(format!("unsupported action {}", json)),
+        }
+    }
+}
+
+// A whole game, in hanab.live's format.  hanab.live reads `players`, `deck`, `actions`,
+// `options` and `seed`; the rest is ours, and it ignores them.  Only the first three are
+// required when reading, and fields hanab.live has that aren't here are dropped.
+#[derive(Debug,Clone,PartialEq)]
+pub struct GameExport {
+    pub players: Vec<String>,
+    // the deck in the order the cards are drawn
+    pub deck: Cards,
+    pub actions: Vec<ActionExport>,
+    // hanab.live's name for the rules, "No Variant" for ours
+    pub variant: Option<String>,
+    // any string to hanab.live; ours are the simulator's seeds
+    pub seed: Option<String>,
+    pub score: Option<Score>,
+    pub version: Option<ArtifactVersion>,
+    // the acting strategy's json_empathy after each action
+    pub empathy: Option<Vec<Json>>,
+    // each player's note on each card, by the card's order, as hanab.live keeps them
+    pub notes: Option<Vec<Vec<String>>>,
+}
+impl GameExport {
+    pub fn to_json(&self) -> Json {
+        let mut json = Json::object()
+            .with("players", self.players.clone())
+            .with("deck", Json::Array(self.deck.iter().map(json_card).collect()))
+            .with("actions", Json::Array(self.actions.iter().map(ActionExport::to_json).collect()));
+        if let Some(ref variant) = self.variant {
+            json.insert("options", Json::object().with("variant", &variant[..]));
+        }
+        if let Some(ref seed) = self.seed {
+            json.insert("seed", &seed[..]);
+        }
+        if let Some(score) = self.score {
+            json.insert("score", score);
+        }
+        if let Some(ref version) = self.version {
+            json.insert("version", version.to_json());
+        }
+        if let Some(ref empathy) = self.empathy {
+            json.insert("empathy", Json::Array(empathy.clone()));
+        }
+        if let Some(ref notes) = self.notes {
+            json.insert("notes", notes.clone());
+        }
+        json
+    }
+
+    pub fn from_json(json: &Json) -> Result<GameExport, String> {
+        let players_json = try!(json.get("players").and_then(Json::as_array).ok_or("missing `players`"));
+        let mut players = Vec::new();
+        for player in players_json {
+            players.push(try!(player.as_str().ok_or_else(|| format!("unsupported player {}", player))).to_string());
+        }
+        let deck_json = try!(json.get("deck").and_then(Json::as_array).ok_or("missing `deck`"));
+        let mut deck = Vec::new();
+        for card in deck_json {
+            deck.push(try!(card_from_json(card)));
+        }
+        let actions_json = try!(json.get("actions").and_then(Json::as_array).ok_or("missing `actions`"));
+        let mut actions = Vec::new();
+        for action in actions_json {
+            actions.push(try!(ActionExport::from_json(action)));
+        }
+        let notes = match json.get("notes").and_then(Json::as_array) {
+            Some(notes_json) => {
+                let mut notes = Vec::new();
+                for player_notes in notes_json {
+                    let player_notes = try!(player_notes.as_array().ok_or_else(|| {
+                        format!("unsupported notes {}", player_notes)
+                    }));
+                    let mut texts = Vec::new();
+                    for note in player_notes {
+                        texts.push(try!(note.as_str().ok_or_else(|| format!("unsupported note {}", note))).to_string());
+                    }
+                    notes.push(texts);
+                }
+                Some(notes)
+            }
+            None => None,
+        };
+        let version = match json.get("version") {
+            Some(version) => Some(try!(ArtifactVersion::from_json(version))),
+            None => None,
+        };
+        Ok(GameExport {
+            players: players,
+            deck: deck,
+            actions: actions,
+            variant: json.get("options").and_then(|options| options.get("variant"))
+                .and_then(Json::as_str).map(String::from),
+            seed: json.get("seed").and_then(Json::as_str).map(String::from),
+            score: json.get("score").and_then(Json::as_u32),
+            version: version,
+            empathy: json.get("empathy").and_then(Json::as_array).cloned(),
+            notes: notes,
+        })
+    }
+}
+
+// What a strategy believes is common knowledge about every card in every hand, after a turn:
+// for each player, for each card in their hand, the probability of each card, in the order
+// r1, r2, ..., r5, y1, ..., w5, rounded to 3 decimal places.  Null if the strategy doesn't
+// keep track (see PlayerStrategy::public_card_probabilities).
+fn json_empathy(strategy: &PlayerStrategy, num_players: u32) -> Json {
+    let mut hands = Vec::new();
+    for player in 0..num_players {
+        let hand = match strategy.public_card_probabilities(player) {
+            Some(hand) => hand,
+            None => return Json::Null,
+        };
+        hands.push(Json::Array(hand.iter().map(|probabilities| {
+            let mut row = vec![0.0; COLORS.len() * VALUES.len()];
+            for &(ref card, probability) in probabilities.iter() {
+                let index = suit_index(card.color) as usize * VALUES.len() + card.value as usize - 1;
+                row[index] = (probability as f64 * 1000.0).round() / 1000.0;
+            }
+            Json::from(row)
+        }).collect()));
+    }
+    Json::Array(hands)
+}
+
+// A stream of games, which can be finished off (e.g. writing a gzip trailer)
+trait GameStream: Write + Send {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+impl GameStream for BufWriter<fs::File> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush()
+    }
+}
+#[cfg(feature = "gzip")]
+impl GameStream for ::flate2::write::GzEncoder<BufWriter<fs::File>> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        (*self).finish().and_then(|mut file| file.flush())
+    }
+}
+
+// Where JsonObserver writes its games.  Cloning it shares the same destination, so every thread
+// of a simulation can write to one stream.
+#[derive(Clone)]
+pub enum Writer {
+    // a file per game, at a pattern where `%s` is replaced by the seed, and `%d` by the seed
+    // divided by 1000, to spread the files over directories of at most 1000 games each
+    Files(String),
+    // every game in one file, one per line (JSON has no raw newlines, so each game is exactly
+    // one line).  Games are written whole, in the order they finish, and each has its seed.
+    // None once finished
+    Stream(String, Arc<Mutex<Option<Box<GameStream>>>>),
+}
+impl Writer {
+    // A pattern ending in `.jsonl` is a stream, and `.jsonl.gz` a gzipped one, with the gzip
+    // feature.  Anything else is a pattern for a file per game.
+    pub fn new(pattern: &str) -> Result<Writer, String> {
+        let is_gzip = pattern.ends_with(".jsonl.gz");
+        if !is_gzip && !pattern.ends_with(".jsonl") {
+            return Ok(Writer::Files(pattern.to_string()));
+        }
+        if pattern.contains("%s") || pattern.contains("%d") {
+            return Err(format!("{} is a single stream of games, so can't have %s or %d", pattern));
+        }
+        let stream = try!(Writer::open_stream(pattern, is_gzip));
+        Ok(Writer::Stream(pattern.to_string(), Arc::new(Mutex::new(Some(stream)))))
+    }
+
+    fn create(path: &str) -> Result<BufWriter<fs::File>, String> {
+        let file = try!(fs::File::create(path).map_err(|err| format!("Couldn't create {}: {}", path, err)));
+        Ok(BufWriter::new(file))
+    }
+
+    #[cfg(feature = "gzip")]
+    fn open_stream(path: &str, is_gzip: bool) -> Result<Box<GameStream>, String> {
+        let file = try!(Writer::create(path));
+        if is_gzip {
+            Ok(Box::new(::flate2::write::GzEncoder::new(file, ::flate2::Compression::default())))
+        } else {
+            Ok(Box::new(file))
+        }
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    fn open_stream(path: &str, is_gzip: bool) -> Result<Box<GameStream>, String> {
+        if is_gzip {
+            return Err(format!("Writing {} needs gzip support: build with `--features gzip`", path));
+        }
+        Ok(Box::new(try!(Writer::create(path))))
+    }
+
+    // where the game with this seed goes
+    pub fn path(&self, seed: u32) -> String {
+        match *self {
+            Writer::Files(ref pattern) => {
+                pattern.replace("%s", &seed.to_string()).replace("%d", &(seed / 1000).to_string())
+            }
+            Writer::Stream(ref path, _) => path.clone(),
+        }
+    }
+
+    pub fn write(&self, seed: u32, json: &Json) -> Result<(), String> {
+        let path = self.path(seed);
+        match *self {
+            Writer::Files(ref pattern) => {
+                if pattern.contains("%d") {
+                    if let Some(dir) = Path::new(&path).parent() {
+                        try!(fs::create_dir_all(dir).map_err(|err| {
+                            format!("Couldn't create {}: {}", dir.display(), err)
+                        }));
+                    }
+                }
+                fs::write(&path, format!("{}\n", json)).map_err(|err| format!("Couldn't write {}: {}", path, err))
+            }
+            Writer::Stream(_, ref stream) => {
+                let mut stream = stream.lock().unwrap();
+                let stream = stream.as_mut().expect("Writing a game to a finished stream");
+                writeln!(stream, "{}", json).map_err(|err| format!("Couldn't write to {}: {}", path, err))
+            }
+        }
+    }
+
+    // Writes out everything written to a stream.  Does nothing for a file per game
+    pub fn finish(&self) -> Result<(), String> {
+        match *self {
+            Writer::Files(_) => Ok(()),
+            Writer::Stream(ref path, ref stream) => match stream.lock().unwrap().take() {
+                Some(stream) => stream.finish().map_err(|err| format!("Couldn't write to {}: {}", path, err)),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+// Records a game as it is played, and writes it out when the game ends.
+// hanab.live refers to cards by their position in the deck ("order"), which is their CardId.
+pub struct JsonObserver {
+    writer: Writer,
+    seed: u32,
+    num_players: u32,
+    // the deck in the order the cards are drawn
+    deck: Cards,
+    actions: Vec<ActionExport>,
+    // if recording them, the acting player's json_empathy after each action
+    empathy: Option<Vec<Json>>,
+    // each player's latest note on each card, by CardId, from PlayerStrategy::notes
+    notes: Vec<Vec<String>>,
+    version: ArtifactVersion,
+}
+impl JsonObserver {
+    // With `empathy`, the games also get an "empathy" field, with the acting strategy's
+    // public knowledge after each action.  hanab.live ignores it.
+    pub fn new(writer: Writer, version: ArtifactVersion, empathy: bool) -> JsonObserver {
+        JsonObserver {
+            writer: writer,
+            seed: 0,
+            num_players: 0,
+            deck: Vec::new(),
+            actions: Vec::new(),
+            empathy: if empathy { Some(Vec::new()) } else { None },
+            notes: Vec::new(),
+            version: version,
+        }
+    }
+
+    // the game recorded so far, which has reached `game`
+    pub fn export(&self, game: &GameState) -> GameExport {
+        GameExport {
+            players: (0..self.num_players).map(|player| format!("Player {}", player)).collect(),
+            deck: self.deck.clone(),
+            actions: self.actions.clone(),
+            variant: Some(String::from("No Variant")),
+            seed: Some(self.seed.to_string()),
+            score: Some(game.score()),
+            version: Some(self.version.clone()),
+            empathy: self.empathy.clone(),
+            // only for strategies that write notes
+            notes: if self.notes.iter().any(|notes| notes.iter().any(|note| !note.is_empty())) {
+                Some(self.notes.clone())
+            } else {
+                None
+            },
+        }
+    }
+}
+impl GameObserver for JsonObserver {
+    fn on_game_start(&mut self, seed: u32, game: &GameState) {
+        self.seed = seed;
+        self.num_players = game.board.num_players;
+        self.deck.clear();
+        self.actions.clear();
+        if let Some(ref mut empathy) = self.empathy {
+            empathy.clear();
+        }
+        for player in game.get_players() {
+            self.deck.extend(game.hands[&player].iter().cloned());
+        }
+        // cards are drawn from the end of the deck
+        self.deck.extend(game.deck.iter().rev().cloned());
+        self.notes = vec![vec![String::new(); self.deck.len()]; self.num_players as usize];
+    }
+
+    fn on_turn(&mut self, turn: &TurnRecord, _: &BoardState) {
+        self.actions.push(ActionExport::from_turn(turn));
+    }
+
+    fn after_update(&mut self, player: Player, strategy: &PlayerStrategy) {
+        let num_players = self.num_players;
+        if let Some(ref mut empathy) = self.empathy {
+            empathy.push(json_empathy(strategy, num_players));
+        }
+        for note in strategy.notes() {
+            self.notes[player as usize][note.card_id as usize] = note.to_string();
+        }
+    }
+
+    fn on_game_end(&mut self, game: &GameState) {
+        self.writer.write(self.seed, &self.export(game).to_json()).unwrap_or_else(|err| panic!("{}", err));
+    }
+}
+
+// A game read back from hanab.live's JSON format, e.g. one written by JsonObserver,
+// ready to be played again
+pub struct Transcript {
+    pub num_players: u32,
+    // the deck in the order the cards are drawn
+    pub deck: Cards,
+    actions: Vec<ActionExport>,
+    pub seed: Option<u32>,
+    pub version: Option<ArtifactVersion>,
+}
+impl Transcript {
+    pub fn read(path: &str) -> Result<Transcript, String> {
+        let contents = try!(fs::read_to_string(path).map_err(|err| {
+            format!("Couldn't read {}: {}", path, err)
+        }));
+        let json = try!(Json::parse(&contents));
+        Transcript::from_json(&json).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub fn from_json(json: &Json) -> Result<Transcript, String> {
+        GameExport::from_json(json).map(Transcript::from_export)
+    }
+
+    // hanab.live's own seeds aren't numbers, and are dropped
+    pub fn from_export(export: GameExport) -> Transcript {
+        Transcript {
+            num_players: export.players.len() as u32,
+            deck: export.deck,
+            actions: export.actions,
+            seed: export.seed.and_then(|seed| seed.parse().ok()),
+            version: export.version,
+        }
+    }
+
+    // the deck as GameState::new expects it, with the first card to be drawn at the end
+    pub fn engine_deck(&self) -> Cards {
+        self.deck.iter().rev().cloned().collect()
+    }
+
+    // The actions as turn choices, converting card orders back to hand positions
+    pub fn choices(&self, hand_size: u32) -> Result<Vec<TurnChoice>, String> {
+        let mut hands = (0..self.num_players).map(|player| {
+            (player * hand_size .. (player + 1) * hand_size).collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+        let mut next_order = self.num_players * hand_size;
+        let mut choices = Vec::new();
+        for (i, action) in self.actions.iter().enumerate() {
+            let player = i as u32 % self.num_players;
+            let (target, value) = (action.target, action.value);
+            let choice = match action.action_type {
+                ACTION_PLAY | ACTION_DISCARD => {
+                    let hand = &mut hands[player as usize];
+                    let index = try!(hand.iter().position(|&order| order == target).ok_or_else(|| {
+                        format!("action {}: player {} doesn't hold card {}", i, player, target)
+                    }));
+                    hand.remove(index);
+                    if (next_order as usize) < self.deck.len() {
+                        hand.push(next_order);
+                        next_order += 1;
+                    }
+                    if action.action_type == ACTION_PLAY { TurnChoice::Play(index) } else { TurnChoice::Discard(index) }
+                }
+                ACTION_COLOR_CLUE | ACTION_RANK_CLUE => {
+                    let value = try!(value.ok_or_else(|| format!("action {}: clue without a value", i)));
+                    let hinted = if action.action_type == ACTION_COLOR_CLUE {
+                        match COLORS.get(value as usize) {
+                            Some(&color) => Hinted::Color(color),
+                            None => return Err(format!("action {}: unsupported suit {}", i, value)),
+                        }
+                    } else {
+                        Hinted::Value(value)
+                    };
+                    TurnChoice::Hint(Hint { player: target, hinted: hinted })
+                }
+                // hanab.live marks the end of the game (e.g. a timeout) with its own action
+                _ => break,
+            };
+            choices.push(choice);
+        }
+        Ok(choices)
+    }
+}
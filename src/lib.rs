@@ -0,0 +1,48 @@
+// The Hanabi engine, simulator, and built-in strategies.
+// See src/main.rs for the command-line interface built on top of this crate.
+
+#[macro_use]
+extern crate log;
+extern crate rand;
+extern crate crossbeam;
+extern crate fnv;
+extern crate float_ord;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+
+pub mod adaptive;
+pub mod checkpoint;
+pub mod clock;
+pub mod crossplay;
+pub mod debugger;
+pub mod detective;
+pub mod helpers;
+pub mod game;
+pub mod simulator;
+pub mod spans;
+pub mod strategy;
+pub mod html_replay;
+pub mod json;
+pub mod json_output;
+pub mod meta;
+pub mod noisy;
+pub mod observer;
+pub mod position;
+pub mod progress;
+pub mod puzzles;
+pub mod render;
+pub mod search;
+pub mod stats_output;
+pub mod sweep;
+pub mod training;
+pub mod validate;
+pub mod version;
+pub mod watch;
+pub mod strategies {
+    pub mod examples;
+    pub mod cheating;
+    pub mod cheat_search;
+    mod hat_helpers;
+    pub mod information;
+    pub mod semi_cheating;
+}
@@ -2,95 +2,527 @@ extern crate getopts;
 #[macro_use]
 extern crate log;
 extern crate rand;
-extern crate crossbeam;
-extern crate fnv;
-extern crate float_ord;
-
-mod helpers;
-mod game;
-mod simulator;
-mod strategy;
-mod strategies {
-    pub mod examples;
-    pub mod cheating;
-    mod hat_helpers;
-    pub mod information;
-}
-
-use getopts::Options;
+extern crate rust_hanabi;
+
+use rust_hanabi::{adaptive, checkpoint, clock, crossplay, debugger, detective, game, html_replay, json, json_output, meta, noisy, observer, progress, puzzles, render, search, simulator, spans, stats_output, strategy, sweep, strategies, training, validate, version, watch};
+use getopts::{Matches, Options};
+use std::fmt;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::Arc;
 
-struct SimpleLogger;
+// Prints log lines at the chosen level, prefixed with the game and turn they came from.
+// Debug lines are also printed from the game chosen with --log-seed
+struct SimpleLogger {
+    level: log::LogLevelFilter,
+}
 impl log::Log for SimpleLogger {
     fn enabled(&self, metadata: &log::LogMetadata) -> bool {
-        metadata.level() <= log::LogLevel::Trace
+        metadata.level() <= self.level ||
+            (metadata.level() <= log::LogLevel::Debug && spans::in_verbose_game())
     }
 
     fn log(&self, record: &log::LogRecord) {
         if self.enabled(record.metadata()) {
-            println!("{} - {}", record.level(), record.args());
+            let span = spans::current();
+            if span.is_empty() {
+                println!("{} - {}", record.level(), record.args());
+            } else {
+                println!("{} {} - {}", record.level(), span, record.args());
+            }
+        }
+    }
+}
+
+
+// The subcommands, each with its own options.  Without one, the options of every command are
+// accepted as flags, as they were before there were subcommands, and the flags pick what to do
+// (e.g. --compare or --watch), falling back to `simulate`.
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Command {
+    Simulate,
+    ResultsTable,
+    Replay,
+    Compare,
+    Analyze,
+}
+const COMMANDS: [Command; 5] = [
+    Command::Simulate,
+    Command::ResultsTable,
+    Command::Replay,
+    Command::Compare,
+    Command::Analyze,
+];
+impl Command {
+    fn name(&self) -> &'static str {
+        match *self {
+            Command::Simulate => "simulate",
+            Command::ResultsTable => "results-table",
+            Command::Replay => "replay",
+            Command::Compare => "compare",
+            Command::Analyze => "analyze",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Command> {
+        COMMANDS.iter().cloned().find(|command| command.name() == name)
+    }
+
+    fn description(&self) -> &'static str {
+        match *self {
+            Command::Simulate => "Simulate games with one strategy and report its scores",
+            Command::ResultsTable => "Print the table of results for each strategy, or update it in README.md",
+            Command::Replay => "Play a single game, from a seed or a deck file, and show it or save it",
+            Command::Compare => "Play two strategies on the same seeds, or every pair of strategies together",
+            Command::Analyze => "Describe a strategy's conventions, replay a game through its eyes, find the seeds it does worst on, or tune its parameters",
+        }
+    }
+
+    // the positional arguments, for the usage line
+    fn arguments(&self) -> &'static str {
+        match *self {
+            Command::Compare => " [STRATEGY_A STRATEGY_B]",
+            _ => "",
+        }
+    }
+
+    fn options(&self) -> Options {
+        let mut opts = Options::new();
+        add_help_option(&mut opts);
+        match *self {
+            Command::Simulate => {
+                add_logging_options(&mut opts);
+                add_run_options(&mut opts);
+                add_game_options(&mut opts);
+                add_strategy_options(&mut opts, true);
+                add_output_options(&mut opts);
+                add_simulate_options(&mut opts);
+            }
+            Command::ResultsTable => {
+                add_progress_option(&mut opts);
+                opts.optflag("", "write",
+                             "Update the results table in README.md instead of printing it");
+            }
+            Command::Replay => {
+                add_logging_options(&mut opts);
+                add_seed_option(&mut opts);
+                add_game_options(&mut opts);
+                add_strategy_options(&mut opts, true);
+                add_replay_options(&mut opts);
+            }
+            Command::Compare => {
+                add_logging_options(&mut opts);
+                add_run_options(&mut opts);
+                add_game_options(&mut opts);
+                add_strategy_options(&mut opts, false);
+                add_output_options(&mut opts);
+                add_crossplay_option(&mut opts);
+            }
+            Command::Analyze => {
+                add_logging_options(&mut opts);
+                add_run_options(&mut opts);
+                add_game_options(&mut opts);
+                add_strategy_options(&mut opts, true);
+                add_analyze_options(&mut opts);
+            }
         }
+        opts
     }
 }
 
+fn print_usage(program: &str, command: Option<Command>, opts: &Options) {
+    let brief = match command {
+        Some(command) => format!("Usage: {} {} [options]{}\n\n{}.",
+                                 program, command.name(), command.arguments(), command.description()),
+        None => {
+            let commands = COMMANDS.iter().map(|command| {
+                format!("    {:15} {}", command.name(), command.description())
+            }).collect::<Vec<_>>();
+            format!("Usage: {} [COMMAND] [options]\n\nCommands:\n{}\n\n\
+                     Run {} COMMAND --help for the options of each command.\n\
+                     Without a command, every option below is accepted, and runs `simulate` unless it says otherwise.",
+                    program, commands.join("\n"), program)
+        }
+    };
+    print!("{}", opts.usage(&brief));
+}
 
-fn print_usage(program: &str, opts: Options) {
-    print!("{}", opts.usage(&format!("Usage: {} [options]", program)));
+// What went wrong with a run, reported as a message instead of a panic
+#[derive(Debug)]
+enum CliError {
+    // the arguments don't make sense
+    Usage(String),
+    // the arguments were fine, but something else failed, like reading a file
+    Failed(String),
+}
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::Usage(ref message) => f.write_str(message),
+            CliError::Failed(ref message) => f.write_str(message),
+        }
+    }
+}
+impl From<String> for CliError {
+    fn from(message: String) -> CliError {
+        CliError::Failed(message)
+    }
+}
+
+fn usage_error<T>(message: String) -> Result<T, CliError> {
+    Err(CliError::Usage(message))
 }
 
+// the value of an option, if it was given
+fn parse_opt<T: FromStr>(matches: &Matches, name: &str) -> Result<Option<T>, CliError> {
+    match matches.opt_str(name) {
+        Some(value_str) => match T::from_str(&value_str) {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => usage_error(format!("Invalid value for --{}: {}", name, value_str)),
+        },
+        None => Ok(None),
+    }
+}
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let program = args[0].clone();
+    let (command, args) = match args.get(1).and_then(|arg| Command::from_name(arg)) {
+        Some(command) => (Some(command), &args[2..]),
+        None => (None, &args[1..]),
+    };
+    if let Err(err) = run(&program, command, args) {
+        eprintln!("Error: {}", err);
+        if let CliError::Usage(_) = err {
+            match command {
+                Some(command) => eprintln!("Run {} {} --help for the list of options", program, command.name()),
+                None => eprintln!("Run {} --help for the list of options", program),
+            }
+        }
+        std::process::exit(1);
+    }
+}
 
-    let mut opts = Options::new();
+fn add_help_option(opts: &mut Options) {
+    opts.optflag("h", "help",
+                 "Print this help menu");
+}
+
+fn add_logging_options(opts: &mut Options) {
     opts.optopt("l", "loglevel",
                 "Log level, one of 'trace', 'debug', 'info', 'warn', and 'error'",
                 "LOGLEVEL");
+    opts.optopt("", "log-seed",
+                "Also log the game with this seed at debug level, e.g. to follow one game in a large run",
+                "SEED");
+}
+
+fn add_progress_option(opts: &mut Options) {
+    opts.optflag("", "progress",
+                 "Show a progress bar on stderr, with the games per second, time remaining, and average score so far");
+}
+
+fn add_seed_option(opts: &mut Options) {
+    opts.optopt("s", "seed",
+                "Seed for PRNG (default random)",
+                "SEED");
+}
+
+// options for commands that play many games
+fn add_run_options(opts: &mut Options) {
     opts.optopt("n", "ntrials",
                 "Number of games to simulate (default 1)",
                 "NTRIALS");
     opts.optopt("o", "output",
                 "Number of games after which to print an update",
                 "OUTPUT_FREQ");
+    add_progress_option(opts);
     opts.optopt("t", "nthreads",
                 "Number of threads to use for simulation (default 1)",
                 "NTHREADS");
-    opts.optopt("s", "seed",
-                "Seed for PRNG (default random)",
-                "SEED");
+    add_seed_option(opts);
+    opts.optopt("", "shard",
+                "Only simulate the K-th of N equal slices of the seeds, for splitting a run across machines",
+                "K/N");
+    opts.optmulti("", "deal-filter",
+                  "Only simulate deals matching a filter: 'opening-fives:N' (at least N fives \
+                  in the opening hands) or 'critical-at-bottom:N' (a critical card among the \
+                  last N cards drawn).  May be given multiple times",
+                  "FILTER");
+}
+
+fn add_game_options(opts: &mut Options) {
     opts.optopt("p", "nplayers",
                 "Number of players",
                 "NPLAYERS");
-    opts.optopt("g", "strategy",
-                "Which strategy to use.  One of 'random', 'cheat', and 'info'",
+    opts.optopt("", "hand-size",
+                "Number of cards in each hand (default depends on the number of players)",
+                "HAND_SIZE");
+    opts.optopt("", "endgame-rule",
+                "What happens once the deck runs out: 'final-round' (default), where everyone \
+                gets one more turn, or 'until-stuck', where play continues until no card in \
+                anyone's hand is playable",
+                "RULE");
+    opts.optopt("", "misplay-rule",
+                "What happens to a misplayed card: 'discard' (default), as in the official rules, or \
+                'deck-bottom', where it goes back to the bottom of the deck to be drawn again",
+                "RULE");
+    opts.optopt("", "illegal-moves",
+                "What to do when a strategy makes an illegal move: 'panic' (default), 'forfeit', \
+                which scores the game 0 and reports its seed, or 'retry', which asks the player \
+                again a few times before forfeiting",
+                "POLICY");
+    opts.optflag("", "allow-empty-hints",
+                 "Allow hints that match none of the hinted player's cards");
+    opts.optopt("", "starting-hints",
+                "Number of hints at the start of the game (default 8, the maximum)",
+                "N");
+    opts.optopt("", "discard-hints",
+                "Hints regained by discarding, in multiples of 0.5 (default 1)",
+                "HINTS");
+    opts.optopt("", "firework-hints",
+                "Hints regained by completing a firework, in multiples of 0.5 (default 1)",
+                "HINTS");
+    opts.optflag("", "strikeout-scores-zero",
+                 "Score games that lose all their lives as 0");
+    opts.optflag("", "end-when-settled",
+                 "End each game as soon as its score can no longer improve, to save time on large runs");
+}
+
+fn add_strategy_options(opts: &mut Options, choose_strategy: bool) {
+    if choose_strategy {
+        opts.optopt("g", "strategy",
+                    "Which strategy to use.  One of the baselines 'random', 'play-first', 'discard-oldest' and \
+                    'beginner', or 'cheat', 'semi-cheat', 'cheat-search', and 'info', or 'auto' for the best \
+                    known for the number of players, \
+                    optionally with '+mc' to search by sampling what its hand could be (e.g. 'info+mc'), \
+                    and followed by wrappers, e.g. 'info|noisy:0.05' or 'info|adaptive:2' (see README)",
+                    "STRATEGY");
+    }
+    opts.optopt("", "strategy-config",
+                "File of strategy parameter overrides, with lines of the form `name = value`",
+                "FILE");
+    opts.optmulti("", "strategy-opt",
+                  "Override a strategy parameter, e.g. `risky_play_threshold=0.8`.  Applied after \
+                  --strategy-config, and may be given multiple times",
+                  "NAME=VALUE");
+    opts.optflag("", "validate",
+                 "Check that strategies make legal moves and agree on public information after every turn");
+}
+
+// files written for every game played
+fn add_output_options(opts: &mut Options) {
+    opts.optopt("j", "json-output",
+                "Write each game in hanab.live's JSON format to PATTERN, with %s replaced by the seed and %d by \
+                the seed divided by 1000 (a directory per 1000 games), or to a single stream of games, one per \
+                line, if PATTERN ends in .jsonl (or .jsonl.gz, with the gzip feature)",
+                "PATTERN");
+    opts.optflag("", "json-empathy",
+                 "With --json-output, also record what the acting strategy believes everyone knows about \
+                 every card after each turn, for strategies that keep track");
+    opts.optopt("", "dump-training",
+                "Write every decision, with the deciding player's view and the final score, to DIR/<seed>.ndjson",
+                "DIR");
+}
+
+fn add_simulate_options(opts: &mut Options) {
+    opts.optopt("", "checkpoint",
+                "Save the results so far to FILE as the games are played, and resume from it if it \
+                already exists, so an interrupted run can pick up where it left off.  Requires a seed (-s)",
+                "FILE");
+    opts.optopt("", "checkpoint-every",
+                "With --checkpoint, the number of games between saves (default 10000)",
+                "NGAMES");
+    opts.optopt("", "stop-when-significant",
+                "Play the games in batches, and stop once the average score differs from BASELINE_SCORE \
+                at the --significance level, e.g. to cut short runs of a tweak that clearly helps or hurts",
+                "BASELINE_SCORE");
+    opts.optopt("", "significance",
+                "With --stop-when-significant, the largest chance of stopping when the average score \
+                is really the baseline (default 0.01)",
+                "P");
+    opts.optopt("", "significance-every",
+                "With --stop-when-significant, the number of games between checks (default 1000)",
+                "NGAMES");
+    opts.optflag("", "discard-loss",
+                 "Also report how many points each game lost by discarding or misplaying cards that were still needed, \
+                 and how often discards were of a card with another copy still in the deck, or of the last copy");
+    opts.optflagopt("", "tempo",
+                    "Also report how long games lasted and their pace (how many more cards could be discarded \
+                    without running out of time), with the average pace every N turns (default 5)",
+                    "N");
+    opts.optflagopt("", "stack-curves",
+                    "Also report the average turn on which each firework reached each value, and the average \
+                    score every N turns (default 5), to tell slow starts from endgame collapses",
+                    "N");
+    opts.optflag("", "hint-stats",
+                 "Also report what hints said (color or value, and which), how many seats away they went, \
+                 and how many cards they touched");
+    opts.optopt("", "stats-output",
+                "Write the results to FILE, as CSV if it ends in .csv and as JSON otherwise",
+                "FILE");
+}
+
+fn add_replay_options(opts: &mut Options) {
+    opts.optopt("", "html-replay",
+                "Play the game with the given seed and write a step-through HTML replay of it",
+                "FILE");
+    opts.optflag("", "watch",
+                 "Play the game with the given seed in the terminal, one turn per press of Enter");
+    opts.optopt("", "deck-file",
+                "Play one game on the deck in FILE: either a hanab.live JSON export (which also sets \
+                the number of players), or cards like 'r1 y5 b2 ...' in the order they are drawn",
+                "FILE");
+    opts.optopt("", "debug-seed",
+                "Play the game with this seed in an interactive debugger, to step through it and \
+                inspect what each strategy knows",
+                "SEED");
+    opts.optopt("", "break-turn",
+                "With --debug-seed, play up to this turn before stopping (default 1)",
+                "TURN");
+    opts.optopt("", "render",
+                "How --watch and --debug-seed show cards: 'plain' (r1), 'ansi', in color (the \
+                default for --watch), or 'unicode', with a suit symbol for each color (♥1)",
+                "STYLE");
+}
+
+fn add_crossplay_option(opts: &mut Options) {
+    opts.optflag("", "crossplay",
+                 "Play every strategy with every other strategy (and itself) in 2-player games, \
+                 on the same seeds, and print a matrix of the average scores");
+}
+
+fn add_analyze_options(opts: &mut Options) {
+    opts.optopt("", "describe-strategy",
+                "Print a strategy's conventions as markdown, with any --strategy-opt overrides applied",
                 "STRATEGY");
-    opts.optflag("h", "help",
-                 "Print this help menu");
+    opts.optopt("", "detective",
+                "Replay a game from a hanab.live JSON file with the chosen strategy, reporting what its \
+                conventions say about each hidden card and flagging moves it wouldn't have made",
+                "FILE");
+    opts.optopt("", "divergence-check",
+                "Replay the games recorded with --json-output in DIR (or a single such file) with the chosen \
+                strategy, reporting the first move in each that it would now make differently",
+                "DIR");
+    opts.optopt("", "find-hard-seeds",
+                "Simulate games as usual, then list the N seeds with the lowest scores (ties going to \
+                games that ended soonest), to build a corpus of difficult deals",
+                "N");
+    opts.optopt("", "find-puzzles",
+                "Simulate games as usual, then look through their last turns for puzzles: positions where \
+                exactly one move lets the cheating strategy, seeing every card and the deck, still reach the \
+                best score left.  Writes them to FILE as JSON, with their solutions",
+                "FILE");
+    opts.optopt("", "puzzle-deck-size",
+                "With --find-puzzles, only look at positions with at most N cards left in the deck (default 5)",
+                "N");
+    opts.optopt("", "sweep",
+                "Play the same seeds with each configuration of strategy parameters in FILE (a [grid] or \
+                [random] section, in TOML), and print the configurations ranked by average score",
+                "FILE");
+    opts.optopt("", "verify-with",
+                "With --find-hard-seeds, also report how another strategy scores on each hard seed",
+                "STRATEGY");
+}
+
+// every option of every command, for runs without a command
+fn get_flag_options() -> Options {
+    let mut opts = Options::new();
+    add_help_option(&mut opts);
+    add_logging_options(&mut opts);
+    add_run_options(&mut opts);
+    add_game_options(&mut opts);
+    add_strategy_options(&mut opts, true);
+    add_output_options(&mut opts);
+    add_simulate_options(&mut opts);
+    add_replay_options(&mut opts);
+    add_crossplay_option(&mut opts);
+    opts.optopt("", "compare",
+                "Run two strategies on the same seeds and report paired statistics",
+                "STRATEGY_A,STRATEGY_B");
+    add_analyze_options(&mut opts);
     opts.optflag("", "results-table",
                  "Print a table of results for each strategy");
     opts.optflag("", "write-results-table",
                  "Update the results table in README.md");
-    let matches = match opts.parse(&args[1..]) {
+    opts
+}
+
+fn run(program: &str, command: Option<Command>, args: &[String]) -> Result<(), CliError> {
+    let opts = match command {
+        Some(command) => command.options(),
+        None => get_flag_options(),
+    };
+    let matches = match opts.parse(args) {
         Ok(m) => { m }
-        Err(f) => {
-            print_usage(&program, opts);
-            panic!(f.to_string())
-        }
+        Err(f) => return usage_error(f.to_string()),
     };
     if matches.opt_present("h") {
-        return print_usage(&program, opts);
+        return Ok(print_usage(program, command, &opts));
     }
-    if !matches.free.is_empty() {
-        return print_usage(&program, opts);
+    if command != Some(Command::Compare) && !matches.free.is_empty() {
+        return usage_error(format!("Unexpected argument {}", matches.free[0]));
     }
+    match command {
+        Some(Command::Simulate) => simulate(&matches),
+        Some(Command::ResultsTable) => {
+            if matches.opt_present("write") {
+                write_results_table(matches.opt_present("progress"))
+            } else {
+                Ok(print!("{}", get_results_table(matches.opt_present("progress"))))
+            }
+        }
+        Some(Command::Replay) => replay(&matches),
+        Some(Command::Compare) => {
+            if matches.opt_present("crossplay") {
+                if !matches.free.is_empty() {
+                    return usage_error(String::from("--crossplay plays every strategy, so takes no strategies"));
+                }
+                return crossplay(&matches);
+            }
+            match &matches.free[..] {
+                &[ref a, ref b] => compare(&matches, a, b),
+                _ => usage_error(String::from("Expected two strategies to compare, or --crossplay")),
+            }
+        }
+        Some(Command::Analyze) => analyze(&matches),
+        None => run_flags(&matches),
+    }
+}
+
+// Runs without a command, where the flags decide what to do
+fn run_flags(matches: &Matches) -> Result<(), CliError> {
     if matches.opt_present("write-results-table") {
-        return write_results_table();
+        return write_results_table(matches.opt_present("progress"));
     }
     if matches.opt_present("results-table") {
-        return print!("{}", get_results_table());
+        return Ok(print!("{}", get_results_table(matches.opt_present("progress"))));
+    }
+    if matches.opt_present("html-replay") || matches.opt_present("watch") || matches.opt_present("deck-file") ||
+        matches.opt_present("debug-seed") {
+        return replay(matches);
+    }
+    if matches.opt_present("describe-strategy") || matches.opt_present("detective") ||
+        matches.opt_present("divergence-check") {
+        return analyze(matches);
+    }
+    if matches.opt_present("crossplay") {
+        return crossplay(matches);
+    }
+    if matches.opt_present("find-hard-seeds") || matches.opt_present("find-puzzles") || matches.opt_present("sweep") {
+        return analyze(matches);
     }
+    if let Some(compare_str) = matches.opt_str("compare") {
+        let names = compare_str.split(',').collect::<Vec<_>>();
+        if names.len() != 2 {
+            return usage_error(format!("Expected two comma-separated strategies to compare, got {}", compare_str));
+        }
+        return compare(matches, names[0], names[1]);
+    }
+    simulate(matches)
+}
 
+fn init_logging(matches: &Matches) -> Result<(), CliError> {
     let log_level_str : &str = &matches.opt_str("l").unwrap_or("info".to_string());
     let log_level = match log_level_str {
         "trace" => { log::LogLevelFilter::Trace }
@@ -98,82 +530,941 @@ fn main() {
         "info"  => { log::LogLevelFilter::Info }
         "warn"  => { log::LogLevelFilter::Warn }
         "error" => { log::LogLevelFilter::Error }
-        _       => {
-            print_usage(&program, opts);
-            panic!("Unexpected log level argument {}", log_level_str);
-        }
+        _       => return usage_error(format!("Unexpected log level argument {}", log_level_str)),
     };
 
+    let log_seed = try!(parse_opt::<u32>(matches, "log-seed"));
+    spans::set_verbose_seed(log_seed);
     log::set_logger(|max_log_level| {
-        max_log_level.set(log_level);
-        Box::new(SimpleLogger)
+        // the logger itself decides which debug lines to print from the --log-seed game
+        max_log_level.set(if log_seed.is_some() { std::cmp::max(log_level, log::LogLevelFilter::Debug) } else { log_level });
+        Box::new(SimpleLogger { level: log_level })
     }).unwrap();
+    Ok(())
+}
 
-    let n_trials = u32::from_str(&matches.opt_str("n").unwrap_or("1".to_string())).unwrap();
-    let seed = matches.opt_str("s").map(|seed_str| { u32::from_str(&seed_str).unwrap() });
-    let progress_info = matches.opt_str("o").map(|freq_str| { u32::from_str(&freq_str).unwrap() });
-    let n_threads = u32::from_str(&matches.opt_str("t").unwrap_or("1".to_string())).unwrap();
-    let n_players = u32::from_str(&matches.opt_str("p").unwrap_or("4".to_string())).unwrap();
-    let strategy_str : &str = &matches.opt_str("g").unwrap_or("cheat".to_string());
+// Which games to play, and how, for the commands that play many
+struct RunOptions {
+    seed: Option<u32>,
+    n_trials: u32,
+    n_threads: u32,
+    progress_info: Option<u32>,
+    progress_bar: bool,
+    deal_filters: Vec<simulator::DealFilter>,
+}
+impl RunOptions {
+    fn parse(matches: &Matches) -> Result<RunOptions, CliError> {
+        let mut n_trials = try!(parse_opt(matches, "ntrials")).unwrap_or(1);
+        if n_trials == 0 {
+            return usage_error(String::from("--ntrials must be at least 1"));
+        }
+        let mut seed = try!(parse_opt::<u32>(matches, "seed"));
+        if let Some(first_seed) = seed {
+            if first_seed.checked_add(n_trials).is_none() {
+                return usage_error(format!("Seeds from {} for {} trials go past the largest seed, {}",
+                                           first_seed, n_trials, u32::max_value()));
+            }
+        }
+        if let Some(shard_str) = matches.opt_str("shard") {
+            let parts = shard_str.split('/').map(|part| u32::from_str(part).ok()).collect::<Vec<_>>();
+            let (shard, n_shards) = match (parts.get(0).cloned(), parts.get(1).cloned(), parts.len()) {
+                (Some(Some(shard)), Some(Some(n_shards)), 2) if 1 <= shard && shard <= n_shards => (shard, n_shards),
+                _ => return usage_error(format!("Expected --shard K/N with 1 <= K <= N, got {}", shard_str)),
+            };
+            // every machine needs to agree on the seeds being split up
+            let first_seed = match seed {
+                Some(first_seed) => first_seed,
+                None => return usage_error(String::from("--shard requires a seed (-s)")),
+            };
+            let seeds = simulator::shard_range(&(first_seed..first_seed + n_trials), shard - 1, n_shards);
+            info!("Shard {}/{}: seeds {} to {}", shard, n_shards, seeds.start, seeds.end);
+            seed = Some(seeds.start);
+            n_trials = seeds.end - seeds.start;
+        }
+        let progress_info = try!(parse_opt(matches, "output"));
+        if progress_info == Some(0) {
+            return usage_error(String::from("--output must be at least 1"));
+        }
+        let n_threads = try!(parse_opt(matches, "nthreads")).unwrap_or(1);
+        if n_threads == 0 {
+            return usage_error(String::from("--nthreads must be at least 1"));
+        }
+        let mut deal_filters = Vec::new();
+        for filter_str in matches.opt_strs("deal-filter") {
+            deal_filters.push(try!(simulator::DealFilter::from_str(&filter_str).map_err(CliError::Usage)));
+        }
+        Ok(RunOptions {
+            seed: seed,
+            n_trials: n_trials,
+            n_threads: n_threads,
+            progress_info: progress_info,
+            progress_bar: matches.opt_present("progress"),
+            deal_filters: deal_filters,
+        })
+    }
 
-    sim_games(n_players, strategy_str, seed, n_trials, n_threads, progress_info).info();
+    // the given seed, or a random one if there wasn't one
+    fn first_seed(&self) -> u32 {
+        // the seeds of every game must fit in a u32
+        self.seed.unwrap_or_else(|| rand::random::<u32>() % (u32::max_value() - self.n_trials + 1))
+    }
 }
 
-fn sim_games(n_players: u32, strategy_str: &str, seed: Option<u32>, n_trials: u32, n_threads: u32, progress_info: Option<u32>)
-    -> simulator::SimResult {
+// The rules, for `n_players` if a file being played says how many there are
+fn parse_game_options(matches: &Matches, n_players: Option<u32>) -> Result<game::GameOptions, CliError> {
+    let n_players = match n_players {
+        Some(n_players) => n_players,
+        None => try!(parse_opt(matches, "nplayers")).unwrap_or(4),
+    };
+    let endgame_rule = match &matches.opt_str("endgame-rule").unwrap_or("final-round".to_string())[..] {
+        "final-round" => { game::EndgameRule::FinalRound }
+        "until-stuck" => { game::EndgameRule::UntilStuck }
+        rule_str      => return usage_error(format!("Unexpected endgame rule argument {}", rule_str)),
+    };
+    let misplay_rule = match &matches.opt_str("misplay-rule").unwrap_or("discard".to_string())[..] {
+        "discard"     => { game::MisplayRule::Discard }
+        "deck-bottom" => { game::MisplayRule::ReturnToDeckBottom }
+        rule_str      => return usage_error(format!("Unexpected misplay rule argument {}", rule_str)),
+    };
+    let illegal_moves = match &matches.opt_str("illegal-moves").unwrap_or("panic".to_string())[..] {
+        "panic"   => { game::IllegalMovePolicy::Panic }
+        "forfeit" => { game::IllegalMovePolicy::Forfeit }
+        "retry"   => { game::IllegalMovePolicy::Retry }
+        policy_str => return usage_error(format!("Unexpected illegal move policy {}", policy_str)),
+    };
+    let mut game_opts = try!(get_game_options(n_players));
+    if let Some(hand_size) = try!(parse_opt(matches, "hand-size")) {
+        game_opts.hand_size = hand_size;
+    }
+    if game_opts.hand_size == 0 || game_opts.num_players * game_opts.hand_size > game::get_total_cards() {
+        return usage_error(format!("Cannot deal {} hands of {} cards", game_opts.num_players, game_opts.hand_size));
+    }
+    game_opts.endgame_rule = endgame_rule;
+    game_opts.misplay_rule = misplay_rule;
+    game_opts.strikeout_scores_zero = matches.opt_present("strikeout-scores-zero");
+    game_opts.allow_empty_hints = matches.opt_present("allow-empty-hints");
+    game_opts.end_when_settled = matches.opt_present("end-when-settled");
+    game_opts.illegal_moves = illegal_moves;
+    if let Some(starting_hints) = try!(parse_opt(matches, "starting-hints")) {
+        if starting_hints > game_opts.num_hints {
+            return usage_error(format!("Can't start with more than {} hints", game_opts.num_hints));
+        }
+        game_opts.starting_hints = starting_hints;
+    }
+    if let Some(hints_str) = matches.opt_str("discard-hints") {
+        game_opts.discard_half_hints = try!(parse_half_hints(&hints_str).map_err(CliError::Usage));
+    }
+    if let Some(hints_str) = matches.opt_str("firework-hints") {
+        game_opts.firework_half_hints = try!(parse_half_hints(&hints_str).map_err(CliError::Usage));
+    }
+    Ok(game_opts)
+}
+
+// the overrides from --strategy-config, then --strategy-opt
+fn parse_strategy_params(matches: &Matches) -> Result<Vec<(String, String)>, CliError> {
+    let mut strategy_params = match matches.opt_str("strategy-config") {
+        Some(path) => try!(strategy::read_params_file(&path)),
+        None => Vec::new(),
+    };
+    for opt_str in matches.opt_strs("strategy-opt") {
+        let parts = opt_str.splitn(2, '=').map(|part| part.trim()).collect::<Vec<_>>();
+        if parts.len() != 2 || parts[0].is_empty() {
+            return usage_error(format!("Expected --strategy-opt NAME=VALUE, got {}", opt_str));
+        }
+        strategy_params.push((parts[0].to_string(), parts[1].to_string()));
+    }
+    Ok(strategy_params)
+}
+
+fn strategy_name(matches: &Matches) -> String {
+    matches.opt_str("g").unwrap_or("cheat".to_string())
+}
+
+// The files to write for each game
+struct Outputs {
+    json_writer: Option<json_output::Writer>,
+    json_empathy: bool,
+    training_dir: Option<String>,
+}
+impl Outputs {
+    fn parse(matches: &Matches, game_opts: &game::GameOptions) -> Result<Outputs, CliError> {
+        Outputs::parse_in(matches, game_opts, None)
+    }
+
+    // With a subdirectory, e.g. one per strategy compared, each file goes in a directory of that
+    // name, beside where it would otherwise go
+    fn parse_in(matches: &Matches, game_opts: &game::GameOptions, subdir: Option<&str>) -> Result<Outputs, CliError> {
+        let json_pattern = matches.opt_str("j");
+        let json_empathy = matches.opt_present("json-empathy");
+        if json_empathy && json_pattern.is_none() {
+            return usage_error(String::from("--json-empathy requires --json-output"));
+        }
+        if json_pattern.is_some() && game_opts.misplay_rule != game::MisplayRule::Discard {
+            return usage_error(String::from("hanab.live's format can't record misplays going back into \
+                                             the deck, so --json-output needs --misplay-rule discard"));
+        }
+        let json_writer = match (json_pattern, subdir) {
+            (Some(pattern), Some(subdir)) => Some(try!(json_output::Writer::new(&try!(in_subdir(&pattern, subdir))))),
+            (Some(pattern), None) => Some(try!(json_output::Writer::new(&pattern))),
+            (None, _) => None,
+        };
+        let training_dir = match (matches.opt_str("dump-training"), subdir) {
+            (Some(dir), Some(subdir)) => Some(Path::new(&dir).join(subdir).to_string_lossy().into_owned()),
+            (training_dir, _) => training_dir,
+        };
+        if let Some(ref dir) = training_dir {
+            try!(std::fs::create_dir_all(dir).map_err(|err| format!("Couldn't create {}: {}", dir, err)));
+        }
+        Ok(Outputs {
+            json_writer: json_writer,
+            json_empathy: json_empathy,
+            training_dir: training_dir,
+        })
+    }
+
+    fn any(&self) -> bool {
+        self.json_writer.is_some() || self.training_dir.is_some()
+    }
+
+    // once every game has been played
+    fn finish(&self) -> Result<(), CliError> {
+        if let Some(ref writer) = self.json_writer {
+            try!(writer.finish());
+        }
+        Ok(())
+    }
+
+    fn observer(&self, version: &version::ArtifactVersion) -> Box<observer::GameObserver> {
+        let mut observers: Vec<Box<observer::GameObserver>> = vec![Box::new(observer::LogObserver)];
+        if let Some(ref writer) = self.json_writer {
+            observers.push(Box::new(json_output::JsonObserver::new(writer.clone(), version.clone(), self.json_empathy)));
+        }
+        if let Some(ref dir) = self.training_dir {
+            observers.push(Box::new(training::TrainingDataObserver::new(dir, version.clone())));
+        }
+        Box::new(observers)
+    }
+}
+
+// `path` with `subdir` inserted before its file name, creating that directory unless the writer
+// will, for a pattern with a directory per 1000 games
+fn in_subdir(path: &str, subdir: &str) -> Result<String, CliError> {
+    let path = Path::new(path);
+    let dir = path.parent().unwrap_or(Path::new("")).join(subdir);
+    if !dir.to_string_lossy().contains("%d") {
+        try!(std::fs::create_dir_all(&dir).map_err(|err| format!("Couldn't create {}: {}", dir.display(), err)));
+    }
+    let file_name = path.file_name().ok_or_else(|| format!("{} doesn't name a file", path.display()));
+    Ok(dir.join(try!(file_name)).to_string_lossy().into_owned())
+}
+
+fn simulate(matches: &Matches) -> Result<(), CliError> {
+    try!(init_logging(matches));
+    let run = try!(RunOptions::parse(matches));
+    let checkpoint = match matches.opt_str("checkpoint") {
+        Some(path) => {
+            // a resumed run has to play exactly the same seeds
+            if run.seed.is_none() {
+                return usage_error(String::from("--checkpoint requires a seed (-s)"));
+            }
+            let every = try!(parse_opt(matches, "checkpoint-every")).unwrap_or(10000);
+            if every == 0 {
+                return usage_error(String::from("--checkpoint-every must be at least 1"));
+            }
+            Some((path, every))
+        }
+        None => None,
+    };
+    let stop_when_significant = match try!(parse_opt::<f64>(matches, "stop-when-significant")) {
+        Some(baseline) => {
+            if checkpoint.is_some() {
+                return usage_error(String::from("--stop-when-significant can't be used with --checkpoint"));
+            }
+            let level = try!(parse_opt::<f64>(matches, "significance")).unwrap_or(0.01);
+            if !(0.0 < level && level < 1.0) {
+                return usage_error(String::from("--significance must be between 0 and 1"));
+            }
+            let every = try!(parse_opt(matches, "significance-every")).unwrap_or(1000);
+            if every < 2 {
+                return usage_error(String::from("--significance-every must be at least 2"));
+            }
+            Some((baseline, level, every))
+        }
+        None => None,
+    };
+    let game_opts = try!(parse_game_options(matches, None));
+    let strategy_str = &strategy_name(matches)[..];
+    let strategy_params = try!(parse_strategy_params(matches));
+    let validate = matches.opt_present("validate");
+    let outputs = try!(Outputs::parse(matches, &game_opts));
+    let strategy_config = try!(get_supported_strategy_config(strategy_str, &strategy_params, false, &game_opts));
+    let artifact_version = version::ArtifactVersion::new(strategy_str, strategy_config.version());
+    let make_observer = |_| outputs.observer(&artifact_version);
+    let observer_factory = if outputs.any() { Some(&make_observer as &simulator::ObserverFactory) } else { None };
+    // the analyses of each game that will be shown, or written to --stats-output
+    let stats_output = matches.opt_present("stats-output");
+    let analyses = simulator::Analyses {
+        play_stats: true,
+        discard_risk: matches.opt_present("discard-loss") || stats_output,
+        tempo: matches.opt_present("tempo"),
+        stack_curves: matches.opt_present("stack-curves"),
+        hint_stats: matches.opt_present("hint-stats"),
+    };
+
+    let seed = run.first_seed();
+    let result = match (checkpoint, stop_when_significant) {
+        (Some((ref path, every)), _) => try!(sim_games_with_checkpoint(
+            &game_opts, strategy_str, seed, run.n_trials, run.n_threads, run.progress_info, run.progress_bar,
+            &run.deal_filters, &strategy_params, validate, observer_factory, &artifact_version, path, every)),
+        (None, Some((baseline, level, every))) => try!(sim_games_until_significant(
+            &game_opts, strategy_str, seed, run.n_trials, run.n_threads, run.progress_info, run.progress_bar,
+            &run.deal_filters, &strategy_params, validate, observer_factory, analyses, baseline, level, every)),
+        (None, None) => try!(sim_games(&game_opts, strategy_str, Some(seed), run.n_trials, run.n_threads,
+                               run.progress_info, run.progress_bar, &run.deal_filters, None,
+                               &strategy_params, validate, observer_factory, analyses)),
+    };
+    try!(outputs.finish());
+    result.info();
+    if matches.opt_present("discard-loss") {
+        result.discard_loss_info();
+        result.discard_risk_info();
+    }
+    if matches.opt_present("tempo") {
+        let every = match try!(parse_opt::<usize>(matches, "tempo")) {
+            Some(0) => return usage_error(String::from("--tempo takes a positive number of turns")),
+            Some(every) => every,
+            None => 5,
+        };
+        result.tempo_info(every);
+    }
+    if matches.opt_present("stack-curves") {
+        let every = match try!(parse_opt::<usize>(matches, "stack-curves")) {
+            Some(0) => return usage_error(String::from("--stack-curves takes a positive number of turns")),
+            Some(every) => every,
+            None => 5,
+        };
+        result.stack_curves_info(every);
+    }
+    if matches.opt_present("hint-stats") {
+        result.hint_stats_info();
+    }
+    if let Some(path) = matches.opt_str("stats-output") {
+        try!(stats_output::write(&path, &result, &stats_output::RunMetadata::new(strategy_str, &game_opts)));
+        info!("Wrote results to {}", path);
+    }
+    Ok(())
+}
+
+fn replay(matches: &Matches) -> Result<(), CliError> {
+    try!(init_logging(matches));
+    let seed = try!(parse_opt::<u32>(matches, "seed"));
+    let deck_file = match matches.opt_str("deck-file") {
+        Some(path) => Some(try!(read_deck_file(&path))),
+        None => None,
+    };
+    let game_opts = try!(parse_game_options(matches, deck_file.as_ref().and_then(|&(_, n_players)| n_players)));
+    let strategy_str = &strategy_name(matches)[..];
+    let strategy_params = try!(parse_strategy_params(matches));
+    let validate = matches.opt_present("validate");
+    let strategy_config = try!(get_supported_strategy_config(strategy_str, &strategy_params, validate, &game_opts));
+
+    let render_style = match matches.opt_str("render") {
+        None => None,
+        Some(style_str) => Some(match &style_str[..] {
+            "plain"   => { render::RenderStyle::Plain }
+            "ansi"    => { render::RenderStyle::Ansi }
+            "unicode" => { render::RenderStyle::Unicode }
+            _ => return usage_error(format!("Unexpected render style {}", style_str)),
+        }),
+    };
+
+    if let Some(debug_seed) = try!(parse_opt::<u32>(matches, "debug-seed")) {
+        let break_turn = try!(parse_opt(matches, "break-turn")).unwrap_or(1);
+        let mut debugger = debugger::Debugger::new(&game_opts, strategy_config.initialize(&game_opts), debug_seed);
+        debugger.add_breakpoint(break_turn);
+        debugger.set_style(render_style.unwrap_or(render::RenderStyle::Plain));
+        let played = if break_turn > debugger.game().board.turn { debugger.continue_to_breakpoint().len() } else { 0 };
+        println!("Played {} turns of seed {}.  Type `help` for the list of commands", played, debug_seed);
+        let stdin = std::io::stdin();
+        return Ok(try!(debugger.run(stdin.lock(), std::io::stdout())));
+    }
+    if matches.opt_present("break-turn") {
+        return usage_error(String::from("--break-turn requires --debug-seed"));
+    }
+
+    if let Some(path) = matches.opt_str("html-replay") {
+        let seed = match seed {
+            Some(seed) => seed,
+            None => return usage_error(String::from("--html-replay requires a seed (-s)")),
+        };
+        return write_html_replay(&game_opts, strategy_str, strategy_config, seed, &path);
+    }
+
+    if matches.opt_present("watch") {
+        let seed = match seed {
+            Some(seed) => seed,
+            None => return usage_error(String::from("--watch requires a seed (-s)")),
+        };
+        simulator::simulate_once_observed(&game_opts, strategy_config.initialize(&game_opts), seed,
+                                          &mut watch::WatchObserver::new(render_style.unwrap_or(render::RenderStyle::Ansi)));
+        return Ok(());
+    }
+
+    let game = match (deck_file, seed) {
+        (Some((deck, _)), _) => simulator::simulate_once_with_deck(&game_opts, strategy_config.initialize(&game_opts), deck),
+        (None, Some(seed)) => simulator::simulate_once(&game_opts, strategy_config.initialize(&game_opts), seed),
+        (None, None) => return usage_error(String::from("replay requires a seed (-s) or --deck-file")),
+    };
+    info!("Score: {} ({})", game.score(), game.board.end_cause().expect("The game should be over"));
+    Ok(())
+}
+
+fn compare(matches: &Matches, name_a: &str, name_b: &str) -> Result<(), CliError> {
+    try!(init_logging(matches));
+    let run = try!(RunOptions::parse(matches));
+    let game_opts = try!(parse_game_options(matches, None));
+    let strategy_params = try!(parse_strategy_params(matches));
+    let validate = matches.opt_present("validate");
+    // both strategies must see exactly the same deals
+    let seed = run.first_seed();
+    let decks = simulator::DeckCache::new();
+    let mut results = Vec::new();
+    for name in [name_a, name_b].iter() {
+        let config = try!(get_supported_strategy_config(name, &strategy_params, false, &game_opts));
+        // each strategy's games go in a directory of its own, so neither overwrites the other's
+        let outputs = try!(Outputs::parse_in(matches, &game_opts, Some(name)));
+        let artifact_version = version::ArtifactVersion::new(name, config.version());
+        let make_observer = |_| outputs.observer(&artifact_version);
+        let observer_factory = if outputs.any() { Some(&make_observer as &simulator::ObserverFactory) } else { None };
+        let result = try!(sim_games(&game_opts, name, Some(seed), run.n_trials, run.n_threads, run.progress_info,
+                                    run.progress_bar, &run.deal_filters, Some(&decks), &strategy_params, validate,
+                                    observer_factory, simulator::Analyses::none()));
+        info!("{}: average score {:.4} ± {:.4}, {:.2} ± {:.2} % perfect", name,
+              result.average_score(), result.score_stderr(),
+              result.percent_perfect(), result.percent_perfect_stderr());
+        try!(outputs.finish());
+        results.push(result);
+    }
+    Ok(simulator::Comparison::new(&results[0], &results[1]).info(name_a, name_b))
+}
+
+fn crossplay(matches: &Matches) -> Result<(), CliError> {
+    try!(init_logging(matches));
+    let run = try!(RunOptions::parse(matches));
+    let game_opts = try!(parse_game_options(matches, None));
+    if game_opts.num_players != 2 {
+        return usage_error(String::from("--crossplay is only for 2-player games (-p 2)"));
+    }
+    // every strategy plays, and few parameters mean anything to more than one of them
+    if !try!(parse_strategy_params(matches)).is_empty() {
+        return usage_error(String::from("--crossplay plays every strategy as it is by default, so can't take \
+                                         --strategy-opt or --strategy-config"));
+    }
+    let validate = matches.opt_present("validate");
+    for name in STRATEGY_NAMES.iter() {
+        try!(get_strategy_config(name, &[], validate));
+    }
+    let make_config = |name: &str| get_strategy_config(name, &[], validate).expect("Checked above");
+    let crossplay = crossplay::crossplay(&game_opts, &STRATEGY_NAMES, &make_config, run.first_seed(),
+                                         run.n_trials, run.n_threads, &run.deal_filters);
+    Ok(crossplay.info())
+}
+
+fn analyze(matches: &Matches) -> Result<(), CliError> {
+    try!(init_logging(matches));
+    let strategy_str = &strategy_name(matches)[..];
+    let strategy_params = try!(parse_strategy_params(matches));
+    let validate = matches.opt_present("validate");
+
+    if let Some(name) = matches.opt_str("describe-strategy") {
+        let strategy_config = try!(get_strategy_config(&name, &strategy_params, false));
+        return match strategy_config.conventions() {
+            Some(conventions) => Ok(print!("{}", conventions.to_markdown())),
+            None => usage_error(format!("The {} strategy can't describe its conventions", name)),
+        };
+    }
+
+    if let Some(path) = matches.opt_str("detective") {
+        let transcript = try!(json_output::Transcript::read(&path));
+        let game_opts = try!(parse_game_options(matches, Some(transcript.num_players)));
+        let strategy_config = try!(get_supported_strategy_config(strategy_str, &strategy_params, validate, &game_opts));
+        if let Some(ref transcript_version) = transcript.version {
+            let version = version::ArtifactVersion::new(strategy_str, strategy_config.version());
+            if let Err(err) = transcript_version.check_compatible(&version) {
+                warn!("{}", err);
+            }
+        }
+        let choices = try!(transcript.choices(game_opts.hand_size));
+        let investigation = detective::investigate(&game_opts, strategy_config.initialize(&game_opts),
+                                                   transcript.seed.unwrap_or(0), transcript.engine_deck(),
+                                                   &choices);
+        return Ok(detective::log_investigation(&investigation, 3));
+    }
+
+    if let Some(path) = matches.opt_str("divergence-check") {
+        return divergence_check(matches, &path, strategy_str, &strategy_params, validate);
+    }
+
+    if let Some(path) = matches.opt_str("sweep") {
+        return sweep(matches, &path, strategy_str, &strategy_params, validate);
+    }
+
+    if let Some(path) = matches.opt_str("find-puzzles") {
+        return find_puzzles(matches, &path, strategy_str, &strategy_params, validate);
+    }
+
+    let n_hard_seeds = match try!(parse_opt::<usize>(matches, "find-hard-seeds")) {
+        Some(n_hard_seeds) => n_hard_seeds,
+        None => return usage_error(String::from("Expected --describe-strategy STRATEGY, --detective FILE, --divergence-check DIR, --find-hard-seeds N, --find-puzzles FILE or --sweep FILE")),
+    };
+    let run = try!(RunOptions::parse(matches));
+    let game_opts = try!(parse_game_options(matches, None));
+    let strategy_config = try!(get_supported_strategy_config(strategy_str, &strategy_params, validate, &game_opts));
+    let verifier = match matches.opt_str("verify-with") {
+        Some(name) => {
+            let config = try!(get_supported_strategy_config(&name, &[], validate, &game_opts));
+            Some((name, config))
+        }
+        None => None,
+    };
+    let hard_seeds = simulator::find_hard_seeds(&game_opts, strategy_config, Some(run.first_seed()), run.n_trials,
+                                                run.n_threads, &run.deal_filters, n_hard_seeds);
+    info!("The {} hardest seeds for the {} strategy:", hard_seeds.len(), strategy_str);
+    for hard_seed in hard_seeds.iter() {
+        let verified = match verifier {
+            Some((ref name, ref config)) => {
+                let game = simulator::simulate_once(&game_opts, config.initialize(&game_opts), hard_seed.seed);
+                format!(", {} scores {}", name, game.score())
+            }
+            None => String::new(),
+        };
+        info!("seed {}: score {} after {} turns ({}){}",
+              hard_seed.seed, hard_seed.score, hard_seed.turns, hard_seed.end_cause, verified);
+    }
+    let seed_list = hard_seeds.iter().map(|hard_seed| hard_seed.seed.to_string()).collect::<Vec<_>>();
+    info!("Seeds: {}", seed_list.join(","));
+    Ok(())
+}
+
+// Plays games with a strategy, and writes out the puzzles found in them
+fn find_puzzles(matches: &Matches, path: &str, strategy_str: &str, strategy_params: &[(String, String)],
+                validate: bool) -> Result<(), CliError> {
+    let run = try!(RunOptions::parse(matches));
+    let game_opts = try!(parse_game_options(matches, None));
+    let max_deck_size = try!(parse_opt(matches, "puzzle-deck-size")).unwrap_or(5);
+    let mut strategy_config = try!(get_supported_strategy_config(strategy_str, strategy_params, validate, &game_opts));
+    let first_seed = run.first_seed();
+    let mut found = Vec::new();
+    for result in simulator::stream(&game_opts, &mut *strategy_config, first_seed..first_seed + run.n_trials) {
+        let choices = result.game.turn_history.iter().map(|turn| turn.choice.clone()).collect::<Vec<_>>();
+        for puzzle in puzzles::find_puzzles(&game_opts, result.seed, &simulator::new_deck(result.seed), &choices,
+                                            max_deck_size) {
+            info!("Seed {}, turn {}: {}", puzzle.seed, puzzle.position.board.turn,
+                  html_replay::describe_turn(&puzzle.solution));
+            found.push(puzzle.to_json());
+        }
+    }
+    let n_found = found.len();
+    try!(std::fs::write(path, json::Json::Array(found).pretty())
+        .map_err(|err| format!("Couldn't write {}: {}", path, err)));
+    info!("Wrote {} puzzles from {} games to {}", n_found, run.n_trials, path);
+    Ok(())
+}
+
+// Replays recorded games with the current strategy, to check whether it still makes the same moves
+fn divergence_check(matches: &Matches, path: &str, strategy_str: &str, strategy_params: &[(String, String)],
+                    validate: bool) -> Result<(), CliError> {
+    let mut paths = if std::path::Path::new(path).is_dir() {
+        let entries = try!(std::fs::read_dir(path).map_err(|err| format!("Couldn't read {}: {}", path, err)));
+        entries.filter_map(|entry| entry.ok()).map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |extension| extension == "json"))
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+    } else {
+        vec![path.to_string()]
+    };
+    paths.sort();
+    if paths.is_empty() {
+        return usage_error(format!("No recorded games (.json files) in {}", path));
+    }
+
+    let mut diverged = 0;
+    for path in paths.iter() {
+        let transcript = try!(json_output::Transcript::read(path));
+        let game_opts = try!(parse_game_options(matches, Some(transcript.num_players)));
+        let strategy_config = try!(get_supported_strategy_config(strategy_str, strategy_params, validate, &game_opts));
+        let choices = try!(transcript.choices(game_opts.hand_size));
+        let investigation = detective::investigate(&game_opts, strategy_config.initialize(&game_opts),
+                                                   transcript.seed.unwrap_or(0), transcript.engine_deck(),
+                                                   &choices);
+        let recorded_with = transcript.version.as_ref().map_or(String::new(), |version| {
+            format!(" (recorded by {} version {})", version.strategy, version.strategy_version)
+        });
+        if let Some(report) = investigation.first_inconsistency() {
+            diverged += 1;
+            info!("{}{}: diverges on turn {}, where player {} made {:?} but would now make {:?}",
+                  path, recorded_with, report.turn, report.player, report.choice, report.expected);
+        } else if let Some((turn, ref message)) = investigation.stopped_at {
+            diverged += 1;
+            info!("{}{}: the strategy couldn't follow turn {}: {}", path, recorded_with, turn, message);
+        } else {
+            info!("{}: the same {} moves", path, choices.len());
+        }
+    }
+    if diverged > 0 {
+        return Err(CliError::Failed(format!("{} of {} recorded games diverged", diverged, paths.len())));
+    }
+    Ok(info!("All {} recorded games were replayed move for move", paths.len()))
+}
+
+// Plays every configuration in a sweep on the same seeds, with the sweep's parameters applied after
+// any --strategy-opt overrides
+fn sweep(matches: &Matches, path: &str, strategy_str: &str, strategy_params: &[(String, String)],
+         validate: bool) -> Result<(), CliError> {
+    let run = try!(RunOptions::parse(matches));
+    let game_opts = try!(parse_game_options(matches, None));
+    let sweep = try!(sweep::Sweep::read(path));
+    let configurations = sweep.configurations();
+    // catch a misspelled parameter before playing any games
+    let all_params = configurations.iter().map(|params| {
+        let mut all_params = strategy_params.to_vec();
+        all_params.extend(params.iter().cloned());
+        all_params
+    }).collect::<Vec<_>>();
+    for params in all_params.iter() {
+        try!(get_supported_strategy_config(strategy_str, params, validate, &game_opts));
+    }
+    info!("Playing {} configurations of {} on seeds {} to {}", configurations.len(), strategy_str,
+          run.first_seed(), run.first_seed() + run.n_trials - 1);
+    let decks = simulator::DeckCache::new();
+    let mut results = Vec::new();
+    for (params, all_params) in configurations.into_iter().zip(all_params.iter()) {
+        let result = try!(sim_games(&game_opts, strategy_str, Some(run.first_seed()), run.n_trials, run.n_threads,
+                                    run.progress_info, run.progress_bar, &run.deal_filters, Some(&decks), all_params,
+                                    validate, None, simulator::Analyses::none()));
+        results.push((params, result));
+    }
+    Ok(print!("{}", sweep::ranked_table(&results)))
+}
+
+// The deck in a --deck-file, in the order GameState::new expects,
+// and the number of players if the file says
+fn read_deck_file(path: &str) -> Result<(game::Cards, Option<u32>), String> {
+    if path.ends_with(".json") {
+        let transcript = try!(json_output::Transcript::read(path));
+        try!(simulator::check_deck(&transcript.deck).map_err(|err| format!("{}: {}", path, err)));
+        return Ok((transcript.engine_deck(), Some(transcript.num_players)));
+    }
+    let contents = try!(std::fs::read_to_string(path).map_err(|err| {
+        format!("Couldn't read {}: {}", path, err)
+    }));
+    let mut deck = Vec::new();
+    for card_str in contents.split_whitespace() {
+        deck.push(try!(game::Card::from_str(card_str).map_err(|err| format!("{}: {}", path, err))));
+    }
+    try!(simulator::check_deck(&deck).map_err(|err| format!("{}: {}", path, err)));
+    // cards are drawn from the end of the deck
+    deck.reverse();
+    Ok((deck, None))
+}
+
+// parses a number of hints like "1" or "0.5" into halves of a hint
+fn parse_half_hints(s: &str) -> Result<u32, String> {
+    let halves = try!(f64::from_str(s).map_err(|_| format!("Invalid number of hints: {}", s))) * 2.0;
+    if halves < 0.0 || halves.fract() != 0.0 {
+        return Err(format!("Hints must be a non-negative multiple of 0.5, not {}", s));
+    }
+    Ok(halves as u32)
+}
+
+fn get_game_options(n_players: u32) -> Result<game::GameOptions, CliError> {
     let hand_size = match n_players {
         2 => 5,
         3 => 5,
         4 => 4,
         5 => 4,
-        _ => { panic!("There should be 2 to 5 players, not {}", n_players); }
+        n if n >= 6 => 3,
+        _ => return usage_error(format!("There should be at least 2 players, not {}", n_players)),
     };
 
-    let game_opts = game::GameOptions {
+    Ok(game::GameOptions {
         num_players: n_players,
         hand_size: hand_size,
         num_hints: 8,
+        starting_hints: 8,
+        discard_half_hints: 2,
+        firework_half_hints: 2,
         num_lives: 3,
         // hanabi rules are a bit ambiguous about whether you can give hints that match 0 cards
         allow_empty_hints: false,
-    };
+        endgame_rule: game::EndgameRule::FinalRound,
+        misplay_rule: game::MisplayRule::Discard,
+        strikeout_scores_zero: false,
+        end_when_settled: false,
+        illegal_moves: game::IllegalMovePolicy::Panic,
+    })
+}
+
+// the best known strategy for each number of players, played by "auto"
+const AUTO_STRATEGIES: [(u32, &str); 4] = [(2, "info"), (3, "info"), (4, "info"), (5, "info")];
+// what "auto" plays with any other number of players
+const AUTO_FALLBACK: &str = "info";
 
-    let strategy_config : Box<strategy::GameStrategyConfig + Sync> = match strategy_str {
+// every strategy get_strategy_config knows, besides "auto", which plays one of them
+const STRATEGY_NAMES: [&str; 8] = [
+    "random", "play-first", "discard-oldest", "beginner", "cheat", "semi-cheat", "cheat-search", "info",
+];
+
+fn get_strategy_config(strategy_str: &str, strategy_params: &[(String, String)], validate: bool)
+    -> Result<Box<strategy::GameStrategyConfig + Sync>, CliError> {
+    // a strategy, then any wrappers, e.g. "info|noisy:0.05".  A strategy ending in "+mc" searches
+    // with Monte-Carlo rollouts, e.g. "info+mc"
+    let mut parts = strategy_str.split('|');
+    let mut base_str = parts.next().unwrap();
+    let search = base_str.ends_with("+mc");
+    if search {
+        base_str = &base_str[..base_str.len() - "+mc".len()];
+    }
+    let mut strategy_config : Box<strategy::GameStrategyConfig + Sync> = match base_str {
         "random" => {
             Box::new(strategies::examples::RandomStrategyConfig {
                 hint_probability: 0.4,
                 play_probability: 0.2,
             }) as Box<strategy::GameStrategyConfig + Sync>
         },
+        "play-first" => {
+            Box::new(strategies::examples::PlayFirstStrategyConfig::new())
+                as Box<strategy::GameStrategyConfig + Sync>
+        },
+        "discard-oldest" => {
+            Box::new(strategies::examples::DiscardOldestStrategyConfig::new())
+                as Box<strategy::GameStrategyConfig + Sync>
+        },
+        "beginner" => {
+            Box::new(strategies::examples::BeginnerStrategyConfig::new())
+                as Box<strategy::GameStrategyConfig + Sync>
+        },
+        "auto" => {
+            let fallback = try!(get_strategy_config(AUTO_FALLBACK, &[], false));
+            let mut config = meta::MetaStrategyConfig::new("auto", AUTO_FALLBACK, fallback);
+            for &(num_players, name) in AUTO_STRATEGIES.iter() {
+                config = config.with(num_players, name, try!(get_strategy_config(name, &[], false)));
+            }
+            Box::new(config) as Box<strategy::GameStrategyConfig + Sync>
+        },
         "cheat" => {
             Box::new(strategies::cheating::CheatingStrategyConfig::new())
                 as Box<strategy::GameStrategyConfig + Sync>
         },
+        "semi-cheat" => {
+            Box::new(strategies::semi_cheating::SemiCheatingStrategyConfig::new())
+                as Box<strategy::GameStrategyConfig + Sync>
+        },
+        "cheat-search" => {
+            Box::new(strategies::cheat_search::CheatSearchStrategyConfig::new())
+                as Box<strategy::GameStrategyConfig + Sync>
+        },
         "info" => {
             Box::new(strategies::information::InformationStrategyConfig::new())
                 as Box<strategy::GameStrategyConfig + Sync>
         },
-        _ => {
-            panic!("Unexpected strategy argument {}", strategy_str);
-        },
+        _ => return usage_error(format!("Unexpected strategy argument {}", base_str)),
     };
-    simulator::simulate(&game_opts, strategy_config, seed, n_trials, n_threads, progress_info)
+    if search {
+        strategy_config = Box::new(search::DeterminizerConfig::new(strategy_config));
+    }
+    for &(ref name, ref value) in strategy_params {
+        try!(strategy_config.set_param(name, value).map_err(CliError::Usage));
+    }
+    for wrapper_str in parts {
+        strategy_config = try!(wrap_strategy_config(strategy_config, wrapper_str));
+    }
+    if validate {
+        strategy_config = Box::new(validate::ValidatingStrategyConfig::new(strategy_config));
+    }
+    Ok(strategy_config)
+}
+
+// Wraps a strategy as described by one of the "|"-separated parts after its name, e.g.
+// "noisy:0.05", "noisy:0.05:seed=3:seat=1" or "adaptive:2"
+fn wrap_strategy_config(inner: Box<strategy::GameStrategyConfig + Sync>, wrapper_str: &str)
+    -> Result<Box<strategy::GameStrategyConfig + Sync>, CliError> {
+    let args = wrapper_str.split(':').collect::<Vec<_>>();
+    match args[0] {
+        "noisy" => {
+            let error_rate = match args.get(1).and_then(|rate| rate.parse::<f64>().ok()) {
+                Some(rate) if rate >= 0.0 && rate <= 1.0 => rate,
+                _ => return usage_error(format!("Expected noisy:ERROR_RATE, with a rate between 0 and 1, got {}",
+                                                wrapper_str)),
+            };
+            let mut seed = 0;
+            let mut seats = None;
+            for arg in args.iter().skip(2) {
+                let parts = arg.splitn(2, '=').collect::<Vec<_>>();
+                let value = match parts.get(1).and_then(|value| value.parse::<u32>().ok()) {
+                    Some(value) => value,
+                    None => return usage_error(format!("Expected seed=N or seat=N after noisy, got {}", arg)),
+                };
+                match parts[0] {
+                    "seed" => seed = value,
+                    "seat" => seats.get_or_insert(Vec::new()).push(value),
+                    _ => return usage_error(format!("Expected seed=N or seat=N after noisy, got {}", arg)),
+                }
+            }
+            Ok(Box::new(noisy::NoisyStrategyConfig::new(inner, error_rate, seed, seats)))
+        }
+        "adaptive" => {
+            let max_deviations = match args.get(1).and_then(|max| max.parse::<u32>().ok()) {
+                Some(max) if args.len() == 2 => max,
+                _ => return usage_error(format!("Expected adaptive:MAX_DEVIATIONS, got {}", wrapper_str)),
+            };
+            let fallback = Box::new(strategies::examples::BeginnerStrategyConfig::new());
+            Ok(Box::new(adaptive::AdaptiveStrategyConfig::new(inner, fallback, max_deviations)))
+        }
+        _ => usage_error(format!("Unexpected strategy wrapper {}", args[0])),
+    }
+}
+
+// get_strategy_config, for a strategy that can play with these options
+fn get_supported_strategy_config(strategy_str: &str, strategy_params: &[(String, String)], validate: bool,
+                                 game_opts: &game::GameOptions)
+    -> Result<Box<strategy::GameStrategyConfig + Sync>, CliError> {
+    let strategy_config = try!(get_strategy_config(strategy_str, strategy_params, validate));
+    try!(strategy_config.supports(game_opts).map_err(CliError::Usage));
+    Ok(strategy_config)
+}
+
+// `decks`, if given, is shared with other runs on the same seeds
+fn sim_games(game_opts: &game::GameOptions, strategy_str: &str, seed: Option<u32>, n_trials: u32, n_threads: u32,
+             progress_info: Option<u32>, progress_bar: bool, deal_filters: &[simulator::DealFilter],
+             decks: Option<&simulator::DeckCache>, strategy_params: &[(String, String)], validate: bool,
+             observer_factory: Option<&simulator::ObserverFactory>, analyses: simulator::Analyses)
+    -> Result<simulator::SimResult, CliError> {
+    let strategy_config = try!(get_supported_strategy_config(strategy_str, strategy_params, validate, game_opts));
+    // times both the decisions and the progress bar
+    let clock = clock::SystemClock::shared();
+    if !progress_bar {
+        return Ok(simulator::simulate(game_opts, strategy_config, seed, n_trials, n_threads, progress_info,
+                                      deal_filters, decks, observer_factory, analyses, &clock));
+    }
+
+    let label = format!("{} {}p", strategy_str, game_opts.num_players);
+    let bar = Arc::new(progress::ProgressBar::new(&label, n_trials, clock.clone()));
+    let make_observer = |seed| {
+        let observers: Vec<Box<observer::GameObserver>> = vec![
+            match observer_factory {
+                Some(make_observer) => make_observer(seed),
+                None => Box::new(observer::LogObserver),
+            },
+            Box::new(progress::ProgressObserver::new(bar.clone())),
+        ];
+        Box::new(observers) as Box<observer::GameObserver>
+    };
+    let result = simulator::simulate(game_opts, strategy_config, seed, n_trials, n_threads, progress_info,
+                                     deal_filters, decks, Some(&make_observer), analyses, &clock);
+    bar.finish();
+    Ok(result)
+}
+
+// Like sim_games, but plays the seeds in batches of `every`, saving the results after each batch,
+// and skips the seeds already played if there is a checkpoint at `path`
+fn sim_games_with_checkpoint(game_opts: &game::GameOptions, strategy_str: &str, first_seed: u32, n_trials: u32,
+                             n_threads: u32, progress_info: Option<u32>, progress_bar: bool,
+                             deal_filters: &[simulator::DealFilter], strategy_params: &[(String, String)],
+                             validate: bool, observer_factory: Option<&simulator::ObserverFactory>,
+                             version: &version::ArtifactVersion, path: &str, every: u32)
+    -> Result<simulator::SimResult, CliError> {
+    let mut checkpoint = try!(checkpoint::Checkpoint::resume(path, version, game_opts, strategy_params,
+                                                             deal_filters, first_seed, n_trials));
+    if checkpoint.next_seed > first_seed {
+        info!("Resuming from {}: {} of {} seeds already played, continuing from seed {}",
+              path, checkpoint.next_seed - first_seed, n_trials, checkpoint.next_seed);
+    }
+    while checkpoint.remaining() > 0 {
+        let n_seeds = std::cmp::min(every, checkpoint.remaining());
+        // a checkpoint keeps every analysis, whichever the run that resumes it shows
+        let result = try!(sim_games(game_opts, strategy_str, Some(checkpoint.next_seed), n_seeds, n_threads,
+                                    progress_info, progress_bar, deal_filters, None, strategy_params, validate,
+                                    observer_factory, simulator::Analyses::all()));
+        checkpoint.record(n_seeds, result);
+        try!(checkpoint.write(path));
+        debug!("Saved checkpoint to {}, next seed {}", path, checkpoint.next_seed);
+    }
+    Ok(checkpoint.result)
+}
+
+// Like sim_games, but plays the seeds in batches of `every`, and stops after the first batch at
+// which the average score so far differs significantly from `baseline`.
+// Checking after every batch would make a false alarm more likely than `level` alone suggests, so
+// `level` is spent as the games are played: each check is made at the share of `level` that its
+// batch adds to the run.  However the checks depend on each other, the chance of any false alarm
+// is at most the sum of their levels, which is `level`.
+fn sim_games_until_significant(game_opts: &game::GameOptions, strategy_str: &str, first_seed: u32, n_trials: u32,
+                               n_threads: u32, progress_info: Option<u32>, progress_bar: bool,
+                               deal_filters: &[simulator::DealFilter], strategy_params: &[(String, String)],
+                               validate: bool, observer_factory: Option<&simulator::ObserverFactory>,
+                               analyses: simulator::Analyses, baseline: f64, level: f64, every: u32)
+    -> Result<simulator::SimResult, CliError> {
+    let mut result = simulator::SimResult::with_analyses(analyses);
+    let mut next_seed = first_seed;
+    // the share of `level` spent by batches too small to check
+    let mut unspent = 0.0;
+    while next_seed < first_seed + n_trials {
+        let n_seeds = std::cmp::min(every, first_seed + n_trials - next_seed);
+        result.merge(try!(sim_games(game_opts, strategy_str, Some(next_seed), n_seeds, n_threads,
+                                    progress_info, progress_bar, deal_filters, None, strategy_params, validate,
+                                    observer_factory, analyses)));
+        next_seed += n_seeds;
+        let batch_level = unspent + level * n_seeds as f64 / n_trials as f64;
+        if result.scores.total_count < 2 {
+            unspent = batch_level;
+            continue;
+        }
+        unspent = 0.0;
+        let p_value = result.score_p_value(baseline);
+        debug!("After {} seeds: average score {:.4} ± {:.4}, p = {:.4} against {}, checked at {:.4}",
+               next_seed - first_seed, result.average_score(), result.score_stderr(), p_value, baseline,
+               batch_level);
+        if p_value < batch_level {
+            info!("Stopping after {} of {} seeds: average score {:.4} ± {:.4} differs from {} (p = {:.4})",
+                  next_seed - first_seed, n_trials, result.average_score(), result.score_stderr(), baseline, p_value);
+            return Ok(result);
+        }
+    }
+    info!("Played all {} seeds without the average score differing significantly from {} (p = {:.4})",
+          n_trials, baseline, result.score_p_value(baseline));
+    Ok(result)
+}
+
+fn write_html_replay(game_opts: &game::GameOptions, strategy_str: &str,
+                     strategy_config: Box<strategy::GameStrategyConfig + Sync>, seed: u32, path: &str)
+    -> Result<(), CliError> {
+    let game = simulator::simulate_once(game_opts, strategy_config.initialize(game_opts), seed);
+    let trace = html_replay::GameTrace::new(game_opts, simulator::new_deck(seed), &game.turn_history);
+    let title = format!("{} strategy, {} players, seed {}: score {}",
+                        strategy_str, game_opts.num_players, seed, game.score());
+    try!(std::fs::write(path, html_replay::render(&trace, &title))
+        .map_err(|err| format!("Couldn't write {}: {}", path, err)));
+    info!("Wrote replay of seed {} to {}", seed, path);
+    Ok(())
 }
 
-fn get_results_table() -> String {
-    let strategies = ["cheat", "info"];
+fn get_results_table(progress_bar: bool) -> String {
+    // the baselines, then the real strategies.  "auto" only gets a row once it plays something
+    // other than info
+    let strategies = ["play-first", "discard-oldest", "beginner", "cheat", "info"];
     let player_nums = (2..=5).collect::<Vec<_>>();
     let seed = 0;
     let n_trials = 20000;
     let n_threads = 8;
 
     let intro = format!("On the first {} seeds, we have these scores and win rates (average ± standard error):\n\n", n_trials);
-    let format_name    = |x|         format!(" {:7} ",      x);
+    let format_name    = |x|         format!(" {:14} ",     x);
     let format_players = |x|         format!("   {}p    ",  x);
     let format_percent = |x, stderr| format!(" {:05.2} ± {:.2} % ", x, stderr);
     let format_score   = |x, stderr| format!(" {:07.4} ± {:.4} ", x, stderr);
-    let space          =        String::from("         ");
-    let dashes         =        String::from("---------");
+    let space          =        String::from("                ");
+    let dashes         =        String::from("----------------");
     let dashes_long    =        String::from("------------------");
     type TwoLines = (String, String);
     fn make_twolines(player_nums: &Vec<u32>, head: TwoLines, make_block: &dyn Fn(u32) -> TwoLines) -> TwoLines {
@@ -191,9 +1482,14 @@ fn get_results_table() -> String {
     let header = make_twolines(&player_nums,
                                (space.clone(), dashes.clone()),
                                &|n_players| (format_players(n_players), dashes_long.clone()));
+    // every strategy and number of players plays the same deals
+    let decks = simulator::DeckCache::new();
     let mut body = strategies.iter().map(|strategy| {
         make_twolines(&player_nums, (format_name(strategy), space.clone()), &|n_players| {
-            let simresult = sim_games(n_players, strategy, Some(seed), n_trials, n_threads, None);
+            let game_opts = get_game_options(n_players).unwrap();
+            let simresult = sim_games(&game_opts, strategy, Some(seed), n_trials, n_threads, None, progress_bar,
+                                      &[], Some(&decks), &[], false, None, simulator::Analyses::none())
+                .unwrap();
             (
                 format_score(simresult.average_score(), simresult.score_stderr()),
                 format_percent(simresult.percent_perfect(), simresult.percent_perfect_stderr())
@@ -204,7 +1500,7 @@ fn get_results_table() -> String {
     intro + &concat_twolines(body)
 }
 
-fn write_results_table() {
+fn write_results_table(progress_bar: bool) -> Result<(), CliError> {
     let separator = r#"
 ## Results (auto-generated)
 
@@ -220,15 +1516,20 @@ time cargo run --release -- --write-results-table
 
 "#;
     let readme = "README.md";
-    let readme_contents = std::fs::read_to_string(readme).unwrap();
-    let readme_init = {
+    let readme_contents = try!(std::fs::read_to_string(readme).map_err(|err| {
+        format!("Couldn't read {}: {}", readme, err)
+    }));
+    let (readme_init, readme_rest) = {
         let parts = readme_contents.splitn(2, separator).collect::<Vec<_>>();
         if parts.len() != 2 {
-            panic!("{} has been modified in the Results section!", readme);
+            return Err(CliError::Failed(format!("{} has been modified in the Results section!", readme)));
         }
-        parts[0]
+        (parts[0], parts[1])
     };
-    let table = get_results_table();
-    let new_readme_contents = String::from(readme_init) + separator + &table;
-    std::fs::write(readme, new_readme_contents).unwrap();
+    // keep the sections after the table
+    let readme_tail = readme_rest.find("\n## ").map_or("", |start| &readme_rest[start..]);
+    let table = get_results_table(progress_bar);
+    let new_readme_contents = String::from(readme_init) + separator + &table + readme_tail;
+    try!(std::fs::write(readme, new_readme_contents).map_err(|err| format!("Couldn't write {}: {}", readme, err)));
+    Ok(())
 }
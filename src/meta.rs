@@ -0,0 +1,107 @@
+use game::*;
+use strategy::*;
+
+// A strategy that plays a different strategy depending on the number of players, e.g. whichever
+// plays best with that many.  Parameters go to every strategy that knows them.
+
+pub struct MetaStrategyConfig {
+    name: String,
+    // the strategy for each number of players listed, with its name
+    by_players: Vec<(u32, String, Box<GameStrategyConfig + Sync>)>,
+    // the strategy for any other number of players, with its name
+    fallback: (String, Box<GameStrategyConfig + Sync>),
+}
+impl MetaStrategyConfig {
+    pub fn new(name: &str, fallback_name: &str, fallback: Box<GameStrategyConfig + Sync>) -> MetaStrategyConfig {
+        MetaStrategyConfig {
+            name: name.to_string(),
+            by_players: Vec::new(),
+            fallback: (fallback_name.to_string(), fallback),
+        }
+    }
+
+    // plays `config` in games with `num_players` players
+    pub fn with(mut self, num_players: u32, name: &str, config: Box<GameStrategyConfig + Sync>) -> MetaStrategyConfig {
+        assert!(self.by_players.iter().all(|&(n, _, _)| n != num_players),
+                "Two strategies for {} players", num_players);
+        self.by_players.push((num_players, name.to_string(), config));
+        self
+    }
+
+    // the name of the strategy played with `num_players` players, and its config
+    fn choose(&self, num_players: u32) -> (&str, &GameStrategyConfig) {
+        match self.by_players.iter().find(|&&(n, _, _)| n == num_players) {
+            Some(&(_, ref name, ref config)) => (name, &**config),
+            None => (&self.fallback.0, &*self.fallback.1),
+        }
+    }
+
+    fn configs_mut(&mut self) -> Vec<&mut Box<GameStrategyConfig + Sync>> {
+        let mut configs = self.by_players.iter_mut().map(|&mut (_, _, ref mut config)| config).collect::<Vec<_>>();
+        configs.push(&mut self.fallback.1);
+        configs
+    }
+}
+impl GameStrategyConfig for MetaStrategyConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        self.choose(opts.num_players).1.initialize(opts)
+    }
+
+    fn supports(&self, opts: &GameOptions) -> Result<(), String> {
+        self.choose(opts.num_players).1.supports(opts)
+    }
+
+    fn on_game_end(&self, game: &GameState) {
+        self.choose(game.board.num_players).1.on_game_end(game)
+    }
+
+    // changes whenever one of the strategies' versions does
+    fn version(&self) -> u32 {
+        self.by_players.iter().map(|&(_, _, ref config)| config.version()).sum::<u32>() + self.fallback.1.version()
+    }
+
+    // sets the parameter of every strategy that has it, failing only if none do
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let mut error = None;
+        let mut any_ok = false;
+        for config in self.configs_mut() {
+            match config.set_param(name, value) {
+                Ok(()) => any_ok = true,
+                Err(err) => error = error.or(Some(err)),
+            }
+        }
+        if any_ok { Ok(()) } else { Err(error.unwrap()) }
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        let mut by_players = self.by_players.iter().map(|&(n, ref name, _)| (n, name)).collect::<Vec<_>>();
+        by_players.sort();
+        let mut rules = by_players.iter().map(|&(n, name)| format!("With {} players, play {}.", n, name))
+            .collect::<Vec<_>>();
+        rules.push(format!("Otherwise, play {}.", self.fallback.0));
+        let mut conventions = Conventions::new(&self.name, "Plays a different strategy depending on the number \
+                                                            of players.")
+            .section("Choosing a strategy", rules);
+        // each strategy's own conventions, once
+        let mut described = Vec::new();
+        let configs = self.by_players.iter().map(|&(_, ref name, ref config)| (name, config))
+            .chain(Some((&self.fallback.0, &self.fallback.1)));
+        for (name, config) in configs {
+            if described.contains(&name) {
+                continue;
+            }
+            described.push(name);
+            if let Some(inner) = config.conventions() {
+                for (title, rules) in inner.sections {
+                    conventions = conventions.section(&format!("{}: {}", name, title), rules);
+                }
+                for (param, value) in inner.params {
+                    if conventions.params.iter().all(|&(ref other, _)| *other != param) {
+                        conventions = conventions.param(&param, value);
+                    }
+                }
+            }
+        }
+        Some(conventions)
+    }
+}
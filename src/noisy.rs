@@ -0,0 +1,167 @@
+use rand::{self, Rng, SeedableRng};
+
+use game::*;
+use helpers::{CardPossibilityTable, HandInfo};
+use json::Json;
+use strategy::*;
+
+// Wraps a strategy with mistakes: with probability `error_rate`, a player makes a random legal
+// move instead of the one its strategy chose.  Measures how well conventions hold up when
+// partners occasionally misplay, as people do.
+//
+// The inner strategy still decides every turn, so it sees the game exactly as it would
+// without the noise, except for the moves that get replaced.
+
+pub struct NoisyStrategyConfig {
+    inner: Box<GameStrategyConfig + Sync>,
+    error_rate: f64,
+    // mixed into each player's randomness, so different seeds make different mistakes in the same game
+    seed: u32,
+    // the players who make mistakes, or None for everyone
+    seats: Option<Vec<Player>>,
+}
+impl NoisyStrategyConfig {
+    pub fn new(inner: Box<GameStrategyConfig + Sync>, error_rate: f64, seed: u32,
+               seats: Option<Vec<Player>>) -> NoisyStrategyConfig {
+        assert!(error_rate >= 0.0 && error_rate <= 1.0, "Error rate {} is not a probability", error_rate);
+        NoisyStrategyConfig {
+            inner: inner,
+            error_rate: error_rate,
+            seed: seed,
+            seats: seats,
+        }
+    }
+}
+impl GameStrategyConfig for NoisyStrategyConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        Box::new(NoisyStrategy {
+            inner: self.inner.initialize(opts),
+            error_rate: self.error_rate,
+            seed: self.seed,
+            seats: self.seats.clone(),
+        })
+    }
+
+    fn supports(&self, opts: &GameOptions) -> Result<(), String> {
+        if let Some(ref seats) = self.seats {
+            if let Some(seat) = seats.iter().find(|&&seat| seat >= opts.num_players) {
+                return Err(format!("Can't add mistakes to seat {} of a {}-player game", seat, opts.num_players));
+            }
+        }
+        self.inner.supports(opts)
+    }
+
+    fn on_game_end(&self, game: &GameState) {
+        self.inner.on_game_end(game)
+    }
+
+    fn version(&self) -> u32 {
+        self.inner.version()
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.inner.set_param(name, value)
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        self.inner.conventions().map(|conventions| {
+            let who = match self.seats {
+                None => String::from("Every player"),
+                Some(ref seats) => format!("The players in seats {:?}", seats),
+            };
+            conventions.section("Mistakes", vec![
+                format!("{} makes a random legal move instead, with probability {}.", who, self.error_rate),
+            ])
+        })
+    }
+}
+
+pub struct NoisyStrategy {
+    inner: Box<GameStrategy>,
+    error_rate: f64,
+    seed: u32,
+    seats: Option<Vec<Player>>,
+}
+impl GameStrategy for NoisyStrategy {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        let player = context.player;
+        let inner = self.inner.initialize(context, rng.clone());
+        let is_noisy = self.seats.as_ref().map_or(true, |seats| seats.contains(&player));
+        if !is_noisy {
+            return inner;
+        }
+        // A separate stream for mistakes, so the inner strategy's own randomness is untouched.
+        // Its seed varies with the game through the player's rng.
+        let mut game_rng = rng;
+        let noise_rng = rand::ChaChaRng::from_seed(&[self.seed, player, game_rng.next_u32(), game_rng.next_u32()]);
+        Box::new(NoisyPlayerStrategy {
+            inner: inner,
+            error_rate: self.error_rate,
+            rng: noise_rng,
+            mistakes: 0,
+        })
+    }
+
+    fn seat_name(&self, player: Player) -> String {
+        self.inner.seat_name(player)
+    }
+}
+
+pub struct NoisyPlayerStrategy {
+    inner: Box<PlayerStrategy>,
+    error_rate: f64,
+    rng: StrategyRng,
+    mistakes: u32,
+}
+impl PlayerStrategy for NoisyPlayerStrategy {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        let choice = self.inner.decide(view);
+        if self.rng.gen::<f64>() >= self.error_rate {
+            return choice;
+        }
+        let choices = view.board.legal_choices(view).collect::<Vec<_>>();
+        let mistake = self.rng.choose(&choices).unwrap().clone();
+        debug!("Player {} makes a mistake: {:?} instead of {:?}", view.player, mistake, choice);
+        self.mistakes += 1;
+        mistake
+    }
+
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.inner.update(turn, view);
+    }
+
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.inner.replay(turn, view);
+    }
+
+    fn concede(&mut self, view: &BorrowedGameView) -> bool {
+        self.inner.concede(view)
+    }
+
+    fn public_info_digest(&self) -> Option<u64> {
+        self.inner.public_info_digest()
+    }
+
+    fn public_card_probabilities(&self, player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        self.inner.public_card_probabilities(player)
+    }
+
+    fn own_hand_knowledge(&self, view: &BorrowedGameView) -> Option<HandInfo<CardPossibilityTable>> {
+        self.inner.own_hand_knowledge(view)
+    }
+
+    fn notes(&self) -> Vec<CardNote> {
+        self.inner.notes()
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.inner.metrics()
+    }
+
+    fn inspect(&self) -> Option<Json> {
+        let inner = self.inner.inspect().unwrap_or(Json::Null);
+        Some(Json::object()
+             .with("mistakes", self.mistakes)
+             .with("inner", inner))
+    }
+}
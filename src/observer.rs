@@ -0,0 +1,236 @@
+use fnv::FnvHashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clock::Clock;
+use game::*;
+use strategy::PlayerStrategy;
+
+// Hooks the simulator calls as a game progresses, for output and analytics
+// that shouldn't live in the simulator itself.
+pub trait GameObserver {
+    // called once, right after the cards are dealt
+    fn on_game_start(&mut self, _seed: u32, _game: &GameState) {}
+    // called before the current player decides on their turn
+    fn before_turn(&mut self, _game: &GameState) {}
+    // called as soon as the current player has decided, before the choice is processed
+    fn after_decide(&mut self, _choice: &TurnChoice) {}
+    // called after every turn, including turns replayed when resuming a game
+    fn on_turn(&mut self, turn: &TurnRecord, board: &BoardState);
+    // called after on_turn, once every player has updated, with the strategy of the
+    // player who moved, e.g. to record what it believes is common knowledge
+    fn after_update(&mut self, _player: Player, _strategy: &PlayerStrategy) {}
+    // called once the game is over
+    fn on_game_end(&mut self, _game: &GameState) {}
+}
+
+impl GameObserver for Vec<Box<GameObserver>> {
+    fn on_game_start(&mut self, seed: u32, game: &GameState) {
+        for observer in self.iter_mut() {
+            observer.on_game_start(seed, game);
+        }
+    }
+    fn before_turn(&mut self, game: &GameState) {
+        for observer in self.iter_mut() {
+            observer.before_turn(game);
+        }
+    }
+    fn after_decide(&mut self, choice: &TurnChoice) {
+        for observer in self.iter_mut() {
+            observer.after_decide(choice);
+        }
+    }
+    fn on_turn(&mut self, turn: &TurnRecord, board: &BoardState) {
+        for observer in self.iter_mut() {
+            observer.on_turn(turn, board);
+        }
+    }
+    fn after_update(&mut self, player: Player, strategy: &PlayerStrategy) {
+        for observer in self.iter_mut() {
+            observer.after_update(player, strategy);
+        }
+    }
+    fn on_game_end(&mut self, game: &GameState) {
+        for observer in self.iter_mut() {
+            observer.on_game_end(game);
+        }
+    }
+}
+
+// Pretty-prints the game as it's played, at debug log level
+pub struct LogObserver;
+impl GameObserver for LogObserver {
+    fn before_turn(&mut self, game: &GameState) {
+        debug!("");
+        debug!("=======================================================");
+        debug!("Turn {}, Player {} to go", game.board.turn, game.board.player);
+        debug!("=======================================================");
+        debug!("{}", game);
+    }
+    fn on_turn(&mut self, _: &TurnRecord, _: &BoardState) {
+    }
+    fn on_game_end(&mut self, game: &GameState) {
+        debug!("");
+        debug!("=======================================================");
+        debug!("Final state:\n{}", game);
+        debug!("SCORE: {:?}", game.score());
+    }
+}
+
+// Counts what kinds of moves were made, per player
+#[derive(Debug,Default)]
+pub struct MoveCounts {
+    pub hints: u32,
+    pub plays: u32,
+    pub bombs: u32,
+    pub discards: u32,
+}
+
+#[derive(Debug,Default)]
+pub struct StatsObserver {
+    pub games: u32,
+    pub moves: FnvHashMap<Player, MoveCounts>,
+}
+impl StatsObserver {
+    pub fn new() -> StatsObserver {
+        Default::default()
+    }
+
+    pub fn total(&self) -> MoveCounts {
+        let mut total = MoveCounts::default();
+        for counts in self.moves.values() {
+            total.hints += counts.hints;
+            total.plays += counts.plays;
+            total.bombs += counts.bombs;
+            total.discards += counts.discards;
+        }
+        total
+    }
+}
+impl GameObserver for StatsObserver {
+    fn on_turn(&mut self, turn: &TurnRecord, _: &BoardState) {
+        let counts = self.moves.entry(turn.player).or_insert_with(MoveCounts::default);
+        match turn.result {
+            TurnResult::Hint(_) => counts.hints += 1,
+            TurnResult::Play(_, true) => counts.plays += 1,
+            TurnResult::Play(_, false) => counts.bombs += 1,
+            TurnResult::Discard(_) => counts.discards += 1,
+        }
+    }
+    fn on_game_end(&mut self, _: &GameState) {
+        self.games += 1;
+    }
+}
+
+// How long decisions took, in microseconds
+#[derive(Debug,Clone,Default)]
+pub struct DecideTimes {
+    pub micros: Vec<u32>,
+    // (total microseconds, number of decisions) for each turn number, starting from turn 1
+    pub by_turn: Vec<(u64, u32)>,
+}
+impl DecideTimes {
+    pub fn new() -> DecideTimes {
+        Default::default()
+    }
+
+    pub fn record(&mut self, turn: u32, duration: Duration) {
+        let micros = duration.as_secs() * 1_000_000 + (duration.subsec_nanos() / 1000) as u64;
+        let micros = if micros > u32::max_value() as u64 { u32::max_value() } else { micros as u32 };
+        self.micros.push(micros);
+        let index = turn as usize - 1;
+        while self.by_turn.len() <= index {
+            self.by_turn.push((0, 0));
+        }
+        self.by_turn[index].0 += micros as u64;
+        self.by_turn[index].1 += 1;
+    }
+
+    pub fn merge(&mut self, other: DecideTimes) {
+        self.micros.extend(other.micros);
+        for (index, (total, count)) in other.by_turn.into_iter().enumerate() {
+            if index < self.by_turn.len() {
+                self.by_turn[index].0 += total;
+                self.by_turn[index].1 += count;
+            } else {
+                self.by_turn.push((total, count));
+            }
+        }
+    }
+
+    // the time below which `fraction` of decisions took, in microseconds
+    pub fn percentile(&self, fraction: f64) -> Option<u32> {
+        if self.micros.is_empty() {
+            return None;
+        }
+        let mut sorted = self.micros.clone();
+        sorted.sort();
+        let index = ((sorted.len() - 1) as f64 * fraction).round() as usize;
+        Some(sorted[index])
+    }
+
+    pub fn average_by_turn(&self) -> Vec<f64> {
+        self.by_turn.iter().map(|&(total, count)| {
+            if count == 0 { 0.0 } else { total as f64 / count as f64 }
+        }).collect()
+    }
+}
+
+// Times each call to decide, by the given clock
+pub struct DecideTimer {
+    clock: Arc<Clock>,
+    turn: u32,
+    started: Duration,
+    pub times: DecideTimes,
+}
+impl DecideTimer {
+    pub fn new(clock: Arc<Clock>) -> DecideTimer {
+        DecideTimer {
+            clock: clock,
+            turn: 0,
+            started: Duration::from_secs(0),
+            times: DecideTimes::new(),
+        }
+    }
+}
+impl GameObserver for DecideTimer {
+    fn before_turn(&mut self, game: &GameState) {
+        self.turn = game.board.turn;
+        self.started = self.clock.now();
+    }
+    fn after_decide(&mut self, _: &TurnChoice) {
+        let elapsed = self.clock.elapsed_since(self.started);
+        self.times.record(self.turn, elapsed);
+    }
+    fn on_turn(&mut self, _: &TurnRecord, _: &BoardState) {
+    }
+}
+
+// Times decisions while passing everything on to another observer,
+// keeping the other observer's work out of the timings
+pub struct TimedObserver<'a> {
+    pub timer: &'a mut DecideTimer,
+    pub inner: &'a mut GameObserver,
+}
+impl<'a> GameObserver for TimedObserver<'a> {
+    fn on_game_start(&mut self, seed: u32, game: &GameState) {
+        self.inner.on_game_start(seed, game);
+    }
+    fn before_turn(&mut self, game: &GameState) {
+        self.inner.before_turn(game);
+        self.timer.before_turn(game);
+    }
+    fn after_decide(&mut self, choice: &TurnChoice) {
+        self.timer.after_decide(choice);
+        self.inner.after_decide(choice);
+    }
+    fn on_turn(&mut self, turn: &TurnRecord, board: &BoardState) {
+        self.inner.on_turn(turn, board);
+    }
+    fn after_update(&mut self, player: Player, strategy: &PlayerStrategy) {
+        self.inner.after_update(player, strategy);
+    }
+    fn on_game_end(&mut self, game: &GameState) {
+        self.inner.on_game_end(game);
+    }
+}
@@ -0,0 +1,206 @@
+use fnv::FnvHashMap;
+use rand::{self, SeedableRng};
+use std::str::FromStr;
+
+use game::*;
+use strategy::*;
+
+// Sets up arbitrary mid-game positions, to check how a strategy handles a specific
+// situation without having to find a seed whose game happens to reach it.
+
+// parses space-separated cards, e.g. "r1 y2 b5"
+pub fn parse_cards(s: &str) -> Cards {
+    s.split_whitespace().map(|card| {
+        Card::from_str(card).unwrap_or_else(|err| panic!("{}", err))
+    }).collect()
+}
+
+pub struct PositionBuilder<'a> {
+    opts: &'a GameOptions,
+    hands: FnvHashMap<Player, Cards>,
+    fireworks: FnvHashMap<Color, Value>,
+    discard: Cards,
+    // the rest of the deck, in the order it will be drawn
+    deck: Option<Cards>,
+    hints: u32,
+    lives: u32,
+    player: Player,
+}
+impl<'a> PositionBuilder<'a> {
+    pub fn new(opts: &'a GameOptions) -> PositionBuilder<'a> {
+        PositionBuilder {
+            opts: opts,
+            hands: FnvHashMap::default(),
+            fireworks: FnvHashMap::default(),
+            discard: Vec::new(),
+            deck: None,
+            hints: opts.num_hints,
+            lives: opts.num_lives,
+            player: 0,
+        }
+    }
+
+    // every player's hand must be given, in slot order
+    pub fn hand(mut self, player: Player, cards: &str) -> Self {
+        self.hands.insert(player, parse_cards(cards));
+        self
+    }
+
+    pub fn firework(mut self, color: Color, top: Value) -> Self {
+        self.fireworks.insert(color, top);
+        self
+    }
+
+    pub fn discard(mut self, cards: &str) -> Self {
+        self.discard.extend(parse_cards(cards));
+        self
+    }
+
+    // the cards left to draw, first drawn first.  By default, every card not
+    // in a hand, on a firework, or in the discard, in an arbitrary fixed order
+    pub fn deck(mut self, cards: &str) -> Self {
+        self.deck = Some(parse_cards(cards));
+        self
+    }
+
+    pub fn hints(mut self, hints: u32) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    pub fn lives(mut self, lives: u32) -> Self {
+        self.lives = lives;
+        self
+    }
+
+    pub fn player_to_move(mut self, player: Player) -> Self {
+        self.player = player;
+        self
+    }
+
+    pub fn build(self) -> GameState {
+        let opts = self.opts;
+        assert!(self.hints <= opts.num_hints, "{} hints is more than the maximum of {}", self.hints, opts.num_hints);
+        assert!(0 < self.lives && self.lives <= opts.num_lives, "Can't start with {} lives", self.lives);
+        assert!(self.player < opts.num_players, "No player {}", self.player);
+
+        // check that no card is used more times than it is in the deck
+        let mut used = CardCounts::new();
+        let mut use_card = |card: &Card| {
+            used.increment(card);
+            assert!(used.get_count(card) <= get_count_for_value(card.value), "Too many copies of {}", card);
+        };
+        let mut hands = FnvHashMap::default();
+        for player in 0..opts.num_players {
+            let hand = self.hands.get(&player).cloned().unwrap_or_else(|| panic!("No hand given for player {}", player));
+            assert!(hand.len() as u32 == opts.hand_size,
+                    "Player {} has {} cards, instead of {}", player, hand.len(), opts.hand_size);
+            for card in hand.iter() {
+                use_card(card);
+            }
+            hands.insert(player, hand);
+        }
+        for (&color, &top) in self.fireworks.iter() {
+            for value in 1..top + 1 {
+                use_card(&Card::new(color, value));
+            }
+        }
+        for card in self.discard.iter() {
+            use_card(card);
+        }
+        let deck = match self.deck {
+            Some(deck) => {
+                for card in deck.iter() {
+                    use_card(card);
+                }
+                deck
+            }
+            None => {
+                let mut deck = Vec::new();
+                for &color in COLORS.iter() {
+                    for &value in VALUES.iter() {
+                        let card = Card::new(color, value);
+                        for _ in 0..get_count_for_value(value) - used.get_count(&card) {
+                            deck.push(card.clone());
+                        }
+                    }
+                }
+                deck
+            }
+        };
+
+        let mut board = BoardState::new(opts, get_total_cards());
+        board.deck_size = deck.len() as u32;
+        // the cards in hands were the last ones drawn before the rest of the deck
+        board.next_card_id = get_total_cards() - board.deck_size - opts.num_players * opts.hand_size;
+        for player in 0..opts.num_players {
+            board.deal_card_ids(player, opts.hand_size);
+        }
+        for (&color, &top) in self.fireworks.iter() {
+            let firework = board.fireworks.get_mut(&color).unwrap();
+            for value in 1..top + 1 {
+                firework.place(&Card::new(color, value));
+            }
+        }
+        for card in self.discard {
+            board.discard.place(card);
+        }
+        board.discard_loss = PERFECT_SCORE - board.max_attainable_score();
+        board.hints_remaining = self.hints;
+        board.lives_remaining = self.lives;
+        board.player = self.player;
+        board.turn = self.player + 1;
+
+        GameState {
+            hands: hands,
+            board: board,
+            // cards are drawn from the end of the deck
+            deck: deck.into_iter().rev().collect(),
+            turn_history: Vec::new(),
+            strategy_metrics: FnvHashMap::default(),
+        }
+    }
+}
+
+// A position with every player's strategy initialized from it,
+// for asking what a strategy would do and how it interprets moves
+pub struct Position {
+    pub game: GameState,
+    strategies: FnvHashMap<Player, Box<PlayerStrategy>>,
+}
+impl Position {
+    pub fn new(game: GameState, game_strategy: Box<GameStrategy>) -> Position {
+        let opts = game.board.options();
+        let strategies = game.get_players().map(|player| {
+            let rng = rand::ChaChaRng::from_seed(&[0, player]);
+            let view = game.get_view(player);
+            (player, game_strategy.initialize(&InitContext::new(&*game_strategy, &opts, &view).with_deck(&game.deck), rng))
+        }).collect();
+        Position {
+            game: game,
+            strategies: strategies,
+        }
+    }
+
+    // what the player to move would do, without making the move
+    pub fn decide(&mut self) -> TurnChoice {
+        let player = self.game.board.player;
+        self.strategies.get_mut(&player).unwrap().decide(&self.game.get_view(player))
+    }
+
+    // makes a move, whether or not the strategy would have made it, and has every player interpret it
+    pub fn apply(&mut self, choice: TurnChoice) -> TurnRecord {
+        assert!(!self.game.is_over(), "Tried to move after the game ended");
+        let turn = self.game.process_choice(choice);
+        for player in self.game.get_players() {
+            self.strategies.get_mut(&player).unwrap().update(&turn, &self.game.get_view(player));
+        }
+        turn
+    }
+
+    // what `player` believes is common knowledge about their own hand,
+    // see PlayerStrategy::public_card_probabilities
+    pub fn card_probabilities(&self, player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        self.strategies[&player].public_card_probabilities(player)
+    }
+}
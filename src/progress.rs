@@ -0,0 +1,105 @@
+use std::cmp;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clock::Clock;
+use game::*;
+use observer::GameObserver;
+
+// A progress bar for a simulation, shared by all of its threads, drawn on one line of stderr:
+//   cheat 3p [#########-----------]  9000/20000 games, 2250 games/s, ETA 0:04, average 24.9760
+
+const BAR_WIDTH: usize = 20;
+
+struct ProgressState {
+    games: u32,
+    total_score: u64,
+    // when the bar was last drawn, to avoid slowing down fast simulations
+    last_drawn: Option<Duration>,
+}
+
+pub struct ProgressBar {
+    label: String,
+    total: u32,
+    clock: Arc<Clock>,
+    state: Mutex<ProgressState>,
+}
+impl ProgressBar {
+    pub fn new(label: &str, total: u32, clock: Arc<Clock>) -> ProgressBar {
+        ProgressBar {
+            label: label.to_string(),
+            total: total,
+            clock: clock,
+            state: Mutex::new(ProgressState {
+                games: 0,
+                total_score: 0,
+                last_drawn: None,
+            }),
+        }
+    }
+
+    pub fn record(&self, score: Score) {
+        let mut state = self.state.lock().unwrap();
+        state.games += 1;
+        state.total_score += score as u64;
+        let now = self.clock.now();
+        let redraw = match state.last_drawn {
+            Some(last_drawn) => now >= last_drawn + Duration::from_millis(100),
+            None => true,
+        };
+        if redraw {
+            state.last_drawn = Some(now);
+            eprint!("\r{}", self.line(&state, now));
+        }
+    }
+
+    // draws the final state, and moves on from the bar's line
+    pub fn finish(&self) {
+        eprintln!("\r{}", self.status());
+        io::stderr().flush().unwrap();
+    }
+
+    // the bar as it would be drawn now
+    pub fn status(&self) -> String {
+        let state = self.state.lock().unwrap();
+        self.line(&state, self.clock.now())
+    }
+
+    fn line(&self, state: &ProgressState, now: Duration) -> String {
+        // with deal filters, fewer games than seeds may be played
+        let fraction = if self.total == 0 { 1.0 } else { state.games as f64 / self.total as f64 };
+        let filled = cmp::min(BAR_WIDTH, (fraction * BAR_WIDTH as f64) as usize);
+        let seconds = now.as_secs() as f64 + now.subsec_nanos() as f64 * 1e-9;
+        let rate = if seconds > 0.0 { state.games as f64 / seconds } else { 0.0 };
+        let eta = if rate > 0.0 {
+            let remaining = (self.total.saturating_sub(state.games) as f64 / rate).round() as u64;
+            format!("{}:{:02}", remaining / 60, remaining % 60)
+        } else {
+            String::from("?")
+        };
+        let average = if state.games > 0 { state.total_score as f64 / state.games as f64 } else { 0.0 };
+        format!("{} [{}{}] {:>w$}/{} games, {:.0} games/s, ETA {}, average {:.4}",
+                self.label, "#".repeat(filled), "-".repeat(BAR_WIDTH - filled),
+                state.games, self.total, rate, eta, average, w = self.total.to_string().len())
+    }
+}
+
+// Reports each finished game to a progress bar
+pub struct ProgressObserver {
+    bar: Arc<ProgressBar>,
+}
+impl ProgressObserver {
+    pub fn new(bar: Arc<ProgressBar>) -> ProgressObserver {
+        ProgressObserver {
+            bar: bar,
+        }
+    }
+}
+impl GameObserver for ProgressObserver {
+    fn on_turn(&mut self, _: &TurnRecord, _: &BoardState) {
+    }
+    fn on_game_end(&mut self, game: &GameState) {
+        self.bar.record(game.score());
+    }
+}
@@ -0,0 +1,116 @@
+use game::*;
+use html_replay::describe_turn;
+use json::Json;
+use json_output::{ActionExport, GameExport};
+use strategies::cheat_search::Solver;
+
+// Finds puzzles in played games: positions where, with every card known (including the order
+// of the deck), exactly one move still lets the players reach the best score left.
+//
+// Each legal move is judged by searching every way the rest of the game could be played on the
+// true deck (see cheat_search::Solver::exact), so a puzzle's solution is proven to be the only
+// move that reaches the best score left.
+
+pub struct Puzzle {
+    pub seed: u32,
+    // the game up to the position, in hanab.live's format, so it can be loaded there
+    pub game: GameExport,
+    pub position: GameState,
+    // the move that solves the puzzle, as it was made
+    pub solution: TurnRecord,
+    // the score the solution reaches
+    pub score: Score,
+}
+impl Puzzle {
+    pub fn to_json(&self) -> Json {
+        let board = &self.position.board;
+        let cards = |cards: &Cards| cards.iter().map(|card| card.to_string()).collect::<Vec<_>>().join(" ");
+        let fireworks = COLORS.iter().fold(Json::object(), |json, &color| {
+            json.with(&color.to_string(), board.get_firework(color).top)
+        });
+        Json::object()
+            .with("seed", self.seed)
+            .with("turn", board.turn)
+            .with("position", Json::object()
+                  .with("player", board.player)
+                  .with("hands", self.position.get_players().map(|player| {
+                      cards(&self.position.hands[&player])
+                  }).collect::<Vec<_>>())
+                  .with("fireworks", fireworks)
+                  .with("discard", cards(&board.discard.cards))
+                  .with("deck_size", board.deck_size)
+                  .with("hints", board.hints_remaining)
+                  .with("lives", board.lives_remaining))
+            .with("solution", Json::object()
+                  .with("description", describe_turn(&self.solution))
+                  .with("action", ActionExport::from_turn(&self.solution).to_json()))
+            .with("score", self.score)
+            .with("game", self.game.to_json())
+    }
+}
+
+// The puzzles in the game played by `choices` on `deck` (as for GameState::new), among the
+// positions with at most `max_deck_size` cards left to draw
+pub fn find_puzzles(opts: &GameOptions, seed: u32, deck: &Cards, choices: &[TurnChoice], max_deck_size: u32)
+    -> Vec<Puzzle> {
+    // positions don't depend on how they were reached, so one solver serves the whole game
+    let mut solver = Solver::exact();
+    let mut game = GameState::new(opts, deck.clone());
+    // the deck in the order the cards are drawn, as hanab.live wants it
+    let mut draw_order = Cards::new();
+    for player in game.get_players() {
+        draw_order.extend(game.hands[&player].iter().cloned());
+    }
+    draw_order.extend(game.deck.iter().rev().cloned());
+
+    let mut puzzles = Vec::new();
+    for choice in choices {
+        if game.is_over() {
+            break;
+        }
+        if game.board.deck_size <= max_deck_size {
+            if let Some((solution, score)) = unique_solution(&mut solver, &mut game) {
+                let game_export = GameExport {
+                    players: game.get_players().map(|player| format!("Player {}", player)).collect(),
+                    deck: draw_order.clone(),
+                    actions: game.turn_history.iter().map(ActionExport::from_turn).collect(),
+                    variant: Some(String::from("No Variant")),
+                    seed: Some(seed.to_string()),
+                    score: None,
+                    version: None,
+                    empathy: None,
+                    notes: None,
+                };
+                let mut solved = game.clone();
+                puzzles.push(Puzzle {
+                    seed: seed,
+                    game: game_export,
+                    position: game.clone(),
+                    solution: solved.process_choice(solution),
+                    score: score,
+                });
+            }
+        }
+        game.process_choice(choice.clone());
+    }
+    puzzles
+}
+
+// The only legal move in `game` that still reaches the best score left, and that score, if there
+// is exactly one such move, and there was a choice to make.
+// The game is put back as it was afterwards
+fn unique_solution(solver: &mut Solver, game: &mut GameState) -> Option<(TurnChoice, Score)> {
+    let legal = {
+        let view = game.get_view(game.board.player);
+        game.board.legal_choices(&view).collect::<Vec<_>>()
+    };
+    if legal.len() < 2 {
+        return None;
+    }
+    let target = solver.best_score(game);
+    let mut winning = legal.into_iter().filter(|choice| solver.score_after(game, choice) == target);
+    match (winning.next(), winning.next()) {
+        (Some(solution), None) => Some((solution, target)),
+        _ => None,
+    }
+}
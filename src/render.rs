@@ -0,0 +1,227 @@
+use std::fmt;
+
+use game::*;
+use helpers::*;
+
+// Shows cards, the board, and what is known about cards as text, for people: the terminal viewer
+// (see watch.rs), the debugger, and debug logs.
+//
+// Cards can be shown in one of a few styles (see RenderStyle), so that colors can be told apart
+// without relying on seeing them.  The Display implementations in game.rs are the plain style.
+//
+// A card's possibilities are a grid, with a row per color and a column per value, showing how
+// many copies of each card it could be, or `.` if it can't be that card.  A hand is its cards'
+// grids side by side, oldest first:
+//
+//        0     1     2     3     4
+//       12345 12345 12345 12345 12345
+//     r 32221 ..... 3.... 32221 32221
+//     y 32221 ..... ..... 32221 32221
+//     g 32221 32221 ..... 32221 32221
+//     b 32221 ..... ..... 32221 32221
+//     w 32221 ..... ..... 32221 32221
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum RenderStyle {
+    // colors as letters, e.g. r1
+    Plain,
+    // colors as letters, drawn in that color with ANSI escape codes
+    Ansi,
+    // colors as suit symbols, e.g. ♥1, which need no color to tell apart
+    Unicode,
+}
+
+fn ansi_code(color: Color) -> &'static str {
+    match color {
+        'r' => "1;31",
+        'y' => "1;33",
+        'g' => "1;32",
+        'b' => "1;34",
+        'w' => "1;37",
+        _ => "0",
+    }
+}
+
+// text in a card's color, using ANSI escape codes, which take up no room on screen
+pub fn colored(color: Color, text: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", ansi_code(color), text)
+}
+
+fn suit_symbol(color: Color) -> char {
+    match color {
+        'r' => '♥',
+        'y' => '★',
+        'g' => '♣',
+        'b' => '♠',
+        'w' => '●',
+        _ => '?',
+    }
+}
+
+// a color, one character wide on screen
+pub fn color_str(color: Color, style: RenderStyle) -> String {
+    match style {
+        RenderStyle::Plain => color.to_string(),
+        RenderStyle::Ansi => colored(color, &color.to_string()),
+        RenderStyle::Unicode => suit_symbol(color).to_string(),
+    }
+}
+
+// a card, two characters wide on screen
+pub fn card_str(card: &Card, style: RenderStyle) -> String {
+    match style {
+        RenderStyle::Ansi => colored(card.color, &card.to_string()),
+        _ => format!("{}{}", color_str(card.color, style), card.value),
+    }
+}
+
+pub fn firework_str(firework: &Firework, style: RenderStyle) -> String {
+    let color = color_str(firework.color, style);
+    if firework.complete() {
+        format!("{} firework complete!", color)
+    } else {
+        format!("{} firework at {}", color, firework.top)
+    }
+}
+
+// Something from game.rs shown in a given style, e.g. `styled(&game, RenderStyle::Unicode)`,
+// which can be printed like the thing itself
+pub struct Styled<'a, T: 'a> {
+    value: &'a T,
+    style: RenderStyle,
+}
+pub fn styled<T>(value: &T, style: RenderStyle) -> Styled<T> {
+    Styled { value: value, style: style }
+}
+
+impl<'a> fmt::Display for Styled<'a, Card> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&card_str(self.value, self.style))
+    }
+}
+
+impl<'a> fmt::Display for Styled<'a, Firework> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&firework_str(self.value, self.style))
+    }
+}
+
+// how many of each card have been discarded, a line per color
+impl<'a> fmt::Display for Styled<'a, Discard> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &color in COLORS.iter() {
+            try!(f.write_str(&format!("{}: ", color_str(color, self.style))));
+            let counts = VALUES.iter().map(|&value| {
+                let total = get_count_for_value(value);
+                let count = total - self.value.remaining(&Card::new(color, value));
+                format!("{}/{} {}s", count, total, value)
+            }).collect::<Vec<_>>();
+            try!(f.write_str(&format!("{}\n", counts.join(", "))));
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Styled<'a, BoardState> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let board = self.value;
+        if board.is_over() {
+            try!(f.write_str(&format!(
+                "Turn {} (GAME ENDED):\n", board.turn
+            )));
+        } else {
+            try!(f.write_str(&format!(
+                "Turn {} (Player {}'s turn):\n", board.turn, board.player
+            )));
+        }
+
+        try!(f.write_str(&format!(
+            "{} cards remaining in deck\n", board.deck_size
+        )));
+        if board.deck_size == 0 {
+            match board.endgame_rule {
+                EndgameRule::FinalRound => {
+                    try!(f.write_str(&format!(
+                        "Deck is empty.  {} turns remaining in game\n", board.deckless_turns_remaining
+                    )));
+                }
+                EndgameRule::UntilStuck => {
+                    try!(f.write_str("Deck is empty.  Playing until no cards are playable\n"));
+                }
+            }
+        }
+        try!(f.write_str(&format!(
+            "{}{}/{} hints remaining\n", board.hints_remaining,
+            if board.half_hint { " and a half" } else { "" }, board.hints_total
+        )));
+        try!(f.write_str(&format!(
+            "{}/{} lives remaining\n", board.lives_remaining, board.lives_total
+        )));
+        try!(f.write_str("Fireworks:\n"));
+        for &color in COLORS.iter() {
+            try!(f.write_str(&format!("  {}\n", styled(board.get_firework(color), self.style))));
+        }
+        try!(f.write_str("Discard:\n"));
+        try!(f.write_str(&format!("{}\n", styled(&board.discard, self.style))));
+
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Styled<'a, GameState> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let game = self.value;
+        try!(f.write_str("\n"));
+        try!(f.write_str("======\n"));
+        try!(f.write_str("Hands:\n"));
+        try!(f.write_str("======\n"));
+        for player in game.board.get_players() {
+            let hand = &game.hands.get(&player).unwrap();
+            try!(f.write_str(&format!("player {}:", player)));
+            for card in hand.iter() {
+                try!(f.write_str(&format!("    {}", styled(card, self.style))));
+            }
+            try!(f.write_str(&"\n"));
+        }
+        try!(f.write_str("======\n"));
+        try!(f.write_str("Board:\n"));
+        try!(f.write_str("======\n"));
+        try!(f.write_str(&format!("{}", styled(&game.board, self.style))));
+        Ok(())
+    }
+}
+
+// how many copies of `card` a card could be, as one character
+fn weight_char(table: &CardPossibilityTable, card: &Card) -> char {
+    match table.get_weight(card) as u32 {
+        0 => '.',
+        weight if weight < 10 => ::std::char::from_digit(weight, 10).unwrap(),
+        _ => '+',
+    }
+}
+
+// the rows of a card's grid, one per color, each VALUES.len() characters wide
+pub fn card_grid(table: &CardPossibilityTable) -> Vec<String> {
+    COLORS.iter().map(|&color| {
+        VALUES.iter().map(|&value| weight_char(table, &Card::new(color, value))).collect()
+    }).collect()
+}
+
+// The grids of every card in a hand, side by side, under a header of slot numbers and values,
+// with each row labelled by its color in the given style.
+pub fn hand_grid(hand_info: &HandInfo<CardPossibilityTable>, style: RenderStyle) -> String {
+    let width = VALUES.len();
+    let grids = hand_info.iter().map(card_grid).collect::<Vec<_>>();
+    let values = VALUES.iter().map(|value| value.to_string()).collect::<String>();
+    let slots = (0..grids.len()).map(|i| format!("{:<width$}", i, width = width)).collect::<Vec<_>>();
+    let mut lines = vec![
+        format!("  {}", slots.join(" ")),
+        format!("  {}", vec![values; grids.len()].join(" ")),
+    ];
+    for (row, &color) in COLORS.iter().enumerate() {
+        let label = color_str(color, style);
+        let cells = grids.iter().map(|grid| &grid[row][..]).collect::<Vec<_>>();
+        lines.push(format!("{} {}", label, cells.join(" ")));
+    }
+    lines.join("\n")
+}
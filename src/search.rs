@@ -0,0 +1,358 @@
+use std::rc::Rc;
+use rand::{self, Rng, SeedableRng};
+
+use game::*;
+use helpers::*;
+use json::Json;
+use strategy::*;
+
+// Monte-Carlo search for strategies that can't see their own cards.  Wraps a strategy whose
+// players can say what they know about their own hands (PlayerStrategy::own_hand_knowledge):
+//
+// On each turn, the wrapped strategy's move is compared with the other plays and discards,
+// by sampling ways the game could really be (our hand, consistent with what we know, and the
+// order of the deck), and playing each game out with the wrapped strategy from the start,
+// replaying the turns so far.  The move with the best average final score is made, if it
+// beats the wrapped strategy's move by enough.  Every move is tried on the same samples.
+//
+// Conventions can give a move a meaning that isn't true of the sampled game (e.g. the information
+// strategy reads a choice between cards known to be playable as a hint), and strategies may panic
+// on what they can't make sense of.  A move whose rollouts panic is ruled out.
+//
+// The wrapped strategy's GameStrategy starts the players of every rollout, so it shouldn't
+// keep state shared between the players of one game.  A strategy that can't say what its
+// players know just makes its own moves.
+
+pub struct DeterminizerConfig {
+    inner: Box<GameStrategyConfig + Sync>,
+    // how many ways the game could be to try each move on
+    rollouts: u32,
+    // how much better, in average points, a move must do than the wrapped strategy's choice
+    // to be made instead
+    margin: f64,
+}
+impl DeterminizerConfig {
+    pub fn new(inner: Box<GameStrategyConfig + Sync>) -> DeterminizerConfig {
+        DeterminizerConfig {
+            inner: inner,
+            rollouts: 4,
+            margin: 1.0,
+        }
+    }
+}
+impl GameStrategyConfig for DeterminizerConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        Box::new(Determinizer {
+            inner: Rc::new(self.inner.initialize(opts)),
+            rollouts: self.rollouts,
+            margin: self.margin,
+        })
+    }
+
+    fn supports(&self, opts: &GameOptions) -> Result<(), String> {
+        // the deck's order is rebuilt from the order cards were drawn in, which misplays
+        // returned to the deck would scramble
+        if opts.misplay_rule != MisplayRule::Discard {
+            return Err(String::from("Searching needs misplayed cards to be discarded"));
+        }
+        self.inner.supports(opts)
+    }
+
+    fn on_game_end(&self, game: &GameState) {
+        self.inner.on_game_end(game)
+    }
+
+    fn version(&self) -> u32 {
+        self.inner.version()
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "rollouts" => parse_param(name, value, &mut self.rollouts),
+            "margin" => parse_param(name, value, &mut self.margin),
+            _ => self.inner.set_param(name, value),
+        }
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        self.inner.conventions().map(|conventions| {
+            Conventions {
+                name: format!("{}+mc", conventions.name),
+                .. conventions
+            }.section("Search", vec![
+                String::from("Find the move the strategy above would make."),
+                String::from("Try it, and every play and discard that could matter, on the same sampled hands \
+                              and deck orderings consistent with what we know, playing each game out with \
+                              the strategy above."),
+                format!("Make the move with the best average final score over {} samples, if it beats \
+                         the strategy's own move by more than {} points; otherwise make that move.",
+                        self.rollouts, self.margin),
+            ])
+            .param("rollouts", self.rollouts)
+            .param("margin", self.margin)
+        })
+    }
+}
+
+pub struct Determinizer {
+    inner: Rc<Box<GameStrategy>>,
+    rollouts: u32,
+    margin: f64,
+}
+impl GameStrategy for Determinizer {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(DeterminizerPlayer {
+            inner: self.inner.initialize(context, rng.clone()),
+            policy: self.inner.clone(),
+            me: context.player,
+            rollouts: self.rollouts,
+            margin: self.margin,
+            rng: rng,
+            overrides: 0,
+        })
+    }
+
+    fn seat_name(&self, player: Player) -> String {
+        format!("{}+mc", self.inner.seat_name(player))
+    }
+}
+
+// Every card that could be in the deck or in our hand, i.e. not in another hand,
+// played, or discarded
+fn unseen_cards(view: &BorrowedGameView) -> Cards {
+    let mut counts = CardCounts::new();
+    for card in view.other_hands.values().flat_map(|hand| hand.iter()).chain(view.board.discard.cards.iter()) {
+        counts.increment(card);
+    }
+    for &color in COLORS.iter() {
+        for value in 1..view.board.get_firework(color).top + 1 {
+            counts.increment(&Card::new(color, value));
+        }
+    }
+    let mut cards = Vec::new();
+    for &color in COLORS.iter() {
+        for &value in VALUES.iter() {
+            let card = Card::new(color, value);
+            for _ in 0..counts.remaining(&card) {
+                cards.push(card.clone());
+            }
+        }
+    }
+    cards
+}
+
+// A hand we could have, given what we know about each card, drawn from the unseen cards.
+// Returns the hand and the unseen cards left over, or None if a sample keeps getting stuck
+fn sample_hand<R: Rng>(unseen: &Cards, knowledge: &HandInfo<CardPossibilityTable>, rng: &mut R)
+    -> Option<(Cards, Cards)> {
+    // each card is drawn from the copies left that it could be, so knowledge of one card
+    // (say, that it's the last red 5) can rule out others.  That can get stuck; then start over
+    for _ in 0..100 {
+        let mut left = unseen.clone();
+        let mut hand = Vec::with_capacity(knowledge.len());
+        for table in knowledge.iter() {
+            let options = (0..left.len()).filter(|&i| table.is_possible(&left[i])).collect::<Vec<_>>();
+            match rng.choose(&options) {
+                Some(&i) => hand.push(left.swap_remove(i)),
+                None => break,
+            }
+        }
+        if hand.len() == knowledge.len() {
+            return Some((hand, left));
+        }
+    }
+    None
+}
+
+// A full deck, in its original order, that could have produced the game so far: the cards
+// drawn so far in the order they were drawn, with our hand as sampled, and the rest shuffled.
+// Cards are drawn from the end of the deck, and numbered in the order they're drawn
+pub fn consistent_deck<R: Rng>(view: &BorrowedGameView, knowledge: &HandInfo<CardPossibilityTable>, rng: &mut R)
+    -> Option<Cards> {
+    let board = view.board;
+    let (hand, mut undrawn) = match sample_hand(&unseen_cards(view), knowledge, rng) {
+        Some(sample) => sample,
+        None => return None,
+    };
+    assert_eq!(undrawn.len() as u32, board.deck_size);
+    rng.shuffle(&mut undrawn[..]);
+
+    let mut drawn = vec![None; board.next_card_id as usize];
+    for turn in view.history.iter() {
+        if let TurnResult::Play(ref card, _) | TurnResult::Discard(ref card) = turn.result {
+            drawn[turn.card_id.expect("Played or discarded card without an id") as usize] = Some(card.clone());
+        }
+    }
+    for player in board.get_players() {
+        let cards = if player == view.player { &hand } else { view.other_hands[&player] };
+        for (&card_id, card) in board.card_ids(&player).iter().zip(cards.iter()) {
+            drawn[card_id as usize] = Some(card.clone());
+        }
+    }
+    let mut deck = undrawn;
+    deck.extend(drawn.into_iter().rev().map(|card| card.expect("A drawn card is unaccounted for")));
+    Some(deck)
+}
+
+// The final score of a game played on `deck` whose turns are `choices`, after which everyone
+// plays by `policy`, or None if a player panicked
+pub fn rollout(opts: &GameOptions, policy: &GameStrategy, deck: Cards, choices: &[TurnChoice], seed: u32) -> Option<Score> {
+    catch_quietly(|| play_out(opts, policy, deck, choices, seed)).ok()
+}
+
+fn play_out(opts: &GameOptions, policy: &GameStrategy, deck: Cards, choices: &[TurnChoice], seed: u32) -> Score {
+    let mut game = GameState::new(opts, deck);
+    let mut players = game.get_players().map(|player| {
+        let rng = rand::ChaChaRng::from_seed(&[seed, player]);
+        let view = game.get_view(player);
+        policy.initialize(&InitContext::new(policy, opts, &view).with_deck(&game.deck), rng)
+    }).collect::<Vec<_>>();
+    let mut choices = choices.iter();
+    while !game.is_over() {
+        let player = game.board.player;
+        let replaying = choices.len() > 1;
+        let choice = match choices.next() {
+            Some(choice) => choice.clone(),
+            None => players[player as usize].decide(&game.get_view(player)),
+        };
+        let turn = game.process_choice(choice);
+        for player in game.get_players() {
+            let view = game.get_view(player);
+            if replaying {
+                players[player as usize].replay(&turn, &view);
+            } else {
+                players[player as usize].update(&turn, &view);
+            }
+        }
+    }
+    game.score()
+}
+
+pub struct DeterminizerPlayer {
+    inner: Box<PlayerStrategy>,
+    policy: Rc<Box<GameStrategy>>,
+    me: Player,
+    rollouts: u32,
+    margin: f64,
+    rng: StrategyRng,
+    // how many times the search made a different move than the wrapped strategy
+    overrides: u32,
+}
+impl DeterminizerPlayer {
+    // the moves worth comparing: the wrapped strategy's, any play of a card that could be
+    // playable, and any discard
+    fn candidates(&self, view: &BorrowedGameView, knowledge: &HandInfo<CardPossibilityTable>,
+                  default: &TurnChoice) -> Vec<TurnChoice> {
+        let mut candidates = vec![default.clone()];
+        for (i, table) in knowledge.iter().enumerate() {
+            if table.probability_is_playable(view.board) > 0.0 {
+                candidates.push(TurnChoice::Play(i));
+            }
+            candidates.push(TurnChoice::Discard(i));
+        }
+        let mut unique = Vec::new();
+        for candidate in candidates {
+            if !unique.contains(&candidate) {
+                unique.push(candidate);
+            }
+        }
+        unique
+    }
+}
+impl PlayerStrategy for DeterminizerPlayer {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        let default = self.inner.decide(view);
+        let knowledge = match self.inner.own_hand_knowledge(view) {
+            Some(knowledge) => knowledge,
+            None => return default,
+        };
+        let candidates = self.candidates(view, &knowledge, &default);
+        if candidates.len() == 1 {
+            return default;
+        }
+
+        let opts = view.board.options();
+        let mut choices = view.history.iter().map(|turn| turn.choice.clone()).collect::<Vec<_>>();
+        // None once a candidate's rollout has panicked
+        let mut totals = vec![Some(0); candidates.len()];
+        let mut samples = 0;
+        for _ in 0..self.rollouts {
+            let deck = match consistent_deck(view, &knowledge, &mut self.rng) {
+                Some(deck) => deck,
+                None => continue,
+            };
+            let seed = self.rng.next_u32();
+            for (total, candidate) in totals.iter_mut().zip(candidates.iter()) {
+                if total.is_none() {
+                    continue;
+                }
+                choices.push(candidate.clone());
+                let score = rollout(&opts, &**self.policy, deck.clone(), &choices, seed);
+                *total = total.and_then(|total| score.map(|score| total + score));
+                choices.pop();
+            }
+            samples += 1;
+        }
+        let default_total = match totals[0] {
+            Some(total) if samples > 0 => total,
+            _ => return default,
+        };
+        // ties go to the earliest candidate, and the wrapped strategy's choice is kept
+        // unless another move beats it by the margin
+        let mut best = 0;
+        for (i, total) in totals.iter().enumerate() {
+            if total.map_or(false, |total| total > totals[best].unwrap()) {
+                best = i;
+            }
+        }
+        let gain = (totals[best].unwrap() - default_total) as f64 / samples as f64;
+        if gain < self.margin {
+            return default;
+        }
+        debug!("Player {} searched {:?} instead of {:?}, gaining {:.2} points on average",
+               self.me, candidates[best], default, gain);
+        self.overrides += 1;
+        candidates[best].clone()
+    }
+
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.inner.update(turn, view);
+    }
+
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.inner.replay(turn, view);
+    }
+
+    fn concede(&mut self, view: &BorrowedGameView) -> bool {
+        self.inner.concede(view)
+    }
+
+    fn public_info_digest(&self) -> Option<u64> {
+        self.inner.public_info_digest()
+    }
+
+    fn public_card_probabilities(&self, player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        self.inner.public_card_probabilities(player)
+    }
+
+    fn own_hand_knowledge(&self, view: &BorrowedGameView) -> Option<HandInfo<CardPossibilityTable>> {
+        self.inner.own_hand_knowledge(view)
+    }
+
+    fn notes(&self) -> Vec<CardNote> {
+        self.inner.notes()
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.inner.metrics()
+    }
+
+    fn inspect(&self) -> Option<Json> {
+        let inner = self.inner.inspect().unwrap_or(Json::Null);
+        Some(Json::object()
+             .with("rollouts", self.rollouts)
+             .with("margin", self.margin)
+             .with("overrides", self.overrides)
+             .with("inner", inner))
+    }
+}
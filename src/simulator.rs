@@ -1,12 +1,21 @@
 use rand::{self, Rng, SeedableRng};
 use fnv::FnvHashMap;
+use std::cmp;
 use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use crossbeam;
 
+use clock::{Clock, SystemClock};
 use game::*;
+use helpers::*;
+use observer::*;
+use spans;
 use strategy::*;
+use strategies::examples::RandomStrategyConfig;
 
-fn new_deck(seed: u32) -> Cards {
+pub fn new_deck(seed: u32) -> Cards {
     let mut deck: Cards = Cards::new();
 
     for &color in COLORS.iter() {
@@ -22,47 +31,462 @@ fn new_deck(seed: u32) -> Cards {
     deck
 }
 
+// Decks shared by runs on the same seeds, e.g. of several strategies being compared, so each
+// seed's deck is only shuffled once
+pub struct DeckCache {
+    decks: Mutex<FnvHashMap<u32, Arc<Cards>>>,
+}
+impl DeckCache {
+    pub fn new() -> DeckCache {
+        DeckCache {
+            decks: Mutex::new(FnvHashMap::default()),
+        }
+    }
+
+    // the deck for `seed`, as new_deck would shuffle it
+    pub fn get(&self, seed: u32) -> Arc<Cards> {
+        if let Some(deck) = self.decks.lock().unwrap().get(&seed) {
+            return deck.clone();
+        }
+        // shuffled without the lock, so other threads can carry on meanwhile
+        let deck = Arc::new(new_deck(seed));
+        self.decks.lock().unwrap().entry(seed).or_insert(deck).clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.decks.lock().unwrap().len()
+    }
+}
+
+// Checks that a deck has exactly the cards of a full deck, in any order
+pub fn check_deck(deck: &Cards) -> Result<(), String> {
+    let mut counts = CardCounts::new();
+    for card in deck {
+        counts.increment(card);
+        if counts.get_count(card) > get_count_for_value(card.value) {
+            return Err(format!("The deck has too many copies of {}", card));
+        }
+    }
+    if deck.len() as u32 != get_total_cards() {
+        return Err(format!("The deck has {} cards, instead of {}", deck.len(), get_total_cards()));
+    }
+    Ok(())
+}
+
+// a predicate on shuffled decks, used to focus a simulation on a particular class of deals
+#[derive(Debug,Clone)]
+pub enum DealFilter {
+    // the opening hands contain at least this many fives
+    OpeningFives(u32),
+    // a critical card is among the last N cards to be drawn
+    CriticalAtBottom(u32),
+}
+impl DealFilter {
+    pub fn accepts(&self, opts: &GameOptions, deck: &Cards) -> bool {
+        match *self {
+            DealFilter::OpeningFives(min_fives) => {
+                // cards are drawn from the end of the deck
+                let dealt = (opts.num_players * opts.hand_size) as usize;
+                let fives = deck.iter().rev().take(dealt).filter(|card| {
+                    card.value == FINAL_VALUE
+                }).count();
+                fives as u32 >= min_fives
+            }
+            DealFilter::CriticalAtBottom(n) => {
+                deck.iter().take(n as usize).any(|card| {
+                    get_count_for_value(card.value) == 1
+                })
+            }
+        }
+    }
+}
+impl FromStr for DealFilter {
+    type Err = String;
+    fn from_str(s: &str) -> Result<DealFilter, String> {
+        let parts = s.splitn(2, ':').collect::<Vec<_>>();
+        if parts.len() != 2 {
+            return Err(format!("Expected a deal filter of the form NAME:N, got {}", s));
+        }
+        let n = try!(u32::from_str(parts[1]).map_err(|_| {
+            format!("Invalid number in deal filter: {}", parts[1])
+        }));
+        match parts[0] {
+            "opening-fives"      => Ok(DealFilter::OpeningFives(n)),
+            "critical-at-bottom" => Ok(DealFilter::CriticalAtBottom(n)),
+            _ => Err(format!("Unknown deal filter: {}", parts[0])),
+        }
+    }
+}
+// in the form from_str reads
+impl fmt::Display for DealFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DealFilter::OpeningFives(n)     => write!(f, "opening-fives:{}", n),
+            DealFilter::CriticalAtBottom(n) => write!(f, "critical-at-bottom:{}", n),
+        }
+    }
+}
+
+// The `shard`-th (0-based) of `n_shards` contiguous, disjoint pieces of `seeds`.
+// Together the shards cover every seed exactly once.
+pub fn shard_range(seeds: &Range<u32>, shard: u32, n_shards: u32) -> Range<u32> {
+    assert!(shard < n_shards, "Shard {} out of range for {} shards", shard, n_shards);
+    let len = (seeds.end - seeds.start) as u64;
+    let start = seeds.start + (len * shard as u64 / n_shards as u64) as u32;
+    let end = seeds.start + (len * (shard + 1) as u64 / n_shards as u64) as u32;
+    start..end
+}
+
+// Lazily yields (seed, deck) for a range of seeds, skipping deals rejected by the filters
+pub struct DealIterator<'a> {
+    opts: &'a GameOptions,
+    seeds: Range<u32>,
+    filters: &'a [DealFilter],
+    // where to get decks from, instead of shuffling them
+    decks: Option<&'a DeckCache>,
+    // how many seeds have been skipped so far
+    pub filtered: u32,
+}
+impl<'a> DealIterator<'a> {
+    pub fn new(opts: &'a GameOptions, seeds: Range<u32>, filters: &'a [DealFilter]) -> DealIterator<'a> {
+        DealIterator {
+            opts: opts,
+            seeds: seeds,
+            filters: filters,
+            decks: None,
+            filtered: 0,
+        }
+    }
+
+    // takes decks from the cache, e.g. one shared with other runs on the same seeds
+    pub fn with_decks(self, decks: Option<&'a DeckCache>) -> DealIterator<'a> {
+        DealIterator {
+            decks: decks,
+            .. self
+        }
+    }
+
+    // restrict to one of several disjoint pieces, e.g. for splitting a run across machines
+    pub fn shard(self, shard: u32, n_shards: u32) -> DealIterator<'a> {
+        let seeds = shard_range(&self.seeds, shard, n_shards);
+        DealIterator {
+            seeds: seeds,
+            .. self
+        }
+    }
+}
+impl<'a> Iterator for DealIterator<'a> {
+    type Item = (u32, Cards);
+    fn next(&mut self) -> Option<(u32, Cards)> {
+        while let Some(seed) = self.seeds.next() {
+            let deck = match self.decks {
+                Some(decks) => (*decks.get(seed)).clone(),
+                None => new_deck(seed),
+            };
+            if self.filters.iter().all(|filter| filter.accepts(self.opts, &deck)) {
+                return Some((seed, deck));
+            }
+            self.filtered += 1;
+        }
+        None
+    }
+}
+
 pub fn simulate_once(
         opts: &GameOptions,
         game_strategy: Box<GameStrategy>,
         seed: u32,
     ) -> GameState {
-    let deck = new_deck(seed);
+    simulate_once_observed(opts, game_strategy, seed, &mut LogObserver)
+}
 
+// Plays one game on an explicit deck, e.g. a real deal, instead of one shuffled from a seed.
+// The first card to be drawn is at the end of `deck`, and the players' random number generators
+// are seeded as for seed 0.
+pub fn simulate_once_with_deck(
+        opts: &GameOptions,
+        game_strategy: Box<GameStrategy>,
+        deck: Cards,
+    ) -> GameState {
+    check_deck(&deck).unwrap_or_else(|err| panic!("{}", err));
+    play_once(opts, game_strategy, 0, deck, &mut LogObserver)
+}
+
+// Plays one game on the deck for `seed`, e.g. from a DeckCache, rather than shuffling it again
+pub fn simulate_once_with_shared_deck(
+        opts: &GameOptions,
+        game_strategy: Box<GameStrategy>,
+        seed: u32,
+        deck: Arc<Cards>,
+    ) -> GameState {
+    play_once(opts, game_strategy, seed, (*deck).clone(), &mut LogObserver)
+}
+
+pub fn simulate_once_observed(
+        opts: &GameOptions,
+        game_strategy: Box<GameStrategy>,
+        seed: u32,
+        observer: &mut GameObserver,
+    ) -> GameState {
+    play_once(opts, game_strategy, seed, new_deck(seed), observer)
+}
+
+// A game played by stream(), once it is over
+#[derive(Debug,Clone)]
+pub struct GameResult {
+    pub seed: u32,
+    pub game: GameState,
+}
+
+// Plays a game on each seed in turn, only as they are asked for, so callers can show their own
+// progress, stop early, or write games out as they finish, instead of waiting for a SimResult.
+// The games are played on the calling thread.
+pub fn stream<'a>(
+        opts: &'a GameOptions,
+        strat_config: &'a mut GameStrategyConfig,
+        seeds: Range<u32>,
+    ) -> impl Iterator<Item=GameResult> + 'a {
+    strat_config.supports(opts).unwrap_or_else(|err| panic!("{}", err));
+    DealIterator::new(opts, seeds, &[]).map(move |(seed, deck)| {
+        let strategy = strat_config.initialize(opts);
+        let game = play_once(opts, strategy, seed, deck, &mut LogObserver);
+        strat_config.on_game_end(&game);
+        GameResult { seed: seed, game: game }
+    })
+}
+
+// Makes an observer for the game with the given seed
+pub type ObserverFactory<'a> = Fn(u32) -> Box<GameObserver> + Sync + 'a;
+
+// how many more times IllegalMovePolicy::Retry asks a player to decide, before forfeiting.
+// A player that repeats an illegal move forfeits at once
+const MAX_ILLEGAL_MOVE_RETRIES: u32 = 3;
+
+// Plays a whole game on the given deck
+fn play_once(
+        opts: &GameOptions,
+        game_strategy: Box<GameStrategy>,
+        seed: u32,
+        deck: Cards,
+        observer: &mut GameObserver,
+    ) -> GameState {
+    // with no choices to replay, none of them can be illegal
+    resume_once(opts, game_strategy, seed, deck, &[], observer).unwrap()
+}
+
+// Plays a game on the given deck, whose first turns are the choices in `prefix`.
+// The strategies reconstruct their state by replaying those turns, then play out the rest.
+// `seed` seeds the players' random number generators.
+// Fails if a choice in `prefix` is illegal, or comes after the game is over.
+pub fn resume_once(
+        opts: &GameOptions,
+        game_strategy: Box<GameStrategy>,
+        seed: u32,
+        deck: Cards,
+        prefix: &[TurnChoice],
+        observer: &mut GameObserver,
+    ) -> Result<GameState, String> {
+    let _game_span = spans::game_span(seed);
     let mut game = GameState::new(opts, deck);
+    observer.on_game_start(seed, &game);
 
     let mut strategies = game.get_players().map(|player| {
-        (player, game_strategy.initialize(player, &game.get_view(player)))
+        let rng = rand::ChaChaRng::from_seed(&[seed, player]);
+        let view = game.get_view(player);
+        (player, game_strategy.initialize(&InitContext::new(&*game_strategy, opts, &view).with_deck(&game.deck), rng))
     }).collect::<FnvHashMap<Player, Box<PlayerStrategy>>>();
 
+    for (i, choice) in prefix.iter().enumerate() {
+        if game.is_over() {
+            return Err(format!("The game is over after {} of the {} choices to replay", i, prefix.len()));
+        }
+        let _turn_span = spans::turn_span(game.board.turn, game.board.player);
+        let turn = try!(game.try_process_choice(choice.clone()).map_err(|illegal| {
+            format!("Turn {} of the choices to replay is illegal: {}", i, illegal)
+        }));
+        for player in game.get_players() {
+            let strategy = strategies.get_mut(&player).unwrap();
+            strategy.replay(&turn, &game.get_view(player));
+        }
+        observer.on_turn(&turn, &game.board);
+        observer.after_update(turn.player, &*strategies[&turn.player]);
+    }
+    if prefix.len() > 0 {
+        debug!("Resuming game after replaying {} turns", prefix.len());
+    }
+
     while !game.is_over() {
         let player = game.board.player;
+        let _turn_span = spans::turn_span(game.board.turn, player);
+        observer.before_turn(&game);
 
-        debug!("");
-        debug!("=======================================================");
-        debug!("Turn {}, Player {} to go", game.board.turn, player);
-        debug!("=======================================================");
-        debug!("{}", game);
-
-
-        let choice = {
+        if strategies.get_mut(&player).unwrap().concede(&game.get_view(player)) {
+            debug!("Player {} conceded on seed {}", player, seed);
+            game.concede();
+            break;
+        }
+        let mut choice = {
             let mut strategy = strategies.get_mut(&player).unwrap();
             strategy.decide(&game.get_view(player))
         };
+        observer.after_decide(&choice);
 
-        let turn = game.process_choice(choice);
+        // the illegal moves the player has made this turn
+        let mut tried = Vec::new();
+        let turn = loop {
+            let illegal = match game.try_process_choice(choice.clone()) {
+                Ok(turn) => break Some(turn),
+                Err(illegal) => illegal,
+            };
+            tried.push(choice);
+            if opts.illegal_moves == IllegalMovePolicy::Panic {
+                panic!("{}", illegal);
+            }
+            if opts.illegal_moves == IllegalMovePolicy::Retry && tried.len() as u32 <= MAX_ILLEGAL_MOVE_RETRIES {
+                let mut strategy = strategies.get_mut(&player).unwrap();
+                choice = strategy.decide(&game.get_view(player));
+                // a player that makes a move it already tried would only go on making it
+                if !tried.contains(&choice) {
+                    debug!("{}, asking again", illegal);
+                    continue;
+                }
+                debug!("{}, and player {} made it again", illegal, player);
+            }
+            warn!("{} on seed {}, forfeiting the game", illegal, seed);
+            game.forfeit();
+            break None;
+        };
+        let turn = match turn {
+            Some(turn) => turn,
+            None => break,
+        };
 
         for player in game.get_players() {
             let mut strategy = strategies.get_mut(&player).unwrap();
             strategy.update(&turn, &game.get_view(player));
         }
+        observer.on_turn(&turn, &game.board);
+        observer.after_update(player, &*strategies[&player]);
+    }
+    for strategy in strategies.values() {
+        for (name, value) in strategy.metrics() {
+            *game.strategy_metrics.entry(name.to_string()).or_insert(0.0) += value;
+        }
+    }
+    observer.on_game_end(&game);
+    Ok(game)
+}
+
+// Checks that the engine's bookkeeping stays consistent, panicking on the first violation
+struct InvariantChecker {
+    seed: u32,
+    turn: u32,
+    score: Score,
+}
+impl InvariantChecker {
+    fn check(&mut self, game: &GameState) {
+        let board = &game.board;
+        let seed = self.seed;
+        let fail = |message: String| -> ! {
+            panic!("Seed {}, turn {}: {}\n{}", seed, board.turn, message, game)
+        };
+
+        if board.hints_remaining > board.hints_total {
+            fail(format!("{} hints remaining, out of {}", board.hints_remaining, board.hints_total));
+        }
+        if board.lives_remaining > board.lives_total {
+            fail(format!("{} lives remaining, out of {}", board.lives_remaining, board.lives_total));
+        }
+
+        if board.deck_size as usize != game.deck.len() {
+            fail(format!("Board has deck size {}, but the deck has {} cards", board.deck_size, game.deck.len()));
+        }
+        let in_hands = game.hands.values().map(|hand| hand.len() as u32).sum::<u32>();
+        let on_fireworks = COLORS.iter().map(|&color| board.get_firework(color).top).sum::<u32>();
+        let accounted = board.discard.cards.len() as u32 + on_fireworks + in_hands + game.deck.len() as u32;
+        if accounted != board.total_cards {
+            fail(format!("{} cards are accounted for, out of {}", accounted, board.total_cards));
+        }
+        for (player, hand) in game.hands.iter() {
+            if hand.len() as u32 > board.hand_size || (board.deck_size > 0 && hand.len() as u32 != board.hand_size) {
+                fail(format!("Player {} holds {} cards, with a hand size of {} and {} cards in the deck",
+                             player, hand.len(), board.hand_size, board.deck_size));
+            }
+            if board.card_ids(player).len() != hand.len() {
+                fail(format!("Player {} holds {} cards, but {} card ids", player, hand.len(), board.card_ids(player).len()));
+            }
+        }
+        // misplays returned to the deck get drawn a second time
+        let returned = match board.misplay_rule {
+            MisplayRule::Discard => 0,
+            MisplayRule::ReturnToDeckBottom => game.turn_history.iter().filter(|turn| {
+                if let TurnResult::Play(_, false) = turn.result { true } else { false }
+            }).count() as u32,
+        };
+        let drawn = board.total_cards - board.deck_size + returned;
+        if board.next_card_id != drawn {
+            fail(format!("The next card id is {}, but {} cards have been drawn", board.next_card_id, drawn));
+        }
+        let mut held_ids = board.card_ids.values().flat_map(|ids| ids.iter().cloned()).collect::<Vec<_>>();
+        held_ids.sort();
+        held_ids.dedup();
+        if held_ids.len() as u32 != in_hands || held_ids.iter().any(|&id| id >= drawn) {
+            fail(format!("Held card ids {:?} aren't distinct ids of drawn cards", held_ids));
+        }
+
+        let discard_loss = PERFECT_SCORE - board.max_attainable_score();
+        if board.discard_loss != discard_loss {
+            fail(format!("{} points counted as lost to discards, but the discard rules out {}",
+                         board.discard_loss, discard_loss));
+        }
+
+        // not board.score(), which drops to 0 on striking out if strikeout_scores_zero is set
+        if on_fireworks < self.score {
+            fail(format!("Score went down from {} to {}", self.score, on_fireworks));
+        }
+        self.score = on_fireworks;
+
+        if board.turn != self.turn && board.turn != self.turn + 1 {
+            fail(format!("Turn went from {} to {}", self.turn, board.turn));
+        }
+        self.turn = board.turn;
+        if board.player != (board.turn - 1) % board.num_players {
+            fail(format!("Player {} is to go", board.player));
+        }
+    }
+}
+impl GameObserver for InvariantChecker {
+    fn on_game_start(&mut self, seed: u32, game: &GameState) {
+        self.seed = seed;
+        self.turn = game.board.turn;
+        self.score = 0;
+        self.check(game);
+    }
+    fn before_turn(&mut self, game: &GameState) {
+        self.check(game);
+    }
+    fn on_turn(&mut self, _: &TurnRecord, _: &BoardState) {
+    }
+    fn on_game_end(&mut self, game: &GameState) {
+        self.check(game);
+    }
+}
 
+// Plays games with the random strategy, checking invariants of the engine after every turn:
+// hint and life counts are in bounds, every card is somewhere, the score never goes down,
+// and turns rotate between players.  Panics describing the first violation.
+pub fn run_invariant_checks(opts: &GameOptions, first_seed: u32, n_games: u32) {
+    // vary how the random strategy plays, to reach different kinds of game states
+    let probabilities = [(0.4, 0.2), (0.7, 0.05), (0.1, 0.6)];
+    let mut checker = InvariantChecker { seed: 0, turn: 0, score: 0 };
+    for seed in first_seed..first_seed + n_games {
+        let (hint_probability, play_probability) = probabilities[seed as usize % probabilities.len()];
+        let config = RandomStrategyConfig {
+            hint_probability: hint_probability,
+            play_probability: play_probability,
+        };
+        simulate_once_observed(opts, config.initialize(opts), seed, &mut checker);
     }
-    debug!("");
-    debug!("=======================================================");
-    debug!("Final state:\n{}", game);
-    debug!("SCORE: {:?}", game.score());
-    game
 }
 
 #[derive(Debug)]
@@ -79,7 +503,7 @@ impl Histogram {
             total_count: 0,
         }
     }
-    fn insert_many(&mut self, val: Score, count: u32) {
+    pub fn insert_many(&mut self, val: Score, count: u32) {
         let new_count = self.get_count(&val) + count;
         self.hist.insert(val, new_count);
         self.sum += val * (count as u32);
@@ -113,14 +537,64 @@ impl Histogram {
             self.insert_many(val, count);
         }
     }
+    fn sorted_values(&self) -> Vec<Score> {
+        let mut values = self.hist.keys().cloned().collect::<Vec<_>>();
+        values.sort();
+        values
+    }
+    // the smallest value that at least `fraction` of the values are at most, e.g. 0.5 for the median
+    pub fn quantile(&self, fraction: f32) -> Option<Score> {
+        let needed = (fraction * self.total_count as f32).ceil().max(1.0) as u32;
+        let mut seen = 0;
+        for val in self.sorted_values() {
+            seen += self.get_count(&val);
+            if seen >= needed {
+                return Some(val);
+            }
+        }
+        None
+    }
+    pub fn median(&self) -> Option<Score> {
+        self.quantile(0.5)
+    }
+    // each value, with the fraction of values at most it
+    pub fn cumulative(&self) -> Vec<(Score, f32)> {
+        let mut seen = 0;
+        self.sorted_values().into_iter().map(|val| {
+            seen += self.get_count(&val);
+            (val, seen as f32 / self.total_count as f32)
+        }).collect()
+    }
+    // one character per value from the smallest to the largest, taller for more common values
+    pub fn sparkline(&self) -> String {
+        let bars = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let values = self.sorted_values();
+        let (min, max) = match (values.first(), values.last()) {
+            (Some(&min), Some(&max)) => (min, max),
+            _ => return String::new(),
+        };
+        let max_count = self.hist.values().cloned().max().unwrap();
+        (min..max + 1).map(|val| {
+            match self.get_count(&val) {
+                0 => ' ',
+                count => bars[((count - 1) as usize * bars.len()) / max_count as usize],
+            }
+        }).collect()
+    }
 }
 impl fmt::Display for Histogram {
+    // a line per value: its count, the percentage of values that are it and that are at most it,
+    // and a bar proportional to its count
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut keys = self.hist.keys().collect::<Vec<_>>();
-        keys.sort();
-        for val in keys {
+        const BAR_WIDTH: u32 = 40;
+        let max_count = self.hist.values().cloned().max().unwrap_or(0);
+        let count_width = max_count.to_string().len();
+        for (val, at_most) in self.cumulative() {
+            let count = self.get_count(&val);
             try!(f.write_str(&format!(
-                "\n{}: {}", val, self.get_count(val),
+                "\n{:>2}: {:>w$} {:6.2}% {:6.2}% {}", val, count,
+                100.0 * self.percentage_with(&val), 100.0 * at_most,
+                "#".repeat(((count * BAR_WIDTH + max_count - 1) / max_count) as usize), w = count_width,
             )));
         }
         Ok(())
@@ -134,75 +608,899 @@ pub fn simulate<T: ?Sized>(
         n_trials: u32,
         n_threads: u32,
         progress_info: Option<u32>,
+        deal_filters: &[DealFilter],
+        decks: Option<&DeckCache>,
+        observer_factory: Option<&ObserverFactory>,
+        analyses: Analyses,
+        clock: &Arc<Clock>,
     ) -> SimResult
     where T: GameStrategyConfig + Sync {
+    strat_config.supports(opts).unwrap_or_else(|err| panic!("{}", err));
 
     let first_seed = first_seed_opt.unwrap_or_else(|| rand::thread_rng().next_u32());
 
-    let strat_config_ref = &strat_config;
+    let seeds = first_seed..(first_seed + n_trials);
+    // shared by all threads, since configs can learn from each game
+    let strat_config_ref = &*strat_config;
+    // if the caller is catching panics quietly, the threads' panics are caught the same way
+    let quiet = &QuietScope::current();
     crossbeam::scope(|scope| {
         let mut join_handles = Vec::new();
         for i in 0..n_threads {
-            let start = first_seed + ((n_trials * i) / n_threads);
-            let end = first_seed + ((n_trials * (i+1)) / n_threads);
+            let thread_seeds = shard_range(&seeds, i, n_threads);
             join_handles.push(scope.spawn(move || {
+                let _quiet = quiet.enter();
+                let start = thread_seeds.start;
                 if progress_info.is_some() {
-                    info!("Thread {} spawned: seeds {} to {}", i, start, end);
+                    info!("Thread {} spawned: seeds {} to {}", i, start, thread_seeds.end);
                 }
-                let mut non_perfect_seeds = Vec::new();
-
-                let mut score_histogram = Histogram::new();
-                let mut lives_histogram = Histogram::new();
+                let mut result = SimResult::with_analyses(analyses);
+                let mut timer = DecideTimer::new(clock.clone());
 
-                for seed in start..end {
+                let mut deals = DealIterator::new(opts, thread_seeds, deal_filters).with_decks(decks);
+                while let Some((seed, deck)) = deals.next() {
                     if let Some(progress_info_frequency) = progress_info {
                         if (seed > start) && ((seed-start) % progress_info_frequency == 0) {
                             info!(
                                 "Thread {}, Trials: {}, Stats so far: {} score, {} lives, {}% win",
-                                i, seed-start, score_histogram.average(), lives_histogram.average(),
-                                score_histogram.percentage_with(&PERFECT_SCORE) * 100.0
+                                i, seed-start, result.average_score(), result.average_lives(),
+                                result.percent_perfect()
                             );
                         }
                     }
-                    let game = simulate_once(&opts, strat_config_ref.initialize(&opts), seed);
-                    let score = game.score();
-                    lives_histogram.insert(game.board.lives_remaining);
-                    score_histogram.insert(score);
-                    if score != PERFECT_SCORE { non_perfect_seeds.push(seed); }
+                    let strategy = strat_config_ref.initialize(&opts);
+                    let mut observer = match observer_factory {
+                        Some(make_observer) => make_observer(seed),
+                        None => Box::new(LogObserver),
+                    };
+                    let game = play_once(&opts, strategy, seed, deck, &mut TimedObserver {
+                        timer: &mut timer,
+                        inner: &mut *observer,
+                    });
+                    strat_config_ref.on_game_end(&game);
+                    result.add_game(seed, &game);
                 }
+                result.filtered_seeds = deals.filtered;
+                result.decide_times = timer.times;
                 if progress_info.is_some() {
                     info!("Thread {} done", i);
                 }
-                (non_perfect_seeds, score_histogram, lives_histogram)
+                result
             }));
         }
 
-        let mut non_perfect_seeds : Vec<u32> = Vec::new();
-        let mut score_histogram = Histogram::new();
-        let mut lives_histogram = Histogram::new();
+        let mut result = SimResult::with_analyses(analyses);
         for join_handle in join_handles {
-            let (thread_non_perfect_seeds, thread_score_histogram, thread_lives_histogram) = join_handle.join();
-            non_perfect_seeds.extend(thread_non_perfect_seeds.iter());
-            score_histogram.merge(thread_score_histogram);
-            lives_histogram.merge(thread_lives_histogram);
+            result.merge(join_handle.join());
         }
+        result.seed_scores.sort();
+        result
+    })
+}
 
-        non_perfect_seeds.sort();
-        SimResult {
-            scores: score_histogram,
-            lives: lives_histogram,
-            non_perfect_seed: non_perfect_seeds.get(0).cloned(),
+// A deal a strategy did badly on
+#[derive(Debug,Clone)]
+pub struct HardSeed {
+    pub seed: u32,
+    pub score: Score,
+    // how many turns the game lasted
+    pub turns: u32,
+    pub end_cause: EndCause,
+}
+
+// The worst games so far: lowest score first, then the game that ended soonest
+// (e.g. by striking out), then the lowest seed
+struct HardestGames {
+    n: usize,
+    games: Vec<HardSeed>,
+}
+impl HardestGames {
+    fn insert(&mut self, game: HardSeed) {
+        self.games.push(game);
+        self.games.sort_by_key(|game| (game.score, game.turns, game.seed));
+        self.games.truncate(self.n);
+    }
+}
+
+struct HardSeedObserver {
+    seed: u32,
+    hardest: Arc<Mutex<HardestGames>>,
+}
+impl GameObserver for HardSeedObserver {
+    fn on_turn(&mut self, _: &TurnRecord, _: &BoardState) {
+    }
+    fn on_game_end(&mut self, game: &GameState) {
+        self.hardest.lock().unwrap().insert(HardSeed {
+            seed: self.seed,
+            score: game.score(),
+            turns: game.board.turn - 1,
+            end_cause: game.board.end_cause().expect("The game should be over"),
+        });
+    }
+}
+
+// Simulates games like `simulate`, and returns the `n` seeds the strategy did worst on, worst first,
+// e.g. to collect a corpus of difficult deals to check convention changes against
+pub fn find_hard_seeds<T: ?Sized>(
+        opts: &GameOptions,
+        strat_config: Box<T>,
+        first_seed_opt: Option<u32>,
+        n_trials: u32,
+        n_threads: u32,
+        deal_filters: &[DealFilter],
+        n: usize,
+    ) -> Vec<HardSeed>
+    where T: GameStrategyConfig + Sync {
+    let hardest = Arc::new(Mutex::new(HardestGames { n: n, games: Vec::new() }));
+    let make_observer = |seed| {
+        let observers: Vec<Box<GameObserver>> = vec![
+            Box::new(LogObserver),
+            Box::new(HardSeedObserver { seed: seed, hardest: hardest.clone() }),
+        ];
+        Box::new(observers) as Box<GameObserver>
+    };
+    simulate(opts, strat_config, first_seed_opt, n_trials, n_threads, None, deal_filters, None, Some(&make_observer),
+             Analyses::none(), &SystemClock::shared());
+    let games = hardest.lock().unwrap().games.clone();
+    games
+}
+
+// The fireworks, the discard pile, and how many cards are left to draw, followed through a
+// finished game's history, for the analyses that need to know how the board stood on each turn
+struct BoardWalk<'a> {
+    board: &'a BoardState,
+    tops: FnvHashMap<Color, Value>,
+    discard: Discard,
+    cards_to_draw: u32,
+}
+impl<'a> BoardWalk<'a> {
+    // the board as dealt
+    fn new(game: &'a GameState) -> BoardWalk<'a> {
+        let board = &game.board;
+        BoardWalk {
+            board: board,
+            tops: COLORS.iter().map(|&color| (color, 0)).collect(),
+            discard: Discard::new(),
+            cards_to_draw: board.total_cards - board.num_players * board.hand_size,
         }
-    })
+    }
+
+    // whether `card` was still needed, and could still have been played
+    fn still_playable(&self, card: &Card) -> bool {
+        card.value > self.tops[&card.color] && card.value <= self.discard.highest_attainable(card.color)
+    }
+
+    fn pace(&self) -> i32 {
+        let score = self.tops.values().sum();
+        let max_attainable_score = COLORS.iter().map(|color| {
+            if self.tops[color] == FINAL_VALUE { FINAL_VALUE } else { self.discard.highest_attainable(*color) }
+        }).sum();
+        pace(score, self.cards_to_draw, self.board.num_players, max_attainable_score)
+    }
+
+    // moves the board on past `turn`, returning whether the player drew a card
+    fn apply(&mut self, turn: &TurnRecord) -> bool {
+        match turn.result {
+            TurnResult::Hint(_) => {}
+            TurnResult::Play(ref card, true) => {
+                self.tops.insert(card.color, card.value);
+            }
+            TurnResult::Play(ref card, false) => {
+                match self.board.misplay_rule {
+                    MisplayRule::Discard => self.discard.place(card.clone()),
+                    MisplayRule::ReturnToDeckBottom => self.cards_to_draw += 1,
+                }
+            }
+            TurnResult::Discard(ref card) => {
+                self.discard.place(card.clone());
+            }
+        }
+        match turn.choice {
+            TurnChoice::Play(_) | TurnChoice::Discard(_) if self.cards_to_draw > 0 => {
+                self.cards_to_draw -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// Counts of what kinds of moves were made, summed over games, to see how efficiently they were played
+#[derive(Debug,Clone,Default)]
+pub struct PlayStats {
+    pub hints: u32,
+    pub plays: u32,
+    pub bombs: u32,
+    pub discards: u32,
+    // discards of the last remaining copy of a card that could still have been played
+    pub critical_discards: u32,
+    // hints that told the hinted player nothing earlier hints hadn't.
+    // These aren't stalls in the sense the strategies mean (hinting so as not to discard and draw):
+    // a stall may well tell something new, and a redundant hint needn't be given to stall
+    pub redundant_hints: u32,
+}
+impl PlayStats {
+    pub fn new() -> PlayStats {
+        Default::default()
+    }
+
+    // analyzes a finished game's history
+    pub fn from_game(game: &GameState) -> PlayStats {
+        let board = &game.board;
+        let mut stats = PlayStats::new();
+        let mut walk = BoardWalk::new(game);
+        // what earlier hints revealed about each hand
+        let mut knowledge = board.get_players().map(|_| {
+            HandInfo::<CardPossibilityTable>::new(board.hand_size)
+        }).collect::<Vec<_>>();
+
+        for turn in game.turn_history.iter() {
+            match turn.result {
+                TurnResult::Hint(ref matches) => {
+                    stats.hints += 1;
+                    if let TurnChoice::Hint(ref hint) = turn.choice {
+                        let hand_info = &mut knowledge[hint.player as usize];
+                        let before = hand_info.clone();
+                        hand_info.update_for_hint(&hint.hinted, matches);
+                        if *hand_info == before {
+                            stats.redundant_hints += 1;
+                        }
+                    }
+                }
+                TurnResult::Play(_, true) => stats.plays += 1,
+                TurnResult::Play(_, false) => stats.bombs += 1,
+                TurnResult::Discard(ref card) => {
+                    stats.discards += 1;
+                    if walk.still_playable(card) && walk.discard.remaining(card) == 1 {
+                        stats.critical_discards += 1;
+                    }
+                }
+            }
+            let drew = walk.apply(turn);
+            if let TurnChoice::Play(index) | TurnChoice::Discard(index) = turn.choice {
+                let hand_info = &mut knowledge[turn.player as usize];
+                hand_info.remove(index);
+                if drew {
+                    hand_info.push(CardPossibilityTable::new());
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn merge(&mut self, other: &PlayStats) {
+        self.hints += other.hints;
+        self.plays += other.plays;
+        self.bombs += other.bombs;
+        self.discards += other.discards;
+        self.critical_discards += other.critical_discards;
+        self.redundant_hints += other.redundant_hints;
+    }
+
+    // None if no hints were given
+    pub fn plays_per_hint(&self) -> Option<f32> {
+        if self.hints == 0 {
+            None
+        } else {
+            Some(self.plays as f32 / self.hints as f32)
+        }
+    }
+}
+
+// Discards, by the value of the card discarded (index 0 for 1s), split by how risky they were
+// given where the other copies of the card really were, summed over games
+#[derive(Debug,Clone,Default)]
+pub struct DiscardRiskStats {
+    // the card could no longer be played: already played, or a card it needs was lost
+    pub dead: [u32; 5],
+    // another copy was still in the deck
+    pub copy_in_deck: [u32; 5],
+    // no other copy was in the deck, but one was in someone's hand
+    pub copy_in_hand: [u32; 5],
+    // it was the last copy, so the card could never be played
+    pub last_copy: [u32; 5],
+}
+impl DiscardRiskStats {
+    pub fn new() -> DiscardRiskStats {
+        Default::default()
+    }
+
+    // analyzes a finished game's history, following which cards were still in the deck
+    pub fn from_game(game: &GameState) -> DiscardRiskStats {
+        let board = &game.board;
+        let mut stats = DiscardRiskStats::new();
+
+        // every card that was ever dealt or drawn, by id
+        let mut cards = FnvHashMap::default();
+        for player in board.get_players() {
+            for (&card_id, card) in board.card_ids(&player).iter().zip(game.hands[&player].iter()) {
+                cards.insert(card_id, card.clone());
+            }
+        }
+        for turn in game.turn_history.iter() {
+            if let TurnResult::Play(ref card, _) | TurnResult::Discard(ref card) = turn.result {
+                cards.insert(turn.card_id.expect("Played or discarded card without an id"), card.clone());
+            }
+        }
+
+        // counts the cards out of the deck, so `remaining` is the copies still in it
+        let mut drawn = CardCounts::new();
+        let mut next_card_id = board.num_players * board.hand_size;
+        for card_id in 0..next_card_id {
+            drawn.increment(&cards[&card_id]);
+        }
+        let mut walk = BoardWalk::new(game);
+
+        for turn in game.turn_history.iter() {
+            match turn.result {
+                TurnResult::Play(ref card, false) if board.misplay_rule == MisplayRule::ReturnToDeckBottom => {
+                    drawn.decrement(card);
+                }
+                TurnResult::Discard(ref card) => {
+                    let i = card.value as usize - 1;
+                    if !walk.still_playable(card) {
+                        stats.dead[i] += 1;
+                    } else if drawn.remaining(card) > 0 {
+                        stats.copy_in_deck[i] += 1;
+                    } else if walk.discard.remaining(card) > 1 {
+                        stats.copy_in_hand[i] += 1;
+                    } else {
+                        stats.last_copy[i] += 1;
+                    }
+                }
+                _ => {}
+            }
+            if walk.apply(turn) {
+                drawn.increment(&cards[&next_card_id]);
+                next_card_id += 1;
+            }
+        }
+        stats
+    }
+
+    pub fn merge(&mut self, other: &DiscardRiskStats) {
+        for i in 0..VALUES.len() {
+            self.dead[i] += other.dead[i];
+            self.copy_in_deck[i] += other.copy_in_deck[i];
+            self.copy_in_hand[i] += other.copy_in_hand[i];
+            self.last_copy[i] += other.last_copy[i];
+        }
+    }
+
+    pub fn total(&self, counts: &[u32; 5]) -> u32 {
+        counts.iter().sum()
+    }
+
+    // of the discards of cards that were still needed, the fraction that were the last copy,
+    // or 0 if no such card was discarded
+    pub fn last_copy_rate(&self) -> f32 {
+        let needed = self.total(&self.copy_in_deck) + self.total(&self.copy_in_hand) + self.total(&self.last_copy);
+        if needed == 0 {
+            return 0.0;
+        }
+        self.total(&self.last_copy) as f32 / needed as f32
+    }
+}
+
+// How long games lasted, and how their pace (see BoardState::pace) went, summed over games
+#[derive(Debug)]
+pub struct TempoStats {
+    // how many turns each game lasted
+    pub lengths: Histogram,
+    // how many cards were left in the deck when each game ended
+    pub draws_remaining: Histogram,
+    // the lowest pace each game reached, offset by PACE_OFFSET since histograms can't hold negatives
+    pub min_paces: Histogram,
+    // pace_by_turn[i] is the total pace after turn i (with turn 0 being the deal),
+    // and the number of games that lasted that long
+    pub pace_by_turn: Vec<(i64, u32)>,
+}
+// added to each pace in TempoStats::min_paces.  Pace never drops by more than the number of
+// cards discarded or misplayed, so it can't go below -PACE_OFFSET
+pub const PACE_OFFSET: i32 = 100;
+impl TempoStats {
+    pub fn new() -> TempoStats {
+        TempoStats {
+            lengths: Histogram::new(),
+            draws_remaining: Histogram::new(),
+            min_paces: Histogram::new(),
+            pace_by_turn: Vec::new(),
+        }
+    }
+
+    // analyzes a finished game's history
+    pub fn from_game(game: &GameState) -> TempoStats {
+        let board = &game.board;
+        let mut stats = TempoStats::new();
+        stats.lengths.insert(game.turn_history.len() as u32);
+        stats.draws_remaining.insert(board.deck_size);
+
+        let mut walk = BoardWalk::new(game);
+        let mut paces = Vec::with_capacity(game.turn_history.len() + 1);
+        paces.push(walk.pace());
+        for turn in game.turn_history.iter() {
+            walk.apply(turn);
+            paces.push(walk.pace());
+        }
+        stats.min_paces.insert((paces.iter().cloned().min().unwrap() + PACE_OFFSET) as u32);
+        stats.pace_by_turn = paces.into_iter().map(|pace| (pace as i64, 1)).collect();
+        stats
+    }
+
+    pub fn merge(&mut self, other: TempoStats) {
+        self.lengths.merge(other.lengths);
+        self.draws_remaining.merge(other.draws_remaining);
+        self.min_paces.merge(other.min_paces);
+        for (turn, (total, count)) in other.pace_by_turn.into_iter().enumerate() {
+            if turn < self.pace_by_turn.len() {
+                self.pace_by_turn[turn].0 += total;
+                self.pace_by_turn[turn].1 += count;
+            } else {
+                self.pace_by_turn.push((total, count));
+            }
+        }
+    }
+
+    // the average pace after a turn, of the games that lasted that long
+    pub fn average_pace(&self, turn: usize) -> Option<f32> {
+        self.pace_by_turn.get(turn).map(|&(total, count)| total as f32 / count as f32)
+    }
+}
+
+// When each firework reached each value, and how the score grew, summed over games: whether
+// games are lost to slow starts or to endgame collapses
+#[derive(Debug)]
+pub struct StackCurves {
+    // reached[i][value - 1] is the total turn on which the firework of COLORS[i] reached `value`,
+    // and the number of games in which it did
+    pub reached: Vec<[(u64, u32); 5]>,
+    // score_by_turn[i] is the total score after turn i (with turn 0 being the deal),
+    // and the number of games that lasted that long
+    pub score_by_turn: Vec<(u64, u32)>,
+}
+impl StackCurves {
+    pub fn new() -> StackCurves {
+        StackCurves {
+            reached: COLORS.iter().map(|_| [(0, 0); 5]).collect(),
+            score_by_turn: Vec::new(),
+        }
+    }
+
+    // analyzes a finished game's history
+    pub fn from_game(game: &GameState) -> StackCurves {
+        let mut curves = StackCurves::new();
+        let mut score = 0;
+        curves.score_by_turn.push((0, 1));
+        for (i, turn) in game.turn_history.iter().enumerate() {
+            if let TurnResult::Play(ref card, true) = turn.result {
+                let color = COLORS.iter().position(|&color| color == card.color).unwrap();
+                curves.reached[color][card.value as usize - 1] = (i as u64 + 1, 1);
+                score += 1;
+            }
+            curves.score_by_turn.push((score, 1));
+        }
+        curves
+    }
+
+    pub fn merge(&mut self, other: StackCurves) {
+        for (mine, theirs) in self.reached.iter_mut().zip(other.reached.iter()) {
+            for (mine, theirs) in mine.iter_mut().zip(theirs.iter()) {
+                mine.0 += theirs.0;
+                mine.1 += theirs.1;
+            }
+        }
+        for (turn, (total, count)) in other.score_by_turn.into_iter().enumerate() {
+            if turn < self.score_by_turn.len() {
+                self.score_by_turn[turn].0 += total;
+                self.score_by_turn[turn].1 += count;
+            } else {
+                self.score_by_turn.push((total, count));
+            }
+        }
+    }
+
+    // the average turn on which `color`'s firework reached `value`, in the games where it did
+    pub fn average_turn_reached(&self, color: Color, value: Value) -> Option<f32> {
+        let i = COLORS.iter().position(|&other| other == color).unwrap();
+        match self.reached[i][value as usize - 1] {
+            (_, 0) => None,
+            (total, count) => Some(total as f32 / count as f32),
+        }
+    }
+
+    pub fn average_score(&self, turn: usize) -> Option<f32> {
+        self.score_by_turn.get(turn).map(|&(total, count)| total as f32 / count as f32)
+    }
+}
+
+// What hints said and who they went to, summed over games, to compare how strategies hint
+// with each other and with people
+#[derive(Debug)]
+pub struct HintStats {
+    pub color_hints: u32,
+    pub value_hints: u32,
+    // by_color[i] is the number of hints of COLORS[i]
+    pub by_color: Vec<u32>,
+    // by_value[value - 1] is the number of hints of `value`
+    pub by_value: Vec<u32>,
+    // by_distance[d - 1] is the number of hints to the player d seats to the hinter's left
+    pub by_distance: Vec<u32>,
+    // by_touched[n] is the number of hints that touched n cards
+    pub by_touched: Vec<u32>,
+}
+impl HintStats {
+    pub fn new() -> HintStats {
+        HintStats {
+            color_hints: 0,
+            value_hints: 0,
+            by_color: vec![0; COLORS.len()],
+            by_value: vec![0; VALUES.len()],
+            by_distance: Vec::new(),
+            by_touched: Vec::new(),
+        }
+    }
+
+    // analyzes a finished game's history
+    pub fn from_game(game: &GameState) -> HintStats {
+        let num_players = game.board.num_players;
+        let mut stats = HintStats::new();
+        for turn in game.turn_history.iter() {
+            let (hint, matches) = match (&turn.choice, &turn.result) {
+                (&TurnChoice::Hint(ref hint), &TurnResult::Hint(ref matches)) => (hint, matches),
+                _ => continue,
+            };
+            match hint.hinted {
+                Hinted::Color(color) => {
+                    stats.color_hints += 1;
+                    stats.by_color[COLORS.iter().position(|&other| other == color).unwrap()] += 1;
+                }
+                Hinted::Value(value) => {
+                    stats.value_hints += 1;
+                    stats.by_value[value as usize - 1] += 1;
+                }
+            }
+            let distance = (hint.player + num_players - turn.player) % num_players;
+            count_at(&mut stats.by_distance, distance as usize - 1, 1);
+            count_at(&mut stats.by_touched, matches.iter().filter(|&&matched| matched).count(), 1);
+        }
+        stats
+    }
+
+    pub fn merge(&mut self, other: &HintStats) {
+        self.color_hints += other.color_hints;
+        self.value_hints += other.value_hints;
+        merge_counts(&mut self.by_color, &other.by_color);
+        merge_counts(&mut self.by_value, &other.by_value);
+        merge_counts(&mut self.by_distance, &other.by_distance);
+        merge_counts(&mut self.by_touched, &other.by_touched);
+    }
+
+    pub fn total(&self) -> u32 {
+        self.color_hints + self.value_hints
+    }
+}
+
+// adds `count` to counts[i], growing `counts` as needed
+fn count_at(counts: &mut Vec<u32>, i: usize, count: u32) {
+    if counts.len() <= i {
+        counts.resize(i + 1, 0);
+    }
+    counts[i] += count;
+}
+
+fn merge_counts(counts: &mut Vec<u32>, other: &[u32]) {
+    for (i, &count) in other.iter().enumerate() {
+        count_at(counts, i, count);
+    }
+}
+
+// Which analyses of each game's history SimResult::add_game makes.  Each goes over the whole
+// game again, so a run only makes the ones it will show; the rest stay empty
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct Analyses {
+    pub play_stats: bool,
+    pub discard_risk: bool,
+    pub tempo: bool,
+    pub stack_curves: bool,
+    pub hint_stats: bool,
+}
+impl Analyses {
+    pub fn all() -> Analyses {
+        Analyses {
+            play_stats: true,
+            discard_risk: true,
+            tempo: true,
+            stack_curves: true,
+            hint_stats: true,
+        }
+    }
+
+    pub fn none() -> Analyses {
+        Analyses {
+            play_stats: false,
+            discard_risk: false,
+            tempo: false,
+            stack_curves: false,
+            hint_stats: false,
+        }
+    }
 }
 
 pub struct SimResult {
     pub scores: Histogram,
     pub lives: Histogram,
     pub non_perfect_seed: Option<u32>,
+    // number of seeds skipped because their deal was rejected by a filter
+    pub filtered_seeds: u32,
+    // the score of each simulated seed, sorted by seed once simulate returns.
+    // merge appends, so merging in the results of later seeds keeps them sorted
+    pub seed_scores: Vec<(u32, Score)>,
+    // how many games ended for each reason
+    pub end_causes: FnvHashMap<EndCause, u32>,
+    // for each color, how many games ended without its firework complete
+    pub incomplete_colors: FnvHashMap<Color, u32>,
+    // life_loss_turns[i] is a histogram of the turn on which the (i+1)-th life was lost
+    pub life_loss_turns: Vec<Histogram>,
+    pub decide_times: DecideTimes,
+    // a lower bound on the turns skipped by ending games once their score settled
+    pub turns_saved: u64,
+    // the same, for the games a player conceded, perfect or not
+    pub turns_conceded: u64,
+    pub play_stats: PlayStats,
+    // of BoardState::discard_loss, the points each game gave up by discarding or misplaying
+    pub discard_loss: Histogram,
+    pub discard_risk: DiscardRiskStats,
+    pub tempo: TempoStats,
+    pub stack_curves: StackCurves,
+    pub hint_stats: HintStats,
+    // the seeds of the games forfeited because of an illegal move, sorted
+    pub illegal_move_seeds: Vec<u32>,
+    // of GameState::strategy_metrics, the totals over all games
+    pub strategy_metrics: FnvHashMap<String, f64>,
+    // which of the stats above add_game collects
+    pub analyses: Analyses,
 }
 
 impl SimResult {
+    pub fn new() -> SimResult {
+        SimResult::with_analyses(Analyses::all())
+    }
+
+    pub fn with_analyses(analyses: Analyses) -> SimResult {
+        SimResult {
+            scores: Histogram::new(),
+            lives: Histogram::new(),
+            non_perfect_seed: None,
+            filtered_seeds: 0,
+            seed_scores: Vec::new(),
+            end_causes: FnvHashMap::default(),
+            incomplete_colors: FnvHashMap::default(),
+            life_loss_turns: Vec::new(),
+            decide_times: DecideTimes::new(),
+            turns_saved: 0,
+            turns_conceded: 0,
+            play_stats: PlayStats::new(),
+            discard_loss: Histogram::new(),
+            discard_risk: DiscardRiskStats::new(),
+            tempo: TempoStats::new(),
+            stack_curves: StackCurves::new(),
+            hint_stats: HintStats::new(),
+            illegal_move_seeds: Vec::new(),
+            strategy_metrics: FnvHashMap::default(),
+            analyses: analyses,
+        }
+    }
+
+    pub fn add_game(&mut self, seed: u32, game: &GameState) {
+        let score = game.score();
+        self.lives.insert(game.board.lives_remaining);
+        self.scores.insert(score);
+        if score != PERFECT_SCORE && self.non_perfect_seed.map_or(true, |other| seed < other) {
+            self.non_perfect_seed = Some(seed);
+        }
+        self.seed_scores.push((seed, score));
+        self.discard_loss.insert(game.board.discard_loss);
+        let end_cause = game.board.end_cause().expect("Recorded a game that isn't over");
+        *self.end_causes.entry(end_cause).or_insert(0) += 1;
+        if end_cause == EndCause::Forfeited {
+            self.illegal_move_seeds.push(seed);
+        }
+        if self.analyses.play_stats {
+            self.play_stats.merge(&PlayStats::from_game(game));
+        }
+        if self.analyses.discard_risk {
+            self.discard_risk.merge(&DiscardRiskStats::from_game(game));
+        }
+        if self.analyses.tempo {
+            self.tempo.merge(TempoStats::from_game(game));
+        }
+        if self.analyses.stack_curves {
+            self.stack_curves.merge(StackCurves::from_game(game));
+        }
+        if self.analyses.hint_stats {
+            self.hint_stats.merge(&HintStats::from_game(game));
+        }
+        for (name, &value) in &game.strategy_metrics {
+            *self.strategy_metrics.entry(name.clone()).or_insert(0.0) += value;
+        }
+        if end_cause == EndCause::Settled || (end_cause == EndCause::PerfectScore && game.board.settled) {
+            self.turns_saved += game.board.min_turns_remaining() as u64;
+        }
+        if game.board.conceded {
+            self.turns_conceded += game.board.min_turns_remaining() as u64;
+        }
+        for &color in COLORS.iter() {
+            if !game.board.get_firework(color).complete() {
+                *self.incomplete_colors.entry(color).or_insert(0) += 1;
+            }
+        }
+        let bomb_turns = game.turn_history.iter().enumerate().filter(|&(_, turn)| {
+            match turn.result {
+                TurnResult::Play(_, playable) => !playable,
+                _ => false,
+            }
+        }).map(|(i, _)| i as u32 + 1);
+        for (lost, turn) in bomb_turns.enumerate() {
+            while self.life_loss_turns.len() <= lost {
+                self.life_loss_turns.push(Histogram::new());
+            }
+            self.life_loss_turns[lost].insert(turn);
+        }
+    }
+
+    pub fn merge(&mut self, other: SimResult) {
+        self.scores.merge(other.scores);
+        self.lives.merge(other.lives);
+        self.non_perfect_seed = match (self.non_perfect_seed, other.non_perfect_seed) {
+            (Some(a), Some(b)) => Some(cmp::min(a, b)),
+            (a, b) => a.or(b),
+        };
+        self.filtered_seeds += other.filtered_seeds;
+        self.seed_scores.extend(other.seed_scores);
+        for (end_cause, count) in other.end_causes {
+            *self.end_causes.entry(end_cause).or_insert(0) += count;
+        }
+        for (color, count) in other.incomplete_colors {
+            *self.incomplete_colors.entry(color).or_insert(0) += count;
+        }
+        for (lost, hist) in other.life_loss_turns.into_iter().enumerate() {
+            if lost < self.life_loss_turns.len() {
+                self.life_loss_turns[lost].merge(hist);
+            } else {
+                self.life_loss_turns.push(hist);
+            }
+        }
+        self.decide_times.merge(other.decide_times);
+        self.turns_saved += other.turns_saved;
+        self.turns_conceded += other.turns_conceded;
+        self.play_stats.merge(&other.play_stats);
+        self.discard_loss.merge(other.discard_loss);
+        self.discard_risk.merge(&other.discard_risk);
+        self.tempo.merge(other.tempo);
+        self.stack_curves.merge(other.stack_curves);
+        self.hint_stats.merge(&other.hint_stats);
+        self.illegal_move_seeds.extend(other.illegal_move_seeds);
+        self.illegal_move_seeds.sort();
+        for (name, value) in other.strategy_metrics {
+            *self.strategy_metrics.entry(name).or_insert(0.0) += value;
+        }
+    }
+
+    // the average per game of what the strategies measured, sorted by name
+    pub fn average_strategy_metrics(&self) -> Vec<(&str, f64)> {
+        let mut metrics = self.strategy_metrics.iter().map(|(name, &total)| {
+            (&name[..], total / self.scores.total_count as f64)
+        }).collect::<Vec<_>>();
+        metrics.sort_by(|a, b| a.0.cmp(b.0));
+        metrics
+    }
+
+    // How much of the score each game lost by discarding (or misplaying) the last copy of a
+    // card that was still needed, as opposed to running out of time to play what was left
+    pub fn discard_loss_info(&self) {
+        info!("Points lost to discards and misplays (count, percent, cumulative percent):\n{}", self.discard_loss);
+        let missing = PERFECT_SCORE as f32 - self.scores.average();
+        let lost = self.discard_loss.average();
+        info!("Of the {:.4} points missing per game, {:.4} ({:.1}%) were lost to discards and misplays, \
+               in {:.2}% of games",
+              missing, lost, if missing > 0.0 { 100.0 * lost / missing } else { 0.0 },
+              100.0 * (1.0 - self.discard_loss.percentage_with(&0)));
+    }
+
+    // How risky the discards were, by value: whether another copy of the card was still in the
+    // deck, or only in a hand, or whether it was the last one
+    pub fn discard_risk_info(&self) {
+        let risk = &self.discard_risk;
+        let mut table = String::from("value      dead   in deck   in hand last copy\n");
+        for (i, value) in VALUES.iter().enumerate() {
+            table.push_str(&format!("{:5} {:9} {:9} {:9} {:9}\n", value,
+                                    risk.dead[i], risk.copy_in_deck[i], risk.copy_in_hand[i], risk.last_copy[i]));
+        }
+        table.push_str(&format!("total {:9} {:9} {:9} {:9}",
+                                risk.total(&risk.dead), risk.total(&risk.copy_in_deck),
+                                risk.total(&risk.copy_in_hand), risk.total(&risk.last_copy)));
+        info!("Discards by where the other copies were:\n{}", table);
+        info!("{:.2}% of discards of cards still needed were the last copy", 100.0 * risk.last_copy_rate());
+    }
+
+    // How long games lasted and how close they came to running out of time, with the average pace
+    // every `every` turns
+    pub fn tempo_info(&self, every: usize) {
+        let tempo = &self.tempo;
+        info!("Game lengths in turns (count, percent, cumulative percent):\n{}", tempo.lengths);
+        info!("Average game length: {:.2} turns", tempo.lengths.average());
+        info!("Cards left in the deck at the end (count, percent, cumulative percent):\n{}", tempo.draws_remaining);
+        let negative = tempo.min_paces.hist.iter().filter(|&(&pace, _)| (pace as i32) < PACE_OFFSET)
+            .map(|(_, &count)| count).sum::<u32>();
+        info!("Average lowest pace: {:.2}, going negative in {:.2}% of games",
+              tempo.min_paces.average() - PACE_OFFSET as f32,
+              100.0 * negative as f32 / tempo.min_paces.total_count as f32);
+        info!("Average pace after each turn, of the games still going:");
+        for turn in (0..tempo.pace_by_turn.len()).filter(|turn| turn % every == 0) {
+            info!("  turn {:3}: {:6.2} ({} games)", turn, tempo.average_pace(turn).unwrap(),
+                  tempo.pace_by_turn[turn].1);
+        }
+    }
+
+    // On average, when each firework reached each value, and the score every `every` turns
+    pub fn stack_curves_info(&self, every: usize) {
+        let curves = &self.stack_curves;
+        let games = self.scores.total_count as f32;
+        let mut table = String::from("        ");
+        for value in VALUES.iter() {
+            table.push_str(&format!("{:>15}", value));
+        }
+        for (i, &color) in COLORS.iter().enumerate() {
+            table.push_str(&format!("\n  {}     ", color));
+            for &value in VALUES.iter() {
+                let reached = curves.reached[i][value as usize - 1].1 as f32;
+                match curves.average_turn_reached(color, value) {
+                    Some(turn) => table.push_str(&format!("{:>8.1} ({:>3.0}%)", turn, 100.0 * reached / games)),
+                    None => table.push_str(&format!("{:>15}", "-")),
+                }
+            }
+        }
+        info!("Average turn each firework reached each value (percent of games reaching it):\n{}", table);
+        info!("Average score after each turn, of the games still going:");
+        for turn in (0..curves.score_by_turn.len()).filter(|turn| turn % every == 0) {
+            info!("  turn {:3}: {:5.2} ({} games)", turn, curves.average_score(turn).unwrap(),
+                  curves.score_by_turn[turn].1);
+        }
+    }
+
+    // What hints said, who they went to, and how many cards they touched, as percentages of all hints
+    pub fn hint_stats_info(&self) {
+        let stats = &self.hint_stats;
+        let total = stats.total();
+        if total == 0 {
+            info!("Hints per game: 0");
+            return;
+        }
+        let percent = |count: u32| 100.0 * count as f32 / total as f32;
+        let row = |labels: Vec<String>, counts: &[u32]| {
+            labels.iter().zip(counts.iter()).map(|(label, &count)| {
+                format!("\n  {:>8}: {:6.2}% ({})", label, percent(count), count)
+            }).collect::<String>()
+        };
+        info!("Hints per game: {:.2}, {:.2}% color and {:.2}% value",
+              total as f32 / self.scores.total_count as f32, percent(stats.color_hints), percent(stats.value_hints));
+        info!("Hints of each color:{}", row(COLORS.iter().map(|color| color.to_string()).collect(), &stats.by_color));
+        info!("Hints of each value:{}", row(VALUES.iter().map(|value| value.to_string()).collect(), &stats.by_value));
+        info!("Hints by how many seats to the hinter's left they went:{}",
+              row((1..stats.by_distance.len() + 1).map(|d| d.to_string()).collect(), &stats.by_distance));
+        info!("Hints by how many cards they touched:{}",
+              row((0..stats.by_touched.len()).map(|n| n.to_string()).collect(), &stats.by_touched));
+    }
+
+    // fraction of games in which fewer than `lives_lost` lives had been lost by the end of `turn`
+    pub fn survival_probability(&self, lives_lost: u32, turn: u32) -> f32 {
+        assert!(lives_lost > 0);
+        let lost_by_turn = self.life_loss_turns.get(lives_lost as usize - 1).map_or(0, |hist| {
+            hist.hist.iter().filter(|&(&t, _)| t <= turn).map(|(_, &count)| count).sum::<u32>()
+        });
+        1.0 - lost_by_turn as f32 / self.scores.total_count as f32
+    }
+
     pub fn percent_perfect(&self) -> f32 {
         self.scores.percentage_with(&PERFECT_SCORE) * 100.0
     }
@@ -221,20 +1519,186 @@ impl SimResult {
         self.scores.stdev_of_average()
     }
 
+    // two-sided p-value for the average score being different from `baseline` (z-test)
+    pub fn score_p_value(&self, baseline: f64) -> f64 {
+        z_test_p_value(self.average_score() as f64 - baseline, self.score_stderr() as f64)
+    }
+
     pub fn average_lives(&self) -> f32 {
         self.lives.average()
     }
 
     pub fn info(&self) {
-        info!("Score histogram:\n{}", self.scores);
+        if self.filtered_seeds > 0 {
+            info!("Skipped {} seeds rejected by deal filters", self.filtered_seeds);
+        }
+        info!("Score histogram (count, percent, cumulative percent):\n{}", self.scores);
+        if let (Some(p10), Some(median), Some(p90)) = (
+            self.scores.quantile(0.1), self.scores.median(), self.scores.quantile(0.9)) {
+            info!("Score quantiles: 10% {}, median {}, 90% {}   {}", p10, median, p90, self.scores.sparkline());
+        }
 
         // info!("Seeds with non-perfect score: {:?}", non_perfect_seeds);
         if let Some(seed) = self.non_perfect_seed {
             info!("Example seed with non-perfect score: {}", seed);
         }
 
+        let mut end_causes = self.end_causes.iter().collect::<Vec<_>>();
+        end_causes.sort();
+        let end_causes = end_causes.into_iter().map(|(end_cause, &count)| {
+            format!("\n{}: {} ({:.2}%)", end_cause, count, 100.0 * count as f32 / self.scores.total_count as f32)
+        }).collect::<String>();
+        info!("Game end causes:\n{}", end_causes);
+        if !self.illegal_move_seeds.is_empty() {
+            warn!("Seeds forfeited by an illegal move: {:?}", self.illegal_move_seeds);
+        }
+        let incomplete_colors = COLORS.iter().map(|color| {
+            format!("\n{}: {}", color, self.incomplete_colors.get(color).unwrap_or(&0))
+        }).collect::<String>();
+        info!("Games ending with each firework incomplete:\n{}", incomplete_colors);
+        let games = self.scores.total_count as f32;
+        let stats = &self.play_stats;
+        let per_hint = stats.plays_per_hint().map_or(String::new(), |per_hint| {
+            format!(" ({:.3} per hint)", per_hint)
+        });
+        info!("Per game: {:.2} hints ({:.2} redundant), {:.2} plays{}, {:.2} bombs, \
+               {:.2} discards ({:.3} critical)",
+              stats.hints as f32 / games, stats.redundant_hints as f32 / games,
+              stats.plays as f32 / games, per_hint, stats.bombs as f32 / games,
+              stats.discards as f32 / games, stats.critical_discards as f32 / games);
+        if self.turns_saved > 0 {
+            info!("Ending games once their score settled saved at least {} turns ({:.2} per game)",
+                  self.turns_saved, self.turns_saved as f64 / self.scores.total_count as f64);
+        }
+        if self.turns_conceded > 0 {
+            info!("Conceding games skipped at least {} turns ({:.2} per game)",
+                  self.turns_conceded, self.turns_conceded as f64 / self.scores.total_count as f64);
+        }
+        let metrics = self.average_strategy_metrics();
+        if !metrics.is_empty() {
+            let metrics = metrics.into_iter().map(|(name, average)| {
+                format!("\n{}: {:.2}", name, average)
+            }).collect::<String>();
+            info!("Strategy metrics per game:{}", metrics);
+        }
+        if let Some(first_losses) = self.life_loss_turns.first() {
+            info!("Average turn of first life lost (in {} games losing a life): {:.2}",
+                  first_losses.total_count, first_losses.average());
+            let last_turn = self.life_loss_turns.iter().flat_map(|hist| hist.hist.keys()).max().cloned().unwrap();
+            let max_lost = self.life_loss_turns.len() as u32;
+            let header = (1..max_lost + 1).map(|lost| format!("  {:>9}", format!("< {} lost", lost))).collect::<String>();
+            let rows = (1..).map(|i| i * 10).take_while(|&turn| turn < last_turn + 10).map(|turn| {
+                let cells = (1..max_lost + 1).map(|lost| {
+                    format!("  {:8.2}%", 100.0 * self.survival_probability(lost, turn))
+                }).collect::<String>();
+                format!("\nturn {:3}:{}", turn, cells)
+            }).collect::<String>();
+            info!("Fraction of games with fewer than N lives lost by each turn:\n\n         {}{}", header, rows);
+        }
+
+        if let (Some(p50), Some(p90), Some(p99), Some(max)) = (
+            self.decide_times.percentile(0.5), self.decide_times.percentile(0.9),
+            self.decide_times.percentile(0.99), self.decide_times.percentile(1.0)) {
+            info!("Time per decision (microseconds): median {}, 90% {}, 99% {}, max {}", p50, p90, p99, max);
+            let by_turn = self.decide_times.average_by_turn().iter().enumerate().map(|(i, micros)| {
+                format!("\nturn {}: {:.1}", i + 1, micros)
+            }).collect::<String>();
+            debug!("Average time per decision by turn (microseconds):{}", by_turn);
+        }
+
         info!("Percentage perfect: {:?}%", self.percent_perfect());
         info!("Average score: {:?}", self.average_score());
         info!("Average lives: {:?}", self.average_lives());
     }
 }
+
+// standard normal cumulative distribution function
+fn normal_cdf(x: f64) -> f64 {
+    // Abramowitz and Stegun formula 7.1.26 for erf, accurate to about 1e-7
+    let z = x.abs() / 2f64.sqrt();
+    let t = 1.0 / (1.0 + 0.3275911 * z);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-z * z).exp();
+    if x >= 0.0 { 0.5 * (1.0 + erf) } else { 0.5 * (1.0 - erf) }
+}
+
+// two-sided p-value for a mean difference `diff` with standard error `stderr` being nonzero
+fn z_test_p_value(diff: f64, stderr: f64) -> f64 {
+    if stderr > 0.0 {
+        2.0 * (1.0 - normal_cdf((diff / stderr).abs()))
+    } else if diff == 0.0 { 1.0 } else { 0.0 }
+}
+
+// mean and standard error of the mean of a sample.
+// With fewer than two values there's no spread to measure, and the error is 0
+fn mean_and_stderr(xs: &[f64]) -> (f64, f64) {
+    if xs.len() < 2 {
+        return (xs.first().cloned().unwrap_or(0.0), 0.0);
+    }
+    let n = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+    let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, (var / n).sqrt())
+}
+
+// Paired statistics for two simulations on the same seeds
+pub struct Comparison {
+    pub n_games: u32,
+    // average of (score of second - score of first)
+    pub mean_score_diff: f64,
+    pub score_diff_stderr: f64,
+    // two-sided p-value for the mean score difference being nonzero (paired z-test)
+    pub score_p_value: f64,
+    // difference in win rate, as a percentage
+    pub win_rate_diff: f64,
+    pub win_rate_diff_stderr: f64,
+    // (seed, first score, second score), largest differences first
+    pub divergent_seeds: Vec<(u32, Score, Score)>,
+}
+impl Comparison {
+    pub fn new(first: &SimResult, second: &SimResult) -> Comparison {
+        assert!(first.seed_scores.iter().map(|&(seed, _)| seed).eq(
+                second.seed_scores.iter().map(|&(seed, _)| seed)),
+                "Compared simulations must be run on the same seeds");
+        let pairs = first.seed_scores.iter().zip(second.seed_scores.iter()).map(|(&(seed, a), &(_, b))| {
+            (seed, a, b)
+        }).collect::<Vec<_>>();
+
+        let score_diffs = pairs.iter().map(|&(_, a, b)| b as f64 - a as f64).collect::<Vec<_>>();
+        let (mean_score_diff, score_diff_stderr) = mean_and_stderr(&score_diffs);
+        // one game is no evidence either way
+        let score_p_value = if score_diffs.len() < 2 {
+            1.0
+        } else {
+            z_test_p_value(mean_score_diff, score_diff_stderr)
+        };
+
+        let win = |score: Score| if score == PERFECT_SCORE { 100.0 } else { 0.0 };
+        let win_diffs = pairs.iter().map(|&(_, a, b)| win(b) - win(a)).collect::<Vec<_>>();
+        let (win_rate_diff, win_rate_diff_stderr) = mean_and_stderr(&win_diffs);
+
+        let mut divergent_seeds = pairs.into_iter().filter(|&(_, a, b)| a != b).collect::<Vec<_>>();
+        divergent_seeds.sort_by_key(|&(seed, a, b)| (-(a as i32 - b as i32).abs(), seed));
+        divergent_seeds.truncate(10);
+
+        Comparison {
+            n_games: score_diffs.len() as u32,
+            mean_score_diff: mean_score_diff,
+            score_diff_stderr: score_diff_stderr,
+            score_p_value: score_p_value,
+            win_rate_diff: win_rate_diff,
+            win_rate_diff_stderr: win_rate_diff_stderr,
+            divergent_seeds: divergent_seeds,
+        }
+    }
+
+    pub fn info(&self, first_name: &str, second_name: &str) {
+        info!("Paired comparison of {} against {} over {} games:", second_name, first_name, self.n_games);
+        info!("Score difference: {:+.4} ± {:.4} (p = {:.4})",
+              self.mean_score_diff, self.score_diff_stderr, self.score_p_value);
+        info!("Win rate difference: {:+.2} ± {:.2} %", self.win_rate_diff, self.win_rate_diff_stderr);
+        for &(seed, a, b) in self.divergent_seeds.iter() {
+            info!("  seed {}: {} scored {}, {} scored {}", seed, first_name, a, second_name, b);
+        }
+    }
+}
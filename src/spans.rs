@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// Tracks which game and turn each thread is simulating, so log lines can say where they
+// came from (e.g. `game{seed=3}:turn{n=12,player=1}`), and so that verbose logs can be
+// turned on for just one game of a large batch.
+
+#[derive(Debug,Clone,Copy)]
+enum Span {
+    Game { seed: u32 },
+    Turn { n: u32, player: u32 },
+}
+
+thread_local! {
+    static SPANS: RefCell<Vec<Span>> = RefCell::new(Vec::new());
+}
+
+// the seed to log verbosely, if VERBOSE_SEED_SET
+static VERBOSE_SEED_SET: AtomicBool = AtomicBool::new(false);
+static VERBOSE_SEED: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_verbose_seed(seed: Option<u32>) {
+    VERBOSE_SEED.store(seed.unwrap_or(0) as usize, Ordering::SeqCst);
+    VERBOSE_SEED_SET.store(seed.is_some(), Ordering::SeqCst);
+}
+
+// Leaves its span when dropped
+pub struct SpanGuard {
+    _private: (),
+}
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        SPANS.with(|spans| spans.borrow_mut().pop());
+    }
+}
+
+fn enter(span: Span) -> SpanGuard {
+    SPANS.with(|spans| spans.borrow_mut().push(span));
+    SpanGuard { _private: () }
+}
+
+pub fn game_span(seed: u32) -> SpanGuard {
+    enter(Span::Game { seed: seed })
+}
+
+pub fn turn_span(n: u32, player: u32) -> SpanGuard {
+    enter(Span::Turn { n: n, player: player })
+}
+
+// the spans this thread is in, outermost first, or "" outside of any game
+pub fn current() -> String {
+    SPANS.with(|spans| {
+        spans.borrow().iter().map(|span| match *span {
+            Span::Game { seed } => format!("game{{seed={}}}", seed),
+            Span::Turn { n, player } => format!("turn{{n={},player={}}}", n, player),
+        }).collect::<Vec<_>>().join(":")
+    })
+}
+
+// whether this thread is playing the game chosen by set_verbose_seed
+pub fn in_verbose_game() -> bool {
+    if !VERBOSE_SEED_SET.load(Ordering::SeqCst) {
+        return false;
+    }
+    let verbose_seed = VERBOSE_SEED.load(Ordering::SeqCst) as u32;
+    SPANS.with(|spans| {
+        // only the innermost game counts, e.g. not a game a strategy simulates while searching
+        spans.borrow().iter().rev().filter_map(|span| match *span {
+            Span::Game { seed } => Some(seed),
+            _ => None,
+        }).next() == Some(verbose_seed)
+    })
+}
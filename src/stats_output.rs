@@ -0,0 +1,175 @@
+use std::fs;
+use std::process::Command;
+
+use game::*;
+use json::Json;
+use simulator::{Histogram, SimResult};
+
+// Writes the results of a simulation to a file, for aggregating and plotting runs
+// without scraping the log output.
+
+// Describes the run that produced a SimResult
+pub struct RunMetadata {
+    pub strategy: String,
+    pub num_players: u32,
+    pub hand_size: u32,
+    // the git commit of the source tree, if known
+    pub commit: Option<String>,
+}
+impl RunMetadata {
+    pub fn new(strategy: &str, opts: &GameOptions) -> RunMetadata {
+        RunMetadata {
+            strategy: strategy.to_string(),
+            num_players: opts.num_players,
+            hand_size: opts.hand_size,
+            commit: current_commit(),
+        }
+    }
+}
+
+// the commit checked out in the working directory, if it is a git repository
+pub fn current_commit() -> Option<String> {
+    let output = match Command::new("git").args(&["rev-parse", "HEAD"]).output() {
+        Ok(output) => output,
+        Err(_) => return None,
+    };
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok().map(|commit| commit.trim().to_string())
+}
+
+// f32 statistics, without the noise of widening them to f64
+fn float_json(x: f32) -> Json {
+    Json::Number(x.to_string().parse().unwrap())
+}
+
+fn histogram_json(hist: &Histogram) -> Json {
+    let mut keys = hist.hist.keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    let mut json = Json::object();
+    for key in keys {
+        json.insert(&key.to_string(), hist.get_count(&key));
+    }
+    json
+}
+
+// (first seed, last seed), of the games that were played
+fn seed_range(result: &SimResult) -> (Option<u32>, Option<u32>) {
+    (result.seed_scores.first().map(|&(seed, _)| seed), result.seed_scores.last().map(|&(seed, _)| seed))
+}
+
+pub fn to_json(result: &SimResult, metadata: &RunMetadata) -> Json {
+    let (first_seed, last_seed) = seed_range(result);
+    let seed_scores = result.seed_scores.iter().map(|&(seed, score)| {
+        Json::Array(vec![seed.into(), score.into()])
+    }).collect::<Vec<_>>();
+    let mut end_causes = result.end_causes.iter().collect::<Vec<_>>();
+    end_causes.sort();
+    let mut end_causes_json = Json::object();
+    for (end_cause, &count) in end_causes {
+        end_causes_json.insert(&end_cause.to_string(), count);
+    }
+    // discards of each value, from 1s to 5s
+    let by_value = |counts: &[u32; 5]| Json::Array(counts.iter().map(|&count| count.into()).collect());
+    Json::object()
+        .with("metadata", Json::object()
+              .with("strategy", &metadata.strategy[..])
+              .with("num_players", metadata.num_players)
+              .with("hand_size", metadata.hand_size)
+              .with("first_seed", first_seed)
+              .with("last_seed", last_seed)
+              .with("filtered_seeds", result.filtered_seeds)
+              .with("commit", metadata.commit.clone()))
+        .with("games", result.scores.total_count)
+        .with("average_score", float_json(result.average_score()))
+        .with("score_stderr", float_json(result.score_stderr()))
+        .with("percent_perfect", float_json(result.percent_perfect()))
+        .with("percent_perfect_stderr", float_json(result.percent_perfect_stderr()))
+        .with("average_lives", float_json(result.average_lives()))
+        .with("scores", histogram_json(&result.scores))
+        .with("lives", histogram_json(&result.lives))
+        .with("end_causes", end_causes_json)
+        .with("turns_saved", Json::Number(result.turns_saved as f64))
+        .with("turns_conceded", Json::Number(result.turns_conceded as f64))
+        .with("play_stats", Json::object()
+              .with("hints", result.play_stats.hints)
+              .with("redundant_hints", result.play_stats.redundant_hints)
+              .with("plays", result.play_stats.plays)
+              .with("bombs", result.play_stats.bombs)
+              .with("discards", result.play_stats.discards)
+              .with("critical_discards", result.play_stats.critical_discards))
+        .with("life_loss_turns", Json::Array(result.life_loss_turns.iter().map(histogram_json).collect()))
+        .with("discard_loss", histogram_json(&result.discard_loss))
+        .with("discard_risk", Json::object()
+              .with("dead", by_value(&result.discard_risk.dead))
+              .with("copy_in_deck", by_value(&result.discard_risk.copy_in_deck))
+              .with("copy_in_hand", by_value(&result.discard_risk.copy_in_hand))
+              .with("last_copy", by_value(&result.discard_risk.last_copy)))
+        .with("tempo", Json::object()
+              .with("average_length", float_json(result.tempo.lengths.average()))
+              .with("lengths", histogram_json(&result.tempo.lengths))
+              .with("draws_remaining", histogram_json(&result.tempo.draws_remaining))
+              .with("average_pace_by_turn", Json::Array((0..result.tempo.pace_by_turn.len()).map(|turn| {
+                  float_json(result.tempo.average_pace(turn).unwrap())
+              }).collect())))
+        .with("stack_curves", Json::object()
+              .with("average_turn_reached", COLORS.iter().fold(Json::object(), |json, &color| {
+                  json.with(&color.to_string(), Json::Array(VALUES.iter().map(|&value| {
+                      result.stack_curves.average_turn_reached(color, value).map_or(Json::Null, float_json)
+                  }).collect()))
+              }))
+              .with("average_score_by_turn", Json::Array((0..result.stack_curves.score_by_turn.len()).map(|turn| {
+                  float_json(result.stack_curves.average_score(turn).unwrap())
+              }).collect())))
+        .with("hint_stats", Json::object()
+              .with("color_hints", result.hint_stats.color_hints)
+              .with("value_hints", result.hint_stats.value_hints)
+              .with("by_color", result.hint_stats.by_color.clone())
+              .with("by_value", result.hint_stats.by_value.clone())
+              .with("by_distance", result.hint_stats.by_distance.clone())
+              .with("by_touched", result.hint_stats.by_touched.clone()))
+        .with("strategy_metrics", result.average_strategy_metrics().into_iter().fold(Json::object(), |json, (name, average)| {
+            json.with(name, average)
+        }))
+        .with("seed_scores", seed_scores)
+}
+
+// A header and a single row summarizing the run, so rows from many runs can be concatenated
+pub fn to_csv(result: &SimResult, metadata: &RunMetadata) -> String {
+    let (first_seed, last_seed) = seed_range(result);
+    let optional = |x: Option<u32>| x.map_or(String::new(), |x| x.to_string());
+    let mut header = vec![
+        "strategy", "num_players", "hand_size", "first_seed", "last_seed", "commit", "games",
+        "average_score", "score_stderr", "percent_perfect", "percent_perfect_stderr", "average_lives",
+    ].into_iter().map(String::from).collect::<Vec<_>>();
+    let mut row = vec![
+        metadata.strategy.clone(),
+        metadata.num_players.to_string(),
+        metadata.hand_size.to_string(),
+        optional(first_seed),
+        optional(last_seed),
+        metadata.commit.clone().unwrap_or(String::new()),
+        result.scores.total_count.to_string(),
+        result.average_score().to_string(),
+        result.score_stderr().to_string(),
+        result.percent_perfect().to_string(),
+        result.percent_perfect_stderr().to_string(),
+        result.average_lives().to_string(),
+    ];
+    for score in 0..PERFECT_SCORE + 1 {
+        header.push(format!("score_{}", score));
+        row.push(result.scores.get_count(&score).to_string());
+    }
+    format!("{}\n{}\n", header.join(","), row.join(","))
+}
+
+// Writes CSV if the path ends in .csv, and JSON otherwise
+pub fn write(path: &str, result: &SimResult, metadata: &RunMetadata) -> Result<(), String> {
+    let contents = if path.ends_with(".csv") {
+        to_csv(result, metadata)
+    } else {
+        format!("{}\n", to_json(result, metadata))
+    };
+    fs::write(path, contents).map_err(|err| format!("Couldn't write {}: {}", path, err))
+}
@@ -0,0 +1,357 @@
+use std::cmp;
+use std::rc::Rc;
+use std::cell::RefCell;
+use fnv::FnvHashMap;
+use rand;
+
+use strategy::*;
+use game::*;
+use json::Json;
+use strategies::cheating::{cheat_hands_json, CheatingStrategy, CheatingStrategyConfig, ThrowawayHint};
+
+// A cheating strategy that searches, rather than following rules.
+// Like the cheating strategy, players see their own hands through an Rc<RefCell<_>>, and they
+// also see the order of the deck (from InitContext::deck), so the game holds no chance at all.
+//
+// On each turn, every move that could make a difference is searched `depth` turns deep, trying
+// every such move on each of those turns, and the positions reached are played out with the
+// rule-based cheating strategy.  The move with the best final score is made, preferring the
+// cheating strategy's own move on ties.  Every score found is one some play reaches, so this
+// can only do as well as the best play for the deal, and the deeper it searches, the closer it
+// gets; searched to the end of the game (see Solver::exact), the score is the best possible.
+
+// What decides how a game can go on from a position: not the turn number, the order of the
+// cards in each hand, or the discard pile, which holds whatever isn't in a hand, in the deck,
+// or on a firework
+#[derive(PartialEq,Eq,Hash)]
+struct PositionKey {
+    player: Player,
+    hints: u32,
+    half_hint: bool,
+    lives: u32,
+    deckless_turns: u32,
+    fireworks: Vec<Value>,
+    deck: Cards,
+    hands: Vec<Cards>,
+    // how many more turns were searched from the position, for a depth-limited search
+    depth: u32,
+}
+impl PositionKey {
+    fn new(game: &GameState, depth: u32) -> PositionKey {
+        let board = &game.board;
+        PositionKey {
+            player: board.player,
+            hints: board.hints_remaining,
+            half_hint: board.half_hint,
+            lives: board.lives_remaining,
+            deckless_turns: board.deckless_turns_remaining,
+            fireworks: COLORS.iter().map(|&color| board.get_firework(color).top).collect(),
+            deck: game.deck.clone(),
+            hands: game.get_players().map(|player| {
+                let mut hand = game.hands[&player].clone();
+                hand.sort();
+                hand
+            }).collect(),
+            depth: depth,
+        }
+    }
+}
+
+// The moves of the player to move that could lead to different games, when everyone sees
+// everything: playing or discarding either of two copies of a card is the same, and any hint
+// only uses up a hint token
+pub fn distinct_choices(game: &GameState) -> Vec<TurnChoice> {
+    let player = game.board.player;
+    let hand = &game.hands[&player];
+    let view = game.get_view(player);
+    let mut hinted = false;
+    game.board.legal_choices(&view).filter(|choice| {
+        match *choice {
+            TurnChoice::Play(i) | TurnChoice::Discard(i) => !hand[..i].contains(&hand[i]),
+            TurnChoice::Hint(_) => {
+                let first = !hinted;
+                hinted = true;
+                first
+            }
+        }
+    }).collect()
+}
+
+// Finds the best final score reachable from a position, with every hand and the order of the
+// deck known, by trying every distinct move (see distinct_choices) on each turn
+pub struct Solver {
+    // how many turns to search before playing the rest out with the cheating strategy,
+    // or None to search to the end of the game
+    depth: Option<u32>,
+    // the best score found from each position searched
+    scores: FnvHashMap<PositionKey, Score>,
+}
+impl Solver {
+    pub fn new(depth: u32) -> Solver {
+        Solver {
+            depth: Some(depth),
+            scores: FnvHashMap::default(),
+        }
+    }
+
+    // searches to the end of the game, so the scores are the best any play could reach
+    pub fn exact() -> Solver {
+        Solver {
+            depth: None,
+            scores: FnvHashMap::default(),
+        }
+    }
+
+    // the best final score from the game as it is.  The game is put back as it was afterwards
+    pub fn best_score(&mut self, game: &mut GameState) -> Score {
+        let depth = self.depth.unwrap_or(u32::max_value());
+        self.search(game, depth)
+    }
+
+    // the best final score once `choice` is made.  The game is put back as it was afterwards
+    pub fn score_after(&mut self, game: &mut GameState, choice: &TurnChoice) -> Score {
+        let depth = self.depth.unwrap_or(u32::max_value());
+        let undo = game.apply(choice);
+        let score = self.search(game, depth.saturating_sub(1));
+        game.undo(undo);
+        score
+    }
+
+    fn search(&mut self, game: &mut GameState, depth: u32) -> Score {
+        if game.is_over() {
+            return game.score();
+        }
+        if depth == 0 {
+            return play_out(game);
+        }
+        // searched to the end, a position's score doesn't depend on how deep it was found
+        let key = PositionKey::new(game, if self.depth.is_some() { depth } else { 0 });
+        if let Some(&score) = self.scores.get(&key) {
+            return score;
+        }
+        // no play can beat this, so a move that reaches it needs no company
+        let bound = game.board.max_attainable_score();
+        let mut best = 0;
+        for choice in distinct_choices(game) {
+            let undo = game.apply(&choice);
+            best = cmp::max(best, self.search(game, depth - 1));
+            game.undo(undo);
+            if best >= bound {
+                break;
+            }
+        }
+        self.scores.insert(key, best);
+        best
+    }
+}
+
+// every player follows the cheating strategy from this position
+fn cheating_players(game: &GameState) -> FnvHashMap<Player, Box<PlayerStrategy>> {
+    let policy = CheatingStrategy::new(ThrowawayHint::FirstCard);
+    let opts = game.board.options();
+    game.get_players().map(|player| {
+        let view = game.get_view(player);
+        // the cheating strategy doesn't use its randomness
+        (player, policy.initialize(&InitContext::new(&policy, &opts, &view), rand::ChaChaRng::new_unseeded()))
+    }).collect()
+}
+
+// the final score if everyone follows the cheating strategy from here.
+// The game is put back as it was afterwards
+fn play_out(game: &mut GameState) -> Score {
+    let mut undos = Vec::new();
+    let mut players = cheating_players(game);
+    while !game.is_over() {
+        let player = game.board.player;
+        let choice = players.get_mut(&player).unwrap().decide(&game.get_view(player));
+        undos.push(game.apply(&choice));
+        let turn = game.turn_history.last().unwrap().clone();
+        for player in game.get_players() {
+            players.get_mut(&player).unwrap().update(&turn, &game.get_view(player));
+        }
+    }
+    let score = game.score();
+    while let Some(undo) = undos.pop() {
+        game.undo(undo);
+    }
+    score
+}
+
+pub struct CheatSearchStrategyConfig {
+    // how many turns to search, trying every move, before playing the rest out with the
+    // cheating strategy
+    pub depth: u32,
+    // whether to concede when the search finds no way to score another point
+    pub concede: bool,
+}
+
+impl CheatSearchStrategyConfig {
+    pub fn new() -> CheatSearchStrategyConfig {
+        CheatSearchStrategyConfig {
+            depth: 2,
+            concede: false,
+        }
+    }
+}
+impl GameStrategyConfig for CheatSearchStrategyConfig {
+    fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
+        Box::new(CheatSearchStrategy {
+            player_hands_cheat: Rc::new(RefCell::new(FnvHashMap::default())),
+            depth: self.depth,
+            concede: self.concede,
+        })
+    }
+
+    fn version(&self) -> u32 {
+        2
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "depth" => parse_param(name, value, &mut self.depth),
+            "concede" => parse_param(name, value, &mut self.concede),
+            _ => Err(format!("Unknown parameter for the cheat-search strategy: {}", name)),
+        }
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        let cheating = CheatingStrategyConfig::new();
+        let mut choosing = vec![
+            String::from("Find the move the cheating strategy would make (see below)."),
+            format!("Try every play, discard and hint that could make a difference, and every such move on \
+                     the {} turns after it, with the deck in its true order; then play each position \
+                     reached out with the cheating strategy.", self.depth.saturating_sub(1)),
+            String::from("Make the move with the best final score, or the cheating strategy's move if no \
+                          other move does better."),
+        ];
+        if self.concede {
+            choosing.insert(1, String::from("Concede if no move found scores another point."));
+        }
+        Some(Conventions::new("cheat-search", "Sees its own hand like the cheating strategy, and the order of \
+                                               the deck, and searches: every sensible move is tried a few \
+                                               turns deep, and the rest of the game is played out with the \
+                                               cheating strategy.")
+             .section("Choosing a move", choosing)
+             .section("The cheating strategy's move", cheating.rules())
+             .param("depth", self.depth)
+             .param("concede", self.concede))
+    }
+}
+
+pub struct CheatSearchStrategy {
+    player_hands_cheat: Rc<RefCell<FnvHashMap<Player, Cards>>>,
+    depth: u32,
+    concede: bool,
+}
+impl GameStrategy for CheatSearchStrategy {
+    fn initialize(&self, context: &InitContext, _: StrategyRng) -> Box<PlayerStrategy> {
+        for (&player, &hand) in &context.view.other_hands {
+            self.player_hands_cheat.borrow_mut().insert(
+                player, hand.clone()
+            );
+        }
+        let deck = context.deck.expect("The cheat-search strategy can only play where it can see the deck");
+        Box::new(CheatSearchPlayerStrategy {
+            player_hands_cheat: self.player_hands_cheat.clone(),
+            me: context.player,
+            deck: deck.clone(),
+            depth: self.depth,
+            concede: self.concede,
+        })
+    }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("cheat-search")
+    }
+}
+
+pub struct CheatSearchPlayerStrategy {
+    player_hands_cheat: Rc<RefCell<FnvHashMap<Player, Cards>>>,
+    me: Player,
+    // the cards still to be drawn, last drawn first
+    deck: Cards,
+    depth: u32,
+    concede: bool,
+}
+impl CheatSearchPlayerStrategy {
+    // the player to our right might have drawn a new card
+    fn peek_at_previous_hand(&self, view: &BorrowedGameView) {
+        let previous = view.board.player_to_right(&self.me);
+        self.player_hands_cheat.borrow_mut().insert(previous, view.other_hands[&previous].clone());
+    }
+
+    // the whole game as it is now
+    fn known_game(&self, view: &BorrowedGameView) -> GameState {
+        let mut hands = view.other_hands.iter().map(|(&player, &hand)| {
+            (player, hand.clone())
+        }).collect::<FnvHashMap<_, _>>();
+        hands.insert(self.me, self.player_hands_cheat.borrow()[&self.me].clone());
+        assert_eq!(self.deck.len() as u32, view.board.deck_size);
+        GameState {
+            hands: hands,
+            board: view.board.clone(),
+            deck: self.deck.clone(),
+            turn_history: Vec::new(),
+            strategy_metrics: FnvHashMap::default(),
+        }
+    }
+}
+
+impl PlayerStrategy for CheatSearchPlayerStrategy {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        self.peek_at_previous_hand(view);
+
+        let mut game = self.known_game(view);
+        let default = cheating_players(&game).get_mut(&self.me).unwrap().decide(&game.get_view(self.me));
+        let mut solver = Solver::new(self.depth);
+        let bound = game.board.max_attainable_score();
+        let mut best = (solver.score_after(&mut game, &default), default);
+        for choice in distinct_choices(&game) {
+            if best.0 >= bound {
+                break;
+            }
+            let score = solver.score_after(&mut game, &choice);
+            if score > best.0 {
+                best = (score, choice);
+            }
+        }
+        best.1
+    }
+
+    // follows the deck: cards are drawn from its end, and misplays may go back under it
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        if let TurnResult::Play(ref card, false) = turn.result {
+            if view.board.misplay_rule == MisplayRule::ReturnToDeckBottom {
+                self.deck.insert(0, card.clone());
+            }
+        }
+        while self.deck.len() as u32 > view.board.deck_size {
+            self.deck.pop();
+        }
+    }
+
+    // our own turns are when we would have peeked, and our move didn't change what we saw
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        if turn.player == self.me {
+            self.peek_at_previous_hand(view);
+        }
+        self.update(turn, view);
+    }
+
+    fn concede(&mut self, view: &BorrowedGameView) -> bool {
+        if !self.concede {
+            return false;
+        }
+        self.peek_at_previous_hand(view);
+        let mut game = self.known_game(view);
+        Solver::new(self.depth).best_score(&mut game) <= game.score()
+    }
+
+    fn inspect(&self) -> Option<Json> {
+        Some(Json::object()
+             .with("depth", self.depth)
+             .with("concede", self.concede)
+             .with("deck", self.deck.iter().rev().map(|card| card.to_string()).collect::<Vec<_>>().join(" "))
+             .with("peeked_hands", cheat_hands_json(&self.player_hands_cheat.borrow())))
+    }
+}
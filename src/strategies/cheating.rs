@@ -4,6 +4,7 @@ use fnv::{FnvHashMap, FnvHashSet};
 
 use strategy::*;
 use game::*;
+use json::Json;
 
 // strategy that explicitly cheats by using Rc/RefCell
 // serves as a reference point for other strategies
@@ -17,47 +18,142 @@ use game::*;
 //  - if a hint exists, hint
 //  - discard the first card
 
-pub struct CheatingStrategyConfig;
+// the hands a cheater has peeked at, for PlayerStrategy::inspect
+pub fn cheat_hands_json(hands: &FnvHashMap<Player, Cards>) -> Json {
+    let mut players = hands.keys().cloned().collect::<Vec<_>>();
+    players.sort();
+    Json::Array(players.into_iter().map(|player| {
+        let cards = hands[&player].iter().map(|card| card.to_string()).collect::<Vec<_>>();
+        Json::object().with("player", player).with("hand", cards.join(" "))
+    }).collect())
+}
+
+// represents how badly a card needs to be played
+pub fn card_play_value(board: &BoardState, card: &Card) -> u32 {
+    if board.is_dead(card) {
+        return 0;
+    }
+    if !board.is_dispensable(card) {
+        10 - card.value
+    } else {
+        1
+    }
+}
+
+// Which hint to give when we have nothing better to do.
+// The hint carries no meaning for cheaters, but an honest partner may read something into it.
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub enum ThrowawayHint {
+    // the value of the next player's first card
+    FirstCard,
+    // assume every partner reads hints by one fixed convention, that the cards touched are
+    // playable, and touch as few unplayable cards as possible.  A partner who reads hints any
+    // other way (as saves, or by a strategy's own conventions) may still be misled
+    PlayClueReader,
+}
+
+pub struct CheatingStrategyConfig {
+    pub throwaway_hint: ThrowawayHint,
+}
 
 impl CheatingStrategyConfig {
     pub fn new() -> CheatingStrategyConfig {
-        CheatingStrategyConfig
+        CheatingStrategyConfig {
+            throwaway_hint: ThrowawayHint::FirstCard,
+        }
     }
 }
 impl GameStrategyConfig for CheatingStrategyConfig {
     fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
-        Box::new(CheatingStrategy::new())
+        Box::new(CheatingStrategy::new(self.throwaway_hint))
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "throwaway_hint" => {
+                self.throwaway_hint = match value {
+                    "first-card" => ThrowawayHint::FirstCard,
+                    "play-clue-reader" => ThrowawayHint::PlayClueReader,
+                    _ => return Err(format!("Invalid value for parameter {}: {}", name, value)),
+                };
+                Ok(())
+            }
+            _ => Err(format!("Unknown parameter for the cheating strategy: {}", name)),
+        }
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        Some(Conventions::new("cheat", "Every player sees their own hand, by cheating, so hints carry no \
+                                        information and only buy time.  A reference point for honest strategies.")
+             .section("Choosing a move", self.rules())
+             .param("throwaway_hint", match self.throwaway_hint {
+                 ThrowawayHint::FirstCard => "first-card",
+                 ThrowawayHint::PlayClueReader => "play-clue-reader",
+             }))
+    }
+}
+impl CheatingStrategyConfig {
+    // how a cheater chooses its move, in priority order
+    pub fn rules(&self) -> Vec<String> {
+        vec![
+            String::from("Play a playable card, leaving any card to another player who holds it and has \
+                          less to play, and otherwise preferring lower values."),
+            String::from("While discards can't yet cost the last round (no more have been discarded than \
+                          the deck holds beyond the 25 cards to play and the cards in hands), discard a card \
+                          that is dead or duplicated in another hand."),
+            format!("If there are hints left and someone else can play, give a throwaway hint: {}.",
+                    match self.throwaway_hint {
+                        ThrowawayHint::FirstCard => "the value of the next player's first card",
+                        ThrowawayHint::PlayClueReader => "one touching as few unplayable cards as possible, \
+                                                          in case the partner reads hints as play hints",
+                    }),
+            String::from("Discard a card that is dead or duplicated in another hand."),
+            String::from("Discard the card that is best to lose: one someone else holds a copy of, then \
+                          one with copies left, then the highest value."),
+        ]
     }
 }
 
 pub struct CheatingStrategy {
     player_hands_cheat: Rc<RefCell<FnvHashMap<Player, Cards>>>,
+    throwaway_hint: ThrowawayHint,
 }
 
 impl CheatingStrategy {
-    pub fn new() -> CheatingStrategy {
+    pub fn new(throwaway_hint: ThrowawayHint) -> CheatingStrategy {
         CheatingStrategy {
             player_hands_cheat: Rc::new(RefCell::new(FnvHashMap::default())),
+            throwaway_hint: throwaway_hint,
         }
     }
 }
 impl GameStrategy for CheatingStrategy {
-    fn initialize(&self, player: Player, view: &BorrowedGameView) -> Box<PlayerStrategy> {
-        for (&player, &hand) in &view.other_hands {
+    fn initialize(&self, context: &InitContext, _: StrategyRng) -> Box<PlayerStrategy> {
+        for (&player, &hand) in &context.view.other_hands {
             self.player_hands_cheat.borrow_mut().insert(
                 player, hand.clone()
             );
         }
         Box::new(CheatingPlayerStrategy {
             player_hands_cheat: self.player_hands_cheat.clone(),
-            me: player,
+            me: context.player,
+            throwaway_hint: self.throwaway_hint,
         })
     }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("cheat")
+    }
 }
 
 pub struct CheatingPlayerStrategy {
     player_hands_cheat: Rc<RefCell<FnvHashMap<Player, Cards>>>,
     me: Player,
+    throwaway_hint: ThrowawayHint,
 }
 impl CheatingPlayerStrategy {
     // last player might've drawn a new card, let him know!
@@ -71,29 +167,53 @@ impl CheatingPlayerStrategy {
 
     // give a throwaway hint - we only do this when we have nothing to do
     fn throwaway_hint(&self, view: &BorrowedGameView) -> TurnChoice {
-        let hint_player = view.board.player_to_left(&self.me);
-        let hint_card = &view.get_hand(&hint_player).first().unwrap();
-        TurnChoice::Hint(Hint {
-            player: hint_player,
-            hinted: Hinted::Value(hint_card.value)
-        })
+        match self.throwaway_hint {
+            ThrowawayHint::FirstCard => {
+                // the player to our left, unless their hand has run out at the end of the game
+                let mut hint_player = view.board.player_to_left(&self.me);
+                while view.get_hand(&hint_player).is_empty() {
+                    hint_player = view.board.player_to_left(&hint_player);
+                }
+                let hint_card = &view.get_hand(&hint_player).first().unwrap();
+                TurnChoice::Hint(Hint {
+                    player: hint_player,
+                    hinted: Hinted::Value(hint_card.value)
+                })
+            }
+            ThrowawayHint::PlayClueReader => {
+                TurnChoice::Hint(self.least_misleading_hint(view))
+            }
+        }
     }
 
-    // represents how badly a card needs to be played
-    fn card_play_value(&self, view: &BorrowedGameView, card: &Card) -> u32 {
-        if view.board.is_dead(card) {
-            return 0;
-        }
-        if !view.board.is_dispensable(card) {
-            10 - card.value
-        } else {
-            1
+    // the hint that would make a reader who plays touched cards bomb the least,
+    // touching as few cards as possible, to players as close to our left as possible.
+    // Every partner is assumed to read it that way (see ThrowawayHint::PlayClueReader)
+    fn least_misleading_hint(&self, view: &BorrowedGameView) -> Hint {
+        let num_players = view.board.num_players;
+        let mut best: Option<((usize, usize, u32), Hint)> = None;
+        for choice in view.board.legal_choices(view) {
+            let hint = match choice {
+                TurnChoice::Hint(hint) => hint,
+                _ => continue,
+            };
+            let touched = view.get_hand(&hint.player).iter().filter(|card| hint.hinted.matches(card)).collect::<Vec<_>>();
+            if touched.is_empty() {
+                continue;
+            }
+            let misplays = touched.iter().filter(|card| !view.board.is_playable(card)).count();
+            let distance = (hint.player + num_players - self.me) % num_players;
+            let key = (misplays, touched.len(), distance);
+            if best.as_ref().map_or(true, |&(best_key, _)| key < best_key) {
+                best = Some((key, hint));
+            }
         }
+        best.expect("No other player has any cards to hint").1
     }
 
     // given a hand of cards, represents how badly it will need to play things
     fn hand_play_value(&self, view: &BorrowedGameView, hand: &Cards) -> u32 {
-        hand.iter().map(|card| self.card_play_value(view, card)).fold(0, |a,b| a+b)
+        hand.iter().map(|card| card_play_value(view.board, card)).fold(0, |a,b| a+b)
     }
 
     // how badly do we need to play a particular card
@@ -165,10 +285,7 @@ impl PlayerStrategy for CheatingPlayerStrategy {
         // such that if we only played,
         // we would not reach the final countdown round
         // e.g. 50 total, 25 to play, 20 in hand
-        let discard_threshold =
-            view.board.total_cards
-            - (COLORS.len() * VALUES.len()) as u32
-            - (view.board.num_players * view.board.hand_size);
+        let discard_threshold = view.board.spare_discards();
         if view.board.discard_size() <= discard_threshold {
             // if anything is totally useless, discard it
             if let Some(i) = self.find_useless_card(view, my_hand) {
@@ -210,4 +327,16 @@ impl PlayerStrategy for CheatingPlayerStrategy {
     }
     fn update(&mut self, _: &TurnRecord, _: &BorrowedGameView) {
     }
+    // our own turns are when we would have peeked, and our move didn't change what we saw
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        if turn.player == self.me {
+            self.inform_last_player_cards(view);
+        }
+    }
+    fn inspect(&self) -> Option<Json> {
+        Some(Json::object()
+             .with("throwaway_hint", format!("{:?}", self.throwaway_hint))
+             // as of this player's last turn, for the player to its right
+             .with("peeked_hands", cheat_hands_json(&self.player_hands_cheat.borrow())))
+    }
 }
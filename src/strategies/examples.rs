@@ -1,6 +1,9 @@
+use fnv::FnvHashSet;
+use rand::Rng;
+
 use strategy::*;
 use game::*;
-use rand::{self, Rng};
+use helpers::*;
 
 // dummy, terrible strategy, as an example
 #[derive(Clone)]
@@ -16,6 +19,31 @@ impl GameStrategyConfig for RandomStrategyConfig {
             play_probability: self.play_probability,
         })
     }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "hint_probability" => parse_param(name, value, &mut self.hint_probability),
+            "play_probability" => parse_param(name, value, &mut self.play_probability),
+            _ => Err(format!("Unknown parameter for the random strategy: {}", name)),
+        }
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        Some(Conventions::new("random", "Moves at random, without looking at anything but the hint count.")
+             .section("Choosing a move", vec![
+                 format!("With probability {}, hint the next player about the color or value (at random) \
+                          of a random card in their hand, if there are hints left; otherwise discard the \
+                          first card.", self.hint_probability),
+                 format!("Otherwise, with probability {} overall, play the first card.", self.play_probability),
+                 String::from("Otherwise, discard the first card."),
+             ])
+             .param("hint_probability", self.hint_probability)
+             .param("play_probability", self.play_probability))
+    }
 }
 
 pub struct RandomStrategy {
@@ -23,30 +51,40 @@ pub struct RandomStrategy {
     play_probability: f64,
 }
 impl GameStrategy for RandomStrategy {
-    fn initialize(&self, player: Player, _: &BorrowedGameView) -> Box<PlayerStrategy> {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
         Box::new(RandomStrategyPlayer {
             hint_probability: self.hint_probability,
             play_probability: self.play_probability,
-            me: player,
+            me: context.player,
+            rng: rng,
         })
     }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("random")
+    }
 }
 
 pub struct RandomStrategyPlayer {
     hint_probability: f64,
     play_probability: f64,
     me: Player,
+    rng: StrategyRng,
 }
 
 impl PlayerStrategy for RandomStrategyPlayer {
     fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
-        let p = rand::random::<f64>();
+        if view.my_hand_size() == 0 {
+            return any_legal_choice(view);
+        }
+        let p = self.rng.gen::<f64>();
         if p < self.hint_probability {
-            if view.board.hints_remaining > 0 {
-                let hint_player = view.board.player_to_left(&self.me);
-                let hint_card = rand::thread_rng().choose(&view.get_hand(&hint_player)).unwrap();
+            let hint_player = view.board.player_to_left(&self.me);
+            // with EndgameRule::UntilStuck, hands can run out of cards
+            if view.board.hints_remaining > 0 && !view.get_hand(&hint_player).is_empty() {
+                let hint_card = self.rng.choose(&view.get_hand(&hint_player)).unwrap();
                 let hinted = {
-                    if rand::random() {
+                    if self.rng.gen() {
                         // hint a color
                         Hinted::Color(hint_card.color)
                     } else {
@@ -68,4 +106,297 @@ impl PlayerStrategy for RandomStrategyPlayer {
     }
     fn update(&mut self, _: &TurnRecord, _: &BorrowedGameView) {
     }
+    // draws what decide() drew for our own turns, so the rest of the game goes as if we had played
+    // them.  A hint leaves every hand as it was, so a hint from an empty hand drew nothing
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        if turn.player != self.me {
+            return;
+        }
+        if let TurnChoice::Hint(ref hint) = turn.choice {
+            if view.my_hand_size() > 0 {
+                self.rng.gen::<f64>();
+                self.rng.choose(&view.get_hand(&hint.player));
+                self.rng.gen::<bool>();
+            }
+        } else {
+            self.rng.gen::<f64>();
+        }
+    }
+}
+
+// Baselines that use little or no information, to measure real strategies against.
+// Each falls back to any legal move when its hand is empty, as can happen with
+// EndgameRule::UntilStuck.
+
+fn any_legal_choice(view: &BorrowedGameView) -> TurnChoice {
+    view.board.legal_choices(view).next().expect("No legal moves")
+}
+
+// the hints that would be legal if there were hints left, in the order legal_choices gives them
+fn possible_hints(view: &BorrowedGameView) -> Vec<TurnChoice> {
+    let board = view.board;
+    board.get_players().filter(|&player| player != view.me()).flat_map(|player| {
+        let hand = view.get_hand(&player);
+        Hinted::all().filter(move |hinted| {
+            board.allow_empty_hints || hand.iter().any(|card| hinted.matches(card))
+        }).map(move |hinted| TurnChoice::Hint(Hint { player: player, hinted: hinted }))
+    }).collect()
+}
+
+// plays the oldest card, never hinting or discarding
+pub struct PlayFirstStrategyConfig;
+
+impl PlayFirstStrategyConfig {
+    pub fn new() -> PlayFirstStrategyConfig {
+        PlayFirstStrategyConfig
+    }
+}
+impl GameStrategyConfig for PlayFirstStrategyConfig {
+    fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
+        Box::new(PlayFirstStrategy)
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        Some(Conventions::new("play-first", "A baseline that uses no information at all.")
+             .section("Choosing a move", vec![
+                 String::from("Play the oldest card."),
+             ]))
+    }
+}
+
+pub struct PlayFirstStrategy;
+impl GameStrategy for PlayFirstStrategy {
+    fn initialize(&self, _: &InitContext, _: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(PlayFirstStrategyPlayer)
+    }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("play-first")
+    }
+}
+
+pub struct PlayFirstStrategyPlayer;
+impl PlayerStrategy for PlayFirstStrategyPlayer {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        if view.my_hand_size() > 0 {
+            TurnChoice::Play(0)
+        } else {
+            any_legal_choice(view)
+        }
+    }
+    fn update(&mut self, _: &TurnRecord, _: &BorrowedGameView) {
+    }
+}
+
+// discards the oldest card, and hints at random, playing only what random hints happen to prove
+pub struct DiscardOldestStrategyConfig;
+
+impl DiscardOldestStrategyConfig {
+    pub fn new() -> DiscardOldestStrategyConfig {
+        DiscardOldestStrategyConfig
+    }
+}
+impl GameStrategyConfig for DiscardOldestStrategyConfig {
+    fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
+        Box::new(DiscardOldestStrategy)
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        Some(Conventions::new("discard-oldest", "A baseline whose hints carry no meaning: they are chosen at \
+                                                 random, and only what they literally say is used.")
+             .section("Choosing a move", vec![
+                 String::from("Play the oldest card that every hint it was touched by or missed proves is \
+                               playable."),
+                 String::from("If there are hints left, give a legal hint at random."),
+                 String::from("Otherwise, discard the oldest card."),
+             ]))
+    }
+}
+
+pub struct DiscardOldestStrategy;
+impl GameStrategy for DiscardOldestStrategy {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(DiscardOldestStrategyPlayer {
+            me: context.player,
+            public: BeliefTracker::new(context.view.board),
+            rng: rng,
+        })
+    }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("discard-oldest")
+    }
+}
+
+pub struct DiscardOldestStrategyPlayer {
+    me: Player,
+    public: BeliefTracker,
+    rng: StrategyRng,
+}
+impl PlayerStrategy for DiscardOldestStrategyPlayer {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        let board = view.board;
+        let playable = self.public.hand(self.me).iter().position(|table| {
+            table.iter_possibilities().all(|(card, _)| board.is_playable(&card))
+        });
+        if let Some(index) = playable {
+            return TurnChoice::Play(index);
+        }
+        if board.hints_remaining > 0 {
+            let hints = possible_hints(view);
+            if let Some(hint) = self.rng.choose(&hints) {
+                return hint.clone();
+            }
+        }
+        if view.my_hand_size() > 0 {
+            TurnChoice::Discard(0)
+        } else {
+            any_legal_choice(view)
+        }
+    }
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.public.update(turn, view);
+    }
+    // we only drew when we hinted, and a hint leaves every hand, and so the choice of hints, as it was
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        if turn.player == self.me {
+            if let TurnChoice::Hint(_) = turn.choice {
+                self.rng.choose(&possible_hints(view));
+            }
+        }
+        self.update(turn, view);
+    }
+}
+
+// The first convention human beginners learn: every hint asks the player to play the cards it
+// touches, and a player with nothing to play discards their chop, the oldest card no hint touched
+pub struct BeginnerStrategyConfig;
+
+impl BeginnerStrategyConfig {
+    pub fn new() -> BeginnerStrategyConfig {
+        BeginnerStrategyConfig
+    }
+}
+impl GameStrategyConfig for BeginnerStrategyConfig {
+    fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
+        Box::new(BeginnerStrategy)
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        Some(Conventions::new("beginner", "Play if told, otherwise discard chop: every hint is a play hint, \
+                                           and nothing else is remembered.")
+             .section("Choosing a move", vec![
+                 String::from("Play the oldest card any hint has touched."),
+                 String::from("If there are hints left, give the first hint, starting with the player to the \
+                               left, that touches an untouched playable card, and only touches untouched \
+                               cards that are playable, different from each other, and not the same as a \
+                               card touched in another hand.  Value hints come before color hints."),
+                 String::from("Otherwise, discard the chop: the oldest card no hint has touched."),
+             ]))
+    }
+}
+
+pub struct BeginnerStrategy;
+impl GameStrategy for BeginnerStrategy {
+    fn initialize(&self, context: &InitContext, _: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(BeginnerStrategyPlayer {
+            me: context.player,
+            touched: FnvHashSet::default(),
+        })
+    }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("beginner")
+    }
+}
+
+pub struct BeginnerStrategyPlayer {
+    me: Player,
+    // the ids of every card any hint has touched
+    touched: FnvHashSet<CardId>,
+}
+impl BeginnerStrategyPlayer {
+    fn is_touched(&self, board: &BoardState, player: Player, index: usize) -> bool {
+        self.touched.contains(&board.card_ids(&player)[index])
+    }
+
+    // a hint that asks only for good plays, if there is one
+    fn find_play_hint(&self, view: &BorrowedGameView) -> Option<Hint> {
+        let board = view.board;
+        let others = (1..board.num_players).map(|i| (self.me + i) % board.num_players).collect::<Vec<_>>();
+        let touched_elsewhere = |player: Player| -> Vec<Card> {
+            others.iter().filter(|&&other| other != player).flat_map(|&other| {
+                view.get_hand(&other).iter().enumerate()
+                    .filter(move |&(i, _)| self.is_touched(board, other, i))
+                    .map(|(_, card)| card.clone())
+            }).collect()
+        };
+        for &player in others.iter() {
+            let hand = view.get_hand(&player);
+            let elsewhere = touched_elsewhere(player);
+            for (i, card) in hand.iter().enumerate() {
+                if self.is_touched(board, player, i) || !board.is_playable(card) {
+                    continue;
+                }
+                for hinted in vec![Hinted::Value(card.value), Hinted::Color(card.color)] {
+                    let newly_touched = hand.iter().enumerate().filter(|&(j, other)| {
+                        hinted.matches(other) && !self.is_touched(board, player, j)
+                    }).map(|(_, other)| other).collect::<Vec<_>>();
+                    let all_good = newly_touched.iter().enumerate().all(|(k, other)| {
+                        board.is_playable(other) && !elsewhere.contains(other) &&
+                            !newly_touched[..k].contains(other)
+                    });
+                    if all_good {
+                        return Some(Hint {
+                            player: player,
+                            hinted: hinted,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+impl PlayerStrategy for BeginnerStrategyPlayer {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        let board = view.board;
+        let hand_size = view.my_hand_size();
+        if let Some(index) = (0..hand_size).find(|&i| self.is_touched(board, self.me, i)) {
+            return TurnChoice::Play(index);
+        }
+        if board.hints_remaining > 0 {
+            if let Some(hint) = self.find_play_hint(view) {
+                return TurnChoice::Hint(hint);
+            }
+        }
+        if hand_size > 0 {
+            // with no touched cards, the chop is the oldest card
+            TurnChoice::Discard(0)
+        } else {
+            any_legal_choice(view)
+        }
+    }
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        if let (&TurnChoice::Hint(ref hint), &TurnResult::Hint(ref matches)) = (&turn.choice, &turn.result) {
+            let ids = view.board.card_ids(&hint.player);
+            for (&id, &matched) in ids.iter().zip(matches.iter()) {
+                if matched {
+                    self.touched.insert(id);
+                }
+            }
+        }
+    }
 }
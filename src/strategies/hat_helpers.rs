@@ -76,6 +76,36 @@ impl ModulusInformation {
     }
 }
 
+// How much information hat sums carried.  Each player given part of a hat value could learn
+// log2 of its modulus in bits, but only uses what their questions ask for: the rest is wasted
+// when their answers are cast up to the modulus.
+#[derive(Debug,Clone)]
+pub struct HatStats {
+    pub sums: u32,
+    // summed over the players given each hat value
+    pub bits: f64,
+    pub wasted_bits: f64,
+}
+impl HatStats {
+    pub fn new() -> HatStats {
+        HatStats {
+            sums: 0,
+            bits: 0.0,
+            wasted_bits: 0.0,
+        }
+    }
+
+    // a hat sum modulo `total_info`, whose players' answers had the given moduli
+    pub fn record(&mut self, total_info: u32, used_moduli: &[u32]) {
+        let bits = (total_info as f64).log2();
+        self.sums += 1;
+        for &used_modulus in used_moduli {
+            self.bits += bits;
+            self.wasted_bits += bits - (used_modulus as f64).log2();
+        }
+    }
+}
+
 pub trait Question {
     // how much info does this question ask for?
     fn info_amount(&self) -> u32;
@@ -156,7 +186,13 @@ pub trait PublicInformation: Clone {
         self.update_other_info();
     }
 
-    fn get_hat_info_for_player(
+    /// Called by `get_hat_sum()` with the modulus of the sum, and the modulus of each player's
+    /// answers before they were cast up to it, e.g. to measure how much information is wasted.
+    fn on_hat_sum(&mut self, _total_info: u32, _used_moduli: &[u32]) {
+    }
+
+    /// The answers to the questions `player` is asked, before being cast up to `total_info`.
+    fn get_hat_answers_for_player(
         &self, player: &Player, hand_info: &mut HandInfo<CardPossibilityTable>, total_info: u32, view: &OwnedGameView
     ) -> ModulusInformation {
         assert!(player != &view.player);
@@ -166,6 +202,13 @@ pub trait PublicInformation: Clone {
             question.acknowledge_answer_info(new_answer_info.clone(), hand_info, view.get_board());
             answer_info.combine(new_answer_info, total_info);
         }
+        answer_info
+    }
+
+    fn get_hat_info_for_player(
+        &self, player: &Player, hand_info: &mut HandInfo<CardPossibilityTable>, total_info: u32, view: &OwnedGameView
+    ) -> ModulusInformation {
+        let mut answer_info = self.get_hat_answers_for_player(player, hand_info, total_info, view);
         answer_info.cast_up(total_info);
         answer_info
     }
@@ -190,13 +233,16 @@ pub trait PublicInformation: Clone {
     fn get_hat_sum(&mut self, total_info: u32, view: &OwnedGameView) -> ModulusInformation {
         let (infos, new_player_hands): (Vec<_>, Vec<_>) = view.get_other_players().iter().map(|player| {
             let mut hand_info = self.get_player_info(player);
-            let info = self.get_hat_info_for_player(player, &mut hand_info, total_info, view);
+            let info = self.get_hat_answers_for_player(player, &mut hand_info, total_info, view);
             (info, (player.clone(), hand_info))
         }).unzip();
         self.set_player_infos(new_player_hands);
+        let used_moduli = infos.iter().map(|info| info.modulus).collect::<Vec<_>>();
+        self.on_hat_sum(total_info, &used_moduli);
         infos.into_iter().fold(
             ModulusInformation::new(total_info, 0),
-            |mut sum_info, info| {
+            |mut sum_info, mut info| {
+                info.cast_up(total_info);
                 sum_info.add(&info);
                 sum_info
             }
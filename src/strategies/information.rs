@@ -1,10 +1,14 @@
 use fnv::{FnvHashMap, FnvHashSet};
 use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use float_ord::*;
 
 use strategy::*;
 use game::*;
 use helpers::*;
+use json::Json;
+use render;
 use strategies::hat_helpers::*;
 
 // TODO: use random extra information - i.e. when casting up and down,
@@ -180,21 +184,28 @@ impl Question for CardPossibilityPartition {
     }
 }
 
-#[derive(Eq,PartialEq,Clone)]
+#[derive(Clone)]
 struct MyPublicInformation {
-    hand_info: FnvHashMap<Player, HandInfo<CardPossibilityTable>>,
-    card_counts: CardCounts, // what any newly drawn card should be
+    beliefs: BeliefTracker,
     board: BoardState, // TODO: maybe we should store an appropriately lifetimed reference?
+    params: InformationStrategyConfig, // the same for every player, for the whole game
+    // of the hat sums this player has made, not shared with the others
+    hat_stats: HatStats,
 }
-
-impl MyPublicInformation {
-    fn get_player_info_mut(&mut self, player: &Player) -> &mut HandInfo<CardPossibilityTable> {
-        self.hand_info.get_mut(player).unwrap()
+impl PartialEq for MyPublicInformation {
+    fn eq(&self, other: &Self) -> bool {
+        self.beliefs == other.beliefs &&
+        self.board == other.board
     }
-    fn take_player_info(&mut self, player: &Player) -> HandInfo<CardPossibilityTable> {
-        self.hand_info.remove(player).unwrap()
+}
+impl Eq for MyPublicInformation {}
+impl Hash for MyPublicInformation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.beliefs.hash(state);
     }
+}
 
+impl MyPublicInformation {
     fn get_other_players_starting_after(&self, player: Player) -> Vec<Player> {
         let n = self.board.num_players;
         (0 .. n - 1).into_iter().map(|i| { (player + 1 + i) % n }).collect()
@@ -202,11 +213,15 @@ impl MyPublicInformation {
 
     // Returns the number of ways to hint the player.
     fn get_info_per_player(&self, player: Player) -> u32 {
+        // If hints may match no cards, a color and a value hint not involving any given card always exist.
+        if self.board.allow_empty_hints {
+            return 4;
+        }
+
         // Determine if both:
         //  - it is public that there are at least two colors
         //  - it is public that there are at least two numbers
-
-        let ref info = self.hand_info[&player];
+        let info = self.beliefs.hand(player);
 
         let may_be_all_one_color = COLORS.iter().any(|color| {
             info.iter().all(|card| {
@@ -242,7 +257,7 @@ impl MyPublicInformation {
     }
 
     fn get_index_for_hint(&self, player: &Player) -> usize {
-        let mut scores = self.hand_info[player].iter().enumerate().map(|(i, card_table)| {
+        let mut scores = self.beliefs.hand(*player).iter().enumerate().map(|(i, card_table)| {
             let score = self.get_hint_index_score(card_table);
             (-score, i)
         }).collect::<Vec<_>>();
@@ -330,9 +345,17 @@ impl MyPublicInformation {
                 2 => {
                     // Any value hint for a card other than the first
                     let mut hint_option_set = Vec::new();
-                    for card in hand {
-                        if card.value != hint_card.value {
-                            hint_option_set.push(Hinted::Value(card.value));
+                    if view.board.allow_empty_hints {
+                        for &value in VALUES.iter() {
+                            if value != hint_card.value {
+                                hint_option_set.push(Hinted::Value(value));
+                            }
+                        }
+                    } else {
+                        for card in hand {
+                            if card.value != hint_card.value {
+                                hint_option_set.push(Hinted::Value(card.value));
+                            }
                         }
                     }
                     hint_option_set
@@ -340,9 +363,17 @@ impl MyPublicInformation {
                 3 => {
                     // Any color hint for a card other than the first
                     let mut hint_option_set = Vec::new();
-                    for card in hand {
-                        if card.color != hint_card.color {
-                            hint_option_set.push(Hinted::Color(card.color));
+                    if view.board.allow_empty_hints {
+                        for &color in COLORS.iter() {
+                            if color != hint_card.color {
+                                hint_option_set.push(Hinted::Color(color));
+                            }
+                        }
+                    } else {
+                        for card in hand {
+                            if card.color != hint_card.color {
+                                hint_option_set.push(Hinted::Color(card.color));
+                            }
                         }
                     }
                     hint_option_set
@@ -410,13 +441,8 @@ impl MyPublicInformation {
         self.update_from_hat_sum(info, view);
     }
 
-    fn update_from_hint_matches(&mut self, hint: &Hint, matches: &Vec<bool>) {
-        let info = self.get_player_info_mut(&hint.player);
-        info.update_for_hint(&hint.hinted, matches);
-    }
-
     fn knows_playable_card(&self, player: &Player) -> bool {
-            self.hand_info[player].iter().any(|table| {
+            self.beliefs.hand(*player).iter().any(|table| {
                 table.probability_is_playable(&self.board) == 1.0
             })
     }
@@ -438,62 +464,27 @@ impl MyPublicInformation {
             if player != self.board.player && !self.knows_playable_card(&player) {
                 // If player doesn't know any playable cards, player doesn't have any playable
                 // cards.
-                let mut hand_info = self.take_player_info(&player);
-                for ref mut card_table in hand_info.iter_mut() {
+                let board = &self.board;
+                for card_table in self.beliefs.hand_mut(player).iter_mut() {
                     let possible = card_table.get_possibilities();
                     for card in &possible {
-                        if self.board.is_playable(card) {
+                        if board.is_playable(card) {
                             card_table.mark_false(card);
                         }
                     }
                 }
-                self.set_player_info(&player, hand_info);
-            }
-        }
-    }
-
-    fn update_from_discard_or_play_result(
-        &mut self,
-        new_view: &BorrowedGameView,
-        player: &Player,
-        index: usize,
-        card: &Card
-    ) {
-        let new_card_table = CardPossibilityTable::from(&self.card_counts);
-        {
-            let info = self.get_player_info_mut(player);
-            assert!(info[index].is_possible(card));
-            info.remove(index);
-
-            // push *before* incrementing public counts
-            if info.len() < new_view.hand_size(&player) {
-                info.push(new_card_table);
-            }
-        }
-
-        // TODO: decrement weight counts for fully determined cards, ahead of time
-
-        for player in self.board.get_players() {
-            let info = self.get_player_info_mut(&player);
-            for card_table in info.iter_mut() {
-                card_table.decrement_weight_if_possible(card);
             }
         }
-
-        self.card_counts.increment(card);
     }
 }
 
 impl PublicInformation for MyPublicInformation {
     fn new(board: &BoardState) -> Self {
-        let hand_info = board.get_players().map(|player| {
-            let hand_info = HandInfo::new(board.hand_size);
-            (player, hand_info)
-        }).collect::<FnvHashMap<_,_>>();
         MyPublicInformation {
-            hand_info: hand_info,
-            card_counts: CardCounts::new(),
+            beliefs: BeliefTracker::new(board),
             board: board.clone(),
+            params: InformationStrategyConfig::new(),
+            hat_stats: HatStats::new(),
         }
     }
 
@@ -502,17 +493,22 @@ impl PublicInformation for MyPublicInformation {
     }
 
     fn get_player_info(&self, player: &Player) -> HandInfo<CardPossibilityTable> {
-        self.hand_info[player].clone()
+        self.beliefs.hand(*player).clone()
     }
 
     fn set_player_info(&mut self, player: &Player, hand_info: HandInfo<CardPossibilityTable>) {
-        self.hand_info.insert(*player, hand_info);
+        *self.beliefs.hand_mut(*player) = hand_info;
     }
 
     fn agrees_with(&self, other: Self) -> bool {
         *self == other
     }
 
+    fn on_hat_sum(&mut self, total_info: u32, used_moduli: &[u32]) {
+        debug!("Hat sum modulo {}, with answers modulo {:?}", total_info, used_moduli);
+        self.hat_stats.record(total_info, used_moduli);
+    }
+
     fn ask_question(
         &self,
         _me: &Player,
@@ -537,6 +533,14 @@ impl PublicInformation for MyPublicInformation {
             else { true }
         }).collect::<Vec<_>>();
 
+        // In 3-player games, a hint can tell each player less, so it pays to keep looking for
+        // dead cards to discard even once one is known, and to ask about them first
+        let (ask_dead_when_known, ask_dead_first) = match self.params.question_policy {
+            QuestionPolicy::Standard => (false, false),
+            QuestionPolicy::ThreePlayer => (true, true),
+            QuestionPolicy::Auto => panic!("The question policy should be chosen when the strategy is initialized"),
+        };
+
         if !know_playable_card {
             // Vector of tuples (ask_dead, i, p_yes), where ask_dead=false means we'll
             // ask if the card at i is playable, and ask_dead=true means we ask if the card at i is
@@ -545,7 +549,7 @@ impl PublicInformation for MyPublicInformation {
                 if p_play == 0.0 { None }
                 else { Some((false, i, p_play)) }
             }).collect();
-            if !know_dead_card {
+            if !know_dead_card || ask_dead_when_known {
                 to_ask.extend(augmented_hand_info.iter().filter_map(|&(i, _, p_dead)| {
                     if p_dead == 0.0 { None }
                     else { Some((true, i, p_dead)) }
@@ -564,7 +568,7 @@ impl PublicInformation for MyPublicInformation {
             // better to put lower-probability-of-playability/death cards first: The difference
             // only matters if we find a playable/dead card, and conditional on that, it's better
             // to find out about as many non-playable/non-dead cards as possible.
-            to_ask.sort_by_key(|&(ask_dead, _, p_yes)| {(ask_dead, FloatOrd(p_yes))});
+            to_ask.sort_by_key(|&(ask_dead, _, p_yes)| {(ask_dead != ask_dead_first, FloatOrd(p_yes))});
             let questions = to_ask.into_iter().map(|(ask_dead, i, _)| -> Box<Question> {
                 if ask_dead { Box::new(q_is_dead(i)) }
                 else        { Box::new(q_is_playable(i)) }
@@ -574,9 +578,9 @@ impl PublicInformation for MyPublicInformation {
             }
         }
 
-        let ask_play_score = |p_play: f32| FloatOrd((p_play-0.7).abs());
+        let ask_play_score = |p_play: f32| FloatOrd((p_play - self.params.ask_play_target).abs());
         let mut ask_play = augmented_hand_info.iter().filter(|&&(_, p_play, _)| {
-            ask_play_score(p_play) < FloatOrd(0.2)
+            ask_play_score(p_play) < FloatOrd(self.params.ask_play_tolerance)
         }).cloned().collect::<Vec<_>>();
         ask_play.sort_by_key(|&(i, p_play, _)| (ask_play_score(p_play), i));
         if let Some(&(i, _, _)) = ask_play.get(0) {
@@ -599,35 +603,241 @@ impl PublicInformation for MyPublicInformation {
 
 
 
-pub struct InformationStrategyConfig;
+// How players choose the questions about their hands that hints answer
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum QuestionPolicy {
+    // whichever policy is tuned for the number of players
+    Auto,
+    // until a playable card is known, ask which card is playable, or else which is dead
+    Standard,
+    // like Standard, but ask about dead cards first, even when one is known to be dead
+    ThreePlayer,
+}
+impl QuestionPolicy {
+    fn for_players(num_players: u32) -> QuestionPolicy {
+        match num_players {
+            3 => QuestionPolicy::ThreePlayer,
+            _ => QuestionPolicy::Standard,
+        }
+    }
+}
+
+// How to choose a card to discard when every card might be useful
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum DiscardPolicy {
+    // the card whose discard loses the fewest points on average, breaking ties as in Heuristic
+    ExpectedLoss,
+    // a weighted sum of how likely the card is to be seen elsewhere, to be dispensable, and its value
+    Heuristic,
+}
+
+// Tunable parameters of the information strategy.
+// Every player must use the same parameters, since some of them affect public information.
+#[derive(Clone,Debug)]
+pub struct InformationStrategyConfig {
+    // make a risky play when the chance it's playable is above this (and it's otherwise dead)
+    pub risky_play_threshold: f32,
+    // only make risky plays with at least this many lives remaining
+    pub risky_play_min_lives: u32,
+    // ask whether a card is playable if the chance it's playable is within
+    // `ask_play_tolerance` of `ask_play_target`
+    pub ask_play_target: f32,
+    pub ask_play_tolerance: f32,
+    pub discard_policy: DiscardPolicy,
+    // when discarding, how much to prefer cards that are likely visible in another hand
+    pub discard_seen_weight: f32,
+    // when discarding, how much to prefer cards that are likely dispensable
+    pub discard_dispensable_weight: f32,
+    // how much better a hint is for each card it fully determines
+    pub hint_determined_bonus: f32,
+    // how much better a hint is for each card it reveals to be dead
+    pub hint_dead_bonus: f32,
+    // how much better a hint is for each card the hinted player will then know is playable,
+    // counting what they can see in the other hands
+    pub hint_private_playable_bonus: f32,
+    // hint rather than discard a useful card whenever more than this many hints remain
+    pub hint_above_hints_remaining: u32,
+    pub question_policy: QuestionPolicy,
+}
 
 impl InformationStrategyConfig {
     pub fn new() -> InformationStrategyConfig {
-        InformationStrategyConfig
+        InformationStrategyConfig {
+            risky_play_threshold: 0.75,
+            risky_play_min_lives: 2,
+            ask_play_target: 0.7,
+            ask_play_tolerance: 0.2,
+            discard_policy: DiscardPolicy::ExpectedLoss,
+            discard_seen_weight: 20.0,
+            discard_dispensable_weight: 10.0,
+            hint_determined_bonus: 2.0,
+            hint_dead_bonus: 2.0,
+            hint_private_playable_bonus: 2.0,
+            hint_above_hints_remaining: 4,
+            question_policy: QuestionPolicy::Auto,
+        }
     }
 }
 impl GameStrategyConfig for InformationStrategyConfig {
-    fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
-        Box::new(InformationStrategy::new())
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        self.supports(opts).unwrap_or_else(|err| panic!("{}", err));
+        let mut params = self.clone();
+        if params.question_policy == QuestionPolicy::Auto {
+            params.question_policy = QuestionPolicy::for_players(opts.num_players);
+        }
+        Box::new(InformationStrategy::new(params))
+    }
+
+    fn supports(&self, opts: &GameOptions) -> Result<(), String> {
+        // with fewer cards, a hint that avoids the hinted card may not exist
+        if opts.hand_size < 3 {
+            return Err(format!("The information strategy needs hands of at least 3 cards, not {}", opts.hand_size));
+        }
+        Ok(())
+    }
+
+    fn version(&self) -> u32 {
+        5
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "risky_play_threshold"       => parse_param(name, value, &mut self.risky_play_threshold),
+            "risky_play_min_lives"       => parse_param(name, value, &mut self.risky_play_min_lives),
+            "ask_play_target"            => parse_param(name, value, &mut self.ask_play_target),
+            "ask_play_tolerance"         => parse_param(name, value, &mut self.ask_play_tolerance),
+            "discard_policy" => {
+                self.discard_policy = match value {
+                    "expected-loss" => DiscardPolicy::ExpectedLoss,
+                    "heuristic" => DiscardPolicy::Heuristic,
+                    _ => return Err(format!("Invalid value for parameter {}: {}", name, value)),
+                };
+                Ok(())
+            }
+            "discard_seen_weight"        => parse_param(name, value, &mut self.discard_seen_weight),
+            "discard_dispensable_weight" => parse_param(name, value, &mut self.discard_dispensable_weight),
+            "hint_determined_bonus"      => parse_param(name, value, &mut self.hint_determined_bonus),
+            "hint_dead_bonus"            => parse_param(name, value, &mut self.hint_dead_bonus),
+            "hint_private_playable_bonus" => parse_param(name, value, &mut self.hint_private_playable_bonus),
+            "hint_above_hints_remaining" => parse_param(name, value, &mut self.hint_above_hints_remaining),
+            "question_policy" => {
+                self.question_policy = match value {
+                    "auto" => QuestionPolicy::Auto,
+                    "standard" => QuestionPolicy::Standard,
+                    "three-player" => QuestionPolicy::ThreePlayer,
+                    _ => return Err(format!("Invalid value for parameter {}: {}", name, value)),
+                };
+                Ok(())
+            }
+            _ => Err(format!("Unknown parameter for the information strategy: {}", name)),
+        }
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        let questions = match self.question_policy {
+            QuestionPolicy::Auto => String::from("Until a card is known to be playable, ask of as many \
+                                                  cards as fit whether they are playable (or dead, if none \
+                                                  is known to be dead).  With 3 players, ask about dead \
+                                                  cards first, even once one is known to be dead."),
+            QuestionPolicy::Standard => String::from("Until a card is known to be playable, ask of as many \
+                                                      cards as fit whether they are playable, then, if no \
+                                                      card is known to be dead, whether they are dead."),
+            QuestionPolicy::ThreePlayer => String::from("Until a card is known to be playable, ask of as many \
+                                                         cards as fit whether they are dead, even once one is \
+                                                         known to be dead, then whether they are playable."),
+        };
+        Some(Conventions::new("info", "A hat-guessing strategy.  Each hint answers, for every other player \
+                                       at once, a question about their hand whose answers everyone agrees \
+                                       on, by encoding the sum of the answers in the choice of hint.")
+             .section("Choosing a move", vec![
+                 String::from("If several of my cards are publicly known to be playable, play one, choosing \
+                               which to encode the sum of answers about the other hands."),
+                 String::from("Play a card I know to be playable, preferring the one that unlocks the most."),
+                 format!("With at least {} lives left, and while discards can't yet cost the last round, \
+                          play a card that is either playable or dead, if it is playable with probability \
+                          above {}.", self.risky_play_min_lives, self.risky_play_threshold),
+                 String::from("Hint, if there are hints and someone else needs one: they have a playable \
+                               card, but don't know of one."),
+                 String::from("While discards can't yet cost the last round, discard a card I know to be dead."),
+                 format!("Hint, if there are hints and someone else can play, or if more than {} hints \
+                          are left.", self.hint_above_hints_remaining),
+                 String::from("Discard a card I know to be dead.  If several are publicly known to be dead, \
+                               the choice between them encodes answers, like a play."),
+                 match self.discard_policy {
+                     DiscardPolicy::ExpectedLoss => String::from("Discard the card that loses the fewest \
+                                                                  points on average, if it is the last copy of \
+                                                                  a card still needed, breaking ties as below."),
+                     DiscardPolicy::Heuristic => String::from("Discard the card that scores best as below."),
+                 },
+                 format!("Score cards by how likely they are to be visible in another hand (weight {}) or \
+                          dispensable (weight {}), plus their average value.",
+                         self.discard_seen_weight, self.discard_dispensable_weight),
+             ])
+             .section("Questions each hint answers", vec![
+                 questions,
+                 format!("Ask whether a card is playable when the chance it is lies within {} of {}.",
+                         self.ask_play_tolerance, self.ask_play_target),
+                 String::from("Otherwise, ask which of a partition of its possibilities the card least \
+                               likely to be dead is in."),
+             ])
+             .section("Choosing a hint", vec![
+                 String::from("Several hints may encode the same sum, so choose the one that tells the \
+                               hinted player the most about their hand."),
+                 format!("Count each card the hint fully determines as {} more, and each card it \
+                          reveals to be dead as {} more.", self.hint_determined_bonus, self.hint_dead_bonus),
+                 format!("Count each card the hinted player will then know is playable, from the hint and \
+                          the copies they can see in the other hands (other than mine), as {} more.",
+                         self.hint_private_playable_bonus),
+             ])
+             .param("risky_play_threshold", self.risky_play_threshold)
+             .param("risky_play_min_lives", self.risky_play_min_lives)
+             .param("ask_play_target", self.ask_play_target)
+             .param("ask_play_tolerance", self.ask_play_tolerance)
+             .param("discard_policy", match self.discard_policy {
+                 DiscardPolicy::ExpectedLoss => "expected-loss",
+                 DiscardPolicy::Heuristic => "heuristic",
+             })
+             .param("discard_seen_weight", self.discard_seen_weight)
+             .param("discard_dispensable_weight", self.discard_dispensable_weight)
+             .param("hint_determined_bonus", self.hint_determined_bonus)
+             .param("hint_dead_bonus", self.hint_dead_bonus)
+             .param("hint_private_playable_bonus", self.hint_private_playable_bonus)
+             .param("hint_above_hints_remaining", self.hint_above_hints_remaining)
+             .param("question_policy", match self.question_policy {
+                 QuestionPolicy::Auto => "auto",
+                 QuestionPolicy::Standard => "standard",
+                 QuestionPolicy::ThreePlayer => "three-player",
+             }))
     }
 }
 
-pub struct InformationStrategy;
+pub struct InformationStrategy {
+    params: InformationStrategyConfig,
+}
 
 impl InformationStrategy {
-    pub fn new() -> InformationStrategy {
-        InformationStrategy
+    pub fn new(params: InformationStrategyConfig) -> InformationStrategy {
+        InformationStrategy {
+            params: params,
+        }
     }
 }
 impl GameStrategy for InformationStrategy {
-    fn initialize(&self, player: Player, view: &BorrowedGameView) -> Box<PlayerStrategy> {
+    fn initialize(&self, context: &InitContext, _: StrategyRng) -> Box<PlayerStrategy> {
+        let mut public_info = MyPublicInformation::new(context.view.board);
+        public_info.params = self.params.clone();
         Box::new(InformationPlayerStrategy {
-            me: player,
-            public_info: MyPublicInformation::new(view.board),
+            me: context.player,
+            public_info: public_info,
             new_public_info: None,
-            last_view: OwnedGameView::clone_from(view),
+            last_view: OwnedGameView::clone_from(context.view),
+            params: self.params.clone(),
         })
     }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("info")
+    }
 }
 
 pub struct InformationPlayerStrategy {
@@ -635,8 +845,10 @@ pub struct InformationPlayerStrategy {
     public_info: MyPublicInformation,
     // Inside decide(), modify a copy of public_info and put it here. After that, when
     // calling update, check that the updated public_info matches new_public_info.
-    new_public_info: Option<MyPublicInformation>,
+    // (Unless a different choice was actually made, e.g. when replaying someone else's game.)
+    new_public_info: Option<(TurnChoice, MyPublicInformation)>,
     last_view: OwnedGameView, // the view on the previous turn
+    params: InformationStrategyConfig,
 }
 
 impl InformationPlayerStrategy {
@@ -684,6 +896,12 @@ impl InformationPlayerStrategy {
         return useless_vec;
     }
 
+    fn find_playable_cards(&self, board: &BoardState, hand: &HandInfo<CardPossibilityTable>) -> Vec<usize> {
+        hand.iter().enumerate().filter(|&(_, card_table)| {
+            card_table.probability_is_playable(board) == 1.0
+        }).map(|(i, _)| i).collect()
+    }
+
     // how good is it to give this hint to this player?
     fn hint_goodness(&self, hint: &Hint, view: &OwnedGameView) -> f32 {
         // This gets called after self.public_info.get_hint(), which modifies the public
@@ -705,28 +923,52 @@ impl InformationPlayerStrategy {
                 continue;
             }
             let old_weight = card_table.total_weight();
-            match *hinted {
-                Hinted::Color(color) => {
-                    card_table.mark_color(color, color == card.color)
-                }
-                Hinted::Value(value) => {
-                    card_table.mark_value(value, value == card.value)
-                }
-            };
+            card_table.mark_hint(hinted, hinted.matches(card));
             let new_weight = card_table.total_weight();
             assert!(new_weight <= old_weight);
             let bonus = {
                 if card_table.is_determined() {
-                    2
+                    self.params.hint_determined_bonus
                 } else if card_table.probability_is_dead(&view.board) == 1.0 {
-                    2
+                    self.params.hint_dead_bonus
                 } else {
-                    1
+                    1.0
                 }
             };
-            goodness *= (bonus as f32) * (old_weight / new_weight);
+            goodness *= bonus * (old_weight / new_weight);
+        }
+        goodness * self.private_playable_bonus(hint, view)
+    }
+
+    // What `player` knows about their own hand, as far as we can tell: the public information,
+    // less the copies they can see in the hands we both see.  They also see our hand, so may know more
+    fn private_info_of(&self, player: &Player, view: &OwnedGameView) -> HandInfo<CardPossibilityTable> {
+        let mut hand_info = self.public_info.get_player_info(player);
+        for (other, hand) in view.other_hands.iter() {
+            if other == player {
+                continue;
+            }
+            for card in hand.iter() {
+                for card_table in hand_info.iter_mut() {
+                    card_table.decrement_weight_if_possible(card);
+                }
+            }
         }
-        goodness
+        hand_info
+    }
+
+    // The bonus for the cards a hint lets the hinted player work out are playable, with what
+    // they know privately, that they couldn't before
+    fn private_playable_bonus(&self, hint: &Hint, view: &OwnedGameView) -> f32 {
+        let hand = view.get_hand(&hint.player);
+        let before = self.private_info_of(&hint.player, view);
+        let mut after = before.clone();
+        let matches = hand.iter().map(|card| hint.hinted.matches(card)).collect();
+        after.update_for_hint(&hint.hinted, &matches);
+        let newly_playable = before.iter().zip(after.iter()).filter(|&(before, after)| {
+            before.probability_is_playable(&view.board) < 1.0 && after.probability_is_playable(&view.board) == 1.0
+        }).count();
+        self.params.hint_private_playable_bonus.powi(newly_playable as i32)
     }
 
     fn get_best_hint_of_options(&self, mut hints: Vec<Hint>) -> Hint {
@@ -766,10 +1008,7 @@ impl InformationPlayerStrategy {
 
         for player in view.board.get_players() {
            let hand_info = public_info.get_player_info(&player);
-            debug!("Current state of hand_info for {}:", player);
-            for (i, card_table) in hand_info.iter().enumerate() {
-                debug!("  Card {}: {}", i, card_table);
-            }
+            debug!("Current state of hand_info for {}:\n{}", player, render::hand_grid(&hand_info, render::RenderStyle::Plain));
         }
 
         let private_info = public_info.get_private_info(view);
@@ -778,6 +1017,15 @@ impl InformationPlayerStrategy {
         //     debug!("{}: {}", i, card_table);
         // }
 
+        // If several cards are publicly known to be playable, everyone knows we will play one of
+        // them, so use the choice between them to transmit information
+        // NOTE When changing this, make sure to keep the "play" branch of update() up to date!
+        let public_playable_indices = self.find_playable_cards(&view.board, &public_info.get_player_info(me));
+        if public_playable_indices.len() > 1 {
+            let info = public_info.get_hat_sum(public_playable_indices.len() as u32, view);
+            return TurnChoice::Play(public_playable_indices[info.value as usize]);
+        }
+
         // If possible, play the best playable card
         // the higher the play_score, the better to play
         let mut playable_cards = private_info.iter().enumerate().filter_map(|(i, card_table)| {
@@ -789,14 +1037,11 @@ impl InformationPlayerStrategy {
             return TurnChoice::Play(play_index)
         }
 
-        let discard_threshold =
-            view.board.total_cards
-            - (COLORS.len() * VALUES.len()) as u32
-            - (view.board.num_players * view.board.hand_size);
+        let discard_threshold = view.board.spare_discards();
 
         // make a possibly risky play
         // TODO: consider removing this, if we improve information transfer
-        if view.board.lives_remaining > 1 &&
+        if view.board.lives_remaining >= self.params.risky_play_min_lives &&
            view.board.discard_size() <= discard_threshold
         {
             let mut risky_playable_cards = private_info.iter().enumerate().filter(|&(_, card_table)| {
@@ -815,7 +1060,7 @@ impl InformationPlayerStrategy {
                 });
 
                 let maybe_play = risky_playable_cards[0];
-                if maybe_play.2 > 0.75 {
+                if maybe_play.2 > self.params.risky_play_threshold {
                     return TurnChoice::Play(maybe_play.0);
                 }
             }
@@ -831,7 +1076,7 @@ impl InformationPlayerStrategy {
             // hinting is better than discarding dead cards
             // (probably because it stalls the deck-drawing).
             else if view.board.hints_remaining > 0 && view.someone_else_can_play() { true }
-            else if view.board.hints_remaining > 4 { true }
+            else if view.board.hints_remaining > self.params.hint_above_hints_remaining { true }
             // this is the only case in which we discard a potentially useful card.
             else { false };
 
@@ -861,13 +1106,22 @@ impl InformationPlayerStrategy {
                 view.can_see(card)
             });
             let compval =
-                20.0 * probability_is_seen
-                + 10.0 * card_table.probability_is_dispensable(&view.board)
+                self.params.discard_seen_weight * probability_is_seen
+                + self.params.discard_dispensable_weight * card_table.probability_is_dispensable(&view.board)
                 + card_table.average_value();
-            (i, compval)
+            // the points we expect to lose, which the heuristic only approximates
+            let expected_loss = match self.params.discard_policy {
+                DiscardPolicy::ExpectedLoss => {
+                    card_table.weighted_score(&|card| view.board.score_lost_by_discarding(card) as f32)
+                }
+                DiscardPolicy::Heuristic => 0.0,
+            };
+            (i, expected_loss, compval)
         }).collect::<Vec<_>>();
-        cards_by_discard_value.sort_by_key(|&(i, compval)| (FloatOrd(-compval), i));
-        let (index, _) = cards_by_discard_value[0];
+        cards_by_discard_value.sort_by_key(|&(i, expected_loss, compval)| {
+            (FloatOrd(expected_loss), FloatOrd(-compval), i)
+        });
+        let (index, _, _) = cards_by_discard_value[0];
         TurnChoice::Discard(index)
     }
 
@@ -905,8 +1159,17 @@ impl InformationPlayerStrategy {
                     self.public_info.update_from_hat_sum(info, &self.last_view);
                 }
             }
-            TurnChoice::Play(_index) => {
-                // TODO: Maybe we can transfer information through plays as well?
+            TurnChoice::Play(index) => {
+                let known_playable_indices = self.find_playable_cards(
+                    &self.last_view.board, &self.public_info.get_player_info(turn_player)
+                );
+
+                if known_playable_indices.len() > 1 {
+                    // if several cards were known to be playable, the choice between them was a hat sum
+                    let value = known_playable_indices.iter().position(|&i| i == *index).unwrap();
+                    let info = ModulusInformation::new(known_playable_indices.len() as u32, value as u32);
+                    self.public_info.update_from_hat_sum(info, &self.last_view);
+                }
             }
         }
     }
@@ -916,7 +1179,9 @@ impl PlayerStrategy for InformationPlayerStrategy {
     fn decide(&mut self, _: &BorrowedGameView) -> TurnChoice {
         let mut public_info = self.public_info.clone();
         let turn_choice = self.decide_wrapped(&mut public_info);
-        self.new_public_info = Some(public_info);
+        // update() redoes the rest, but only decide() makes hat sums
+        self.public_info.hat_stats = public_info.hat_stats.clone();
+        self.new_public_info = Some((turn_choice.clone(), public_info));
         turn_choice
     }
 
@@ -925,39 +1190,93 @@ impl PlayerStrategy for InformationPlayerStrategy {
             Some(matches)
         } else { None };
         self.update_wrapped(&turn_record.player, &turn_record.choice, hint_matches);
-        if let Some(new_public_info) = self.new_public_info.take() {
-            if !self.public_info.agrees_with(new_public_info) {
+        if let Some((choice, new_public_info)) = self.new_public_info.take() {
+            if choice == turn_record.choice && !self.public_info.agrees_with(new_public_info) {
                 panic!("The change made to public_info in self.decide_wrapped differs from \
                         the corresponding change in self.update_wrapped!");
             }
         }
-        match turn_record.choice {
-            TurnChoice::Hint(ref hint) =>  {
-                if let &TurnResult::Hint(ref matches) = &turn_record.result {
-                    self.public_info.update_from_hint_matches(hint, matches);
-                } else {
-                    panic!("Got turn choice {:?}, but turn result {:?}",
-                           turn_record.choice, turn_record.result);
+        self.public_info.beliefs.update(turn_record, view);
+        self.last_view = OwnedGameView::clone_from(view);
+        self.public_info.set_board(view.board);
+    }
+
+    fn public_info_digest(&self) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        self.public_info.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    fn inspect(&self) -> Option<Json> {
+        let board = &self.public_info.board;
+        let players = board.get_players().map(|player| {
+            let hand_info = self.public_info.get_player_info(&player);
+            // each card's possibilities, with how many copies of each could be it
+            let cards = hand_info.iter().map(|table| {
+                let possibilities = table.iter_possibilities().map(|(card, weight)| {
+                    format!("{}x{}", card, weight)
+                }).collect::<Vec<_>>();
+                possibilities.join(" ")
+            }).collect::<Vec<_>>();
+            Json::object()
+                .with("player", player)
+                .with("public_cards", cards)
+                .with("known_playable", self.find_playable_cards(board, &hand_info))
+                .with("known_useless", self.find_useless_cards(board, &hand_info))
+        }).collect::<Vec<_>>();
+        Some(Json::object()
+             .with("me", self.me)
+             .with("question_policy", format!("{:?}", self.params.question_policy))
+             .with("players", players)
+             // from the hands only this player can see
+             .with("private", Json::object()
+                   .with("someone_else_needs_hint", self.public_info.someone_else_needs_hint(&self.last_view))))
+    }
+
+    fn public_card_probabilities(&self, player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        Some(self.public_info.get_player_info(&player).iter().map(|table| {
+            let total = table.iter_possibilities().map(|(_, weight)| weight).sum::<u32>() as f32;
+            table.iter_possibilities().map(|(card, weight)| (card, weight as f32 / total)).collect()
+        }).collect())
+    }
+
+    fn own_hand_knowledge(&self, view: &BorrowedGameView) -> Option<HandInfo<CardPossibilityTable>> {
+        Some(self.public_info.get_private_info(&OwnedGameView::clone_from(view)))
+    }
+
+    // what everyone knows about each card: whether it's playable or useless, and what it could
+    // be once it's down to a few possibilities
+    fn notes(&self) -> Vec<CardNote> {
+        let board = &self.public_info.board;
+        board.get_players().flat_map(|player| {
+            let hand_info = self.public_info.get_player_info(&player);
+            let playable = self.find_playable_cards(board, &hand_info);
+            let useless = self.find_useless_cards(board, &hand_info);
+            board.card_ids(&player).iter().zip(hand_info.iter()).enumerate().map(|(i, (&card_id, table))| {
+                let mut flags = Vec::new();
+                if playable.contains(&i) {
+                    flags.push(NoteFlag::Play);
                 }
-            }
-            TurnChoice::Discard(index) => {
-                if let &TurnResult::Discard(ref card) = &turn_record.result {
-                    self.public_info.update_from_discard_or_play_result(view, &turn_record.player, index, card);
-                } else {
-                    panic!("Got turn choice {:?}, but turn result {:?}",
-                           turn_record.choice, turn_record.result);
+                if useless.contains(&i) {
+                    flags.push(NoteFlag::Trash);
                 }
-            }
-            TurnChoice::Play(index) =>  {
-                if let &TurnResult::Play(ref card, _) = &turn_record.result {
-                    self.public_info.update_from_discard_or_play_result(view, &turn_record.player, index, card);
+                let possibilities = table.get_possibilities();
+                let text = if possibilities.len() <= 4 {
+                    possibilities.iter().map(|card| card.to_string()).collect::<Vec<_>>().join(" ")
                 } else {
-                    panic!("Got turn choice {:?}, but turn result {:?}",
-                           turn_record.choice, turn_record.result);
-                }
-            }
-        }
-        self.last_view = OwnedGameView::clone_from(view);
-        self.public_info.set_board(view.board);
+                    String::new()
+                };
+                CardNote { card_id: card_id, text: text, flags: flags }
+            }).collect::<Vec<_>>()
+        }).collect()
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        let stats = &self.public_info.hat_stats;
+        vec![
+            ("hat sums", stats.sums as f64),
+            ("hat bits", stats.bits),
+            ("hat bits wasted", stats.wasted_bits),
+        ]
     }
 }
@@ -0,0 +1,285 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use strategy::*;
+use game::*;
+use helpers::*;
+use json::Json;
+use strategies::cheating::card_play_value;
+
+// The cheating strategy's heuristics, without the cheating: each player sees the other hands,
+// as in a real game, but neither its own cards nor the deck.  What it knows about its own cards
+// comes from hints, and from the copies it can see in other hands.
+// Hints mean nothing beyond the cards they touch, so this is a reference point for how far plain
+// deduction goes.  It is no upper bound on real play: the strategies with conventions say far more
+// with each hint, and score well above it (it averages around 14.5).
+//
+// Plays according to the following rules:
+//  - if any card is known to be playable,
+//      play the one with the lowest value
+//  - if a card is known to be dead, discard it
+//  - if a hint tells someone a card is playable, or keeps them from discarding
+//      a useful card, hint
+//  - if hints are full, hint
+//  - discard the card that loses the fewest points on average
+
+pub struct SemiCheatingStrategyConfig;
+
+impl SemiCheatingStrategyConfig {
+    pub fn new() -> SemiCheatingStrategyConfig {
+        SemiCheatingStrategyConfig
+    }
+}
+impl GameStrategyConfig for SemiCheatingStrategyConfig {
+    fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
+        Box::new(SemiCheatingStrategy)
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        Some(Conventions::new("semi-cheat", "Every player sees the other hands, but not their own cards or \
+                                             the deck.  Hints mean only what they say, so players only play \
+                                             cards they know are playable.  A reference point for \
+                                             how far deduction goes without conventions.")
+             .section("What a player knows about their own cards", vec![
+                 String::from("Each card is one of the cards it could still be after every hint it was \
+                               touched by or missed, weighted by how many copies are neither played, \
+                               discarded, nor in another hand."),
+             ])
+             .section("Choosing a move", vec![
+                 String::from("Play a card known to be playable, leaving a card someone else is known to \
+                               hold to them if they have less to play, and otherwise preferring lower values."),
+                 String::from("While discards can't yet cost the last round, discard a card known to be dead, \
+                               or known to be the same as an older card in the hand."),
+                 String::from("If there are hints left, give the hint that tells someone the most cards are \
+                               playable, then the one that saves the most points by changing the card they \
+                               would discard (judging by what they know, less the copies in our own hand), \
+                               breaking ties by the most cards it identifies, then the nearest player to the \
+                               left.  Only hint if that does some good, or if hints are full."),
+                 String::from("Discard a card known to be dead or a duplicate."),
+                 String::from("Discard the card that loses the fewest points on average, then the one with \
+                               the highest value on average, then the oldest."),
+             ]))
+    }
+}
+
+pub struct SemiCheatingStrategy;
+
+impl GameStrategy for SemiCheatingStrategy {
+    fn initialize(&self, context: &InitContext, _: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(SemiCheatingPlayerStrategy {
+            me: context.player,
+            num_players: context.opts.num_players,
+            public: BeliefTracker::new(context.view.board),
+        })
+    }
+
+    fn seat_name(&self, _: Player) -> String {
+        String::from("semi-cheat")
+    }
+}
+
+fn surely(table: &CardPossibilityTable, predicate: &Fn(&Card) -> bool) -> bool {
+    table.iter_possibilities().all(|(card, _)| predicate(&card))
+}
+
+// When all cards are plausibly useful, the one to discard: the one we expect to lose the least by,
+// then the highest, then the oldest
+fn discard_choice(board: &BoardState, hand_info: &HandInfo<CardPossibilityTable>) -> usize {
+    let mut index = 0;
+    let mut compval = None;
+    for (i, table) in hand_info.iter().enumerate() {
+        let expected_loss = table.weighted_score(&|card| board.score_lost_by_discarding(card) as f32);
+        let my_compval = (expected_loss, -table.average_value());
+        if compval.map_or(true, |compval| my_compval < compval) {
+            index = i;
+            compval = Some(my_compval);
+        }
+    }
+    index
+}
+
+pub struct SemiCheatingPlayerStrategy {
+    me: Player,
+    num_players: u32,
+    public: BeliefTracker,
+}
+impl SemiCheatingPlayerStrategy {
+    // What `player` knows about their own cards, as far as we can tell: what everyone knows,
+    // less the copies they can see.  For another player, we can't count the copies in our hand,
+    // so they may know more.
+    fn hand_info_seen_by(&self, view: &BorrowedGameView, player: Player) -> HandInfo<CardPossibilityTable> {
+        let mut hand_info = self.public.hand(player).clone();
+        for other in view.get_other_players() {
+            if other == player {
+                continue;
+            }
+            for card in view.get_hand(&other).iter() {
+                for table in hand_info.iter_mut() {
+                    table.decrement_weight_if_possible(card);
+                }
+            }
+        }
+        hand_info
+    }
+
+    // how badly do we need to play a card we know is playable
+    fn get_play_score(&self, view: &BorrowedGameView, hand_info: &HandInfo<CardPossibilityTable>,
+                      table: &CardPossibilityTable) -> f32 {
+        let board = view.board;
+        let value = table.average_value();
+        if let Some(card) = table.get_card() {
+            let my_hand_value = hand_info.iter().map(|table| {
+                table.weighted_score(&|card| card_play_value(board, card) as f32)
+            }).fold(0.0, |a, b| a + b);
+            for player in view.get_other_players() {
+                if view.has_card(&player, &card) {
+                    let their_hand_value = view.get_hand(&player).iter().map(|card| {
+                        card_play_value(board, card)
+                    }).fold(0, |a, b| a + b);
+                    // they can play this card, and have less urgent plays than i do
+                    if (their_hand_value as f32) < my_hand_value {
+                        return 10.0 - value;
+                    }
+                }
+            }
+        }
+        20.0 - value
+    }
+
+    fn find_useless_card(&self, view: &BorrowedGameView, hand_info: &HandInfo<CardPossibilityTable>) -> Option<usize> {
+        let mut known = Vec::new();
+        for (i, table) in hand_info.iter().enumerate() {
+            if surely(table, &|card| view.board.is_dead(card)) {
+                return Some(i);
+            }
+            if let Some(card) = table.get_card() {
+                if known.contains(&card) {
+                    return Some(i);
+                }
+                known.push(card);
+            }
+        }
+        None
+    }
+
+    // the most useful hint, and how useful it is: how many cards it shows are playable, and how
+    // many points it saves by changing which card its receiver would discard.  Ties go to hints
+    // that identify the most cards
+    fn best_hint(&self, view: &BorrowedGameView) -> Option<((usize, Score), Hint)> {
+        let board = view.board;
+        let num_players = board.num_players;
+        let mut best: Option<((usize, Score, usize, i32), Hint)> = None;
+        for choice in board.legal_choices(view) {
+            let hint = match choice {
+                TurnChoice::Hint(hint) => hint,
+                _ => continue,
+            };
+            let hand = view.get_hand(&hint.player);
+            let matches = hand.iter().map(|card| hint.hinted.matches(card)).collect();
+            let before = self.hand_info_seen_by(view, hint.player);
+            let mut after = before.clone();
+            after.update_for_hint(&hint.hinted, &matches);
+            let is_playable = |card: &Card| board.is_playable(card);
+            let newly_playable = before.iter().zip(after.iter()).filter(|&(before, after)| {
+                !surely(before, &is_playable) && surely(after, &is_playable)
+            }).count();
+            let newly_determined = before.iter().zip(after.iter()).filter(|&(before, after)| {
+                !before.is_determined() && after.is_determined()
+            }).count();
+            let discard_loss = |hand_info: &HandInfo<CardPossibilityTable>| board.score_lost_by_discarding(&hand[discard_choice(board, hand_info)]);
+            let points_saved = discard_loss(&before).saturating_sub(discard_loss(&after));
+            let distance = ((hint.player + num_players - self.me) % num_players) as i32;
+            let key = (newly_playable, points_saved, newly_determined, -distance);
+            if best.as_ref().map_or(true, |&(best_key, _)| key > best_key) {
+                best = Some((key, hint));
+            }
+        }
+        best.map(|((newly_playable, points_saved, _, _), hint)| ((newly_playable, points_saved), hint))
+    }
+}
+impl PlayerStrategy for SemiCheatingPlayerStrategy {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        let board = view.board;
+        let hand_info = self.hand_info_seen_by(view, self.me);
+
+        // play the best card we know is playable
+        // the higher the play_score, the better to play
+        let mut play: Option<(usize, f32)> = None;
+        for (i, table) in hand_info.iter().enumerate() {
+            if !surely(table, &|card| board.is_playable(card)) {
+                continue;
+            }
+            let score = self.get_play_score(view, &hand_info, table);
+            if play.map_or(true, |(_, play_score)| score > play_score) {
+                play = Some((i, score));
+            }
+        }
+        if let Some((index, _)) = play {
+            return TurnChoice::Play(index);
+        }
+
+        if board.discard_size() <= board.spare_discards() {
+            if let Some(i) = self.find_useless_card(view, &hand_info) {
+                return TurnChoice::Discard(i);
+            }
+        }
+
+        if let Some(((newly_playable, points_saved), hint)) = self.best_hint(view) {
+            // waiting on a hint nobody needs is worse than discarding, unless there's nothing left to discard
+            if newly_playable > 0 || points_saved > 0 || board.hints_remaining == board.hints_total ||
+                    hand_info.is_empty() {
+                return TurnChoice::Hint(hint);
+            }
+        }
+
+        if let Some(i) = self.find_useless_card(view, &hand_info) {
+            return TurnChoice::Discard(i);
+        }
+
+        // All cards are plausibly useful.
+        TurnChoice::Discard(discard_choice(board, &hand_info))
+    }
+
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.public.update(turn, view);
+    }
+
+    fn public_info_digest(&self) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        self.public.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    fn public_card_probabilities(&self, player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        Some(self.public.hand(player).iter().map(|table| {
+            let total = table.iter_possibilities().map(|(_, weight)| weight).sum::<u32>() as f32;
+            table.iter_possibilities().map(|(card, weight)| (card, weight as f32 / total)).collect()
+        }).collect())
+    }
+
+    fn own_hand_knowledge(&self, view: &BorrowedGameView) -> Option<HandInfo<CardPossibilityTable>> {
+        Some(self.hand_info_seen_by(view, self.me))
+    }
+
+    fn inspect(&self) -> Option<Json> {
+        let players = (0..self.num_players).map(|player| {
+            // each card's possibilities, with how many copies of each could be it
+            let cards = self.public.hand(player).iter().map(|table| {
+                let possibilities = table.iter_possibilities().map(|(card, weight)| {
+                    format!("{}x{}", card, weight)
+                }).collect::<Vec<_>>();
+                possibilities.join(" ")
+            }).collect::<Vec<_>>();
+            Json::object()
+                .with("player", player)
+                .with("public_cards", cards)
+        }).collect::<Vec<_>>();
+        Some(Json::object()
+             .with("me", self.me)
+             .with("players", players))
+    }
+}
@@ -1,4 +1,11 @@
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use rand::ChaChaRng;
+
 use game::*;
+use helpers::{CardPossibilityTable, HandInfo};
+use json::Json;
 
 // Traits to implement for any valid Hanabi strategy
 
@@ -8,19 +15,246 @@ pub trait PlayerStrategy {
     // Given a BorrowedGameView, outputs their choice.
     fn decide(&mut self, &BorrowedGameView) -> TurnChoice;
     // A function to update internal state after other players' turns.
-    // Given what happened last turn, and the new state.
+    // Given what happened last turn, and the new state.  Only the last turn is passed in;
+    // strategies that need earlier ones can look them up in the view's `history`.
     fn update(&mut self, &TurnRecord, &BorrowedGameView);
+    // A function to rebuild internal state when joining a game already in progress.
+    // Called for each turn of the game so far, in order, instead of decide/update.
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.update(turn, view);
+    }
+    // A hash of the state this player believes is common knowledge, if the strategy keeps any.
+    // After every update, it should be the same for all players.
+    fn public_info_digest(&self) -> Option<u64> {
+        None
+    }
+    // For each card in the given player's hand, how likely each card is according to what
+    // this player believes is common knowledge, if the strategy keeps track of it.
+    fn public_card_probabilities(&self, _player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        None
+    }
+    // What this player knows about each card in their own hand, public and private, if the
+    // strategy keeps track of it.  Lets a search (see search.rs) sample hands they could have.
+    fn own_hand_knowledge(&self, _view: &BorrowedGameView) -> Option<HandInfo<CardPossibilityTable>> {
+        None
+    }
+    // Everything this player knows or believes that could help debug its conventions,
+    // e.g. what it thinks is common knowledge and what it has deduced privately.
+    // Shown by the debugger (see debugger.rs); the format is up to the strategy.
+    fn inspect(&self) -> Option<Json> {
+        None
+    }
+    // Whether to give up at the start of this player's turn, ending the game with its current
+    // score, e.g. when a search finds no way to score more.  Saves the time that playing out a
+    // lost game would take; since the score can only go up, giving up never helps a strategy.
+    fn concede(&mut self, _view: &BorrowedGameView) -> bool {
+        false
+    }
+    // Totals of anything the strategy measures about how it played, by name, e.g. how much
+    // information its hints carried.  Collected at the end of the game and summed over the
+    // players into GameState::strategy_metrics.
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        Vec::new()
+    }
+    // What this player would write on cards, in any hand including its own, as players do on
+    // hanab.live.  Shown by the debugger and --watch, and exported with --json-output.
+    fn notes(&self) -> Vec<CardNote> {
+        Vec::new()
+    }
+}
+
+// What a note says a card is for, whatever else it says
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum NoteFlag {
+    // known to be playable now
+    Play,
+    // known to be useless
+    Trash,
+    // the next card the holder will discard
+    Chop,
+}
+impl fmt::Display for NoteFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            NoteFlag::Play => "play",
+            NoteFlag::Trash => "trash",
+            NoteFlag::Chop => "chop",
+        })
+    }
+}
+
+// A strategy's note on one card, e.g. the cards it could be
+#[derive(Debug,Clone,PartialEq)]
+pub struct CardNote {
+    pub card_id: CardId,
+    pub text: String,
+    pub flags: Vec<NoteFlag>,
+}
+// the flags in brackets, then the text, e.g. "[play] r1 y1"
+impl fmt::Display for CardNote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = self.flags.iter().map(|flag| flag.to_string()).collect::<Vec<_>>();
+        match (flags.is_empty(), self.text.is_empty()) {
+            (true, _) => write!(f, "{}", self.text),
+            (false, true) => write!(f, "[{}]", flags.join(", ")),
+            (false, false) => write!(f, "[{}] {}", flags.join(", "), self.text),
+        }
+    }
 }
+// Source of randomness handed to each player.
+// It is seeded from the game seed and the player's index, so games are reproducible.
+pub type StrategyRng = ChaChaRng;
+
+// What a player's strategy is told at the start of a game
+pub struct InitContext<'a> {
+    pub player: Player,
+    pub opts: &'a GameOptions,
+    pub view: &'a BorrowedGameView<'a>,
+    // the name of the strategy in each seat, by player (including this one), so conventions
+    // can adapt to who they are playing with
+    pub partner_names: Vec<String>,
+    // the cards still to be drawn, last drawn first as in GameState::deck, where the game knows
+    // them.  Only for strategies that cheat by looking at the deck, like cheat-search
+    pub deck: Option<&'a Cards>,
+}
+impl<'a> InitContext<'a> {
+    pub fn new(game_strategy: &GameStrategy, opts: &'a GameOptions, view: &'a BorrowedGameView<'a>) -> InitContext<'a> {
+        InitContext {
+            player: view.player,
+            opts: opts,
+            view: view,
+            partner_names: (0..opts.num_players).map(|player| game_strategy.seat_name(player)).collect(),
+            deck: None,
+        }
+    }
+
+    pub fn with_deck(mut self, deck: &'a Cards) -> InitContext<'a> {
+        self.deck = Some(deck);
+        self
+    }
+}
+
 // Represents the overall strategy for a game
 // Shouldn't do much, except store configuration parameters and
 // possibility initialize some shared randomness between players
 pub trait GameStrategy {
-    fn initialize(&self, Player, &BorrowedGameView) -> Box<PlayerStrategy>;
+    fn initialize(&self, &InitContext, StrategyRng) -> Box<PlayerStrategy>;
+
+    // The name of the strategy playing in a seat, as given on the command line
+    fn seat_name(&self, player: Player) -> String;
 }
 
 // Represents configuration for a strategy.
 // Acts as a factory for game strategies, so we can play many rounds
-pub trait GameStrategyConfig {
+pub trait GameStrategyConfig: Send {
     fn initialize(&self, &GameOptions) -> Box<GameStrategy>;
+
+    // Whether the strategy can play games with these options (number of players, hand size,
+    // rule variants), and if not, why.  Games are only started with options it supports.
+    fn supports(&self, _opts: &GameOptions) -> Result<(), String> {
+        Ok(())
+    }
+
+    // Called after each game of a simulation, so the config can learn across games
+    // (e.g. tuning its parameters).  Games on other threads may be in progress meanwhile,
+    // and the order in which games finish depends on how they are split across threads.
+    // The simulator doesn't lock the config, so that threads can start games at the same time:
+    // a config that learns keeps what it learns behind its own lock (e.g. a Mutex).
+    fn on_game_end(&self, _game: &GameState) {}
+
+    // Bump when a change to the strategy's conventions could change its decisions,
+    // so saved artifacts can be checked against the strategy that made them.
+    // 0 means unversioned.
+    fn version(&self) -> u32 {
+        0
+    }
+
+    // Override a named tuning parameter, e.g. from a config file
+    fn set_param(&mut self, name: &str, _value: &str) -> Result<(), String> {
+        Err(format!("Unknown strategy parameter: {}", name))
+    }
+
+    // The strategy's conventions, for people to read, if it can describe them
+    fn conventions(&self) -> Option<Conventions> {
+        None
+    }
+}
+
+// A description of a strategy's conventions, e.g. how it chooses a move and what its hints mean.
+// Strategies build it from their own parameters, so it describes how they will actually play,
+// including any parameters that have been overridden.
+pub struct Conventions {
+    pub name: String,
+    pub summary: String,
+    // titled lists of rules.  Where the order matters, earlier rules take priority
+    pub sections: Vec<(String, Vec<String>)>,
+    // every tuning parameter (see GameStrategyConfig::set_param), and its value
+    pub params: Vec<(String, String)>,
+}
+impl Conventions {
+    pub fn new(name: &str, summary: &str) -> Conventions {
+        Conventions {
+            name: name.to_string(),
+            summary: summary.to_string(),
+            sections: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    pub fn section(mut self, title: &str, rules: Vec<String>) -> Conventions {
+        self.sections.push((title.to_string(), rules));
+        self
+    }
+
+    pub fn param<T: ToString>(mut self, name: &str, value: T) -> Conventions {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# The {} strategy\n\n{}\n", self.name, self.summary);
+        for &(ref title, ref rules) in self.sections.iter() {
+            markdown.push_str(&format!("\n## {}\n\n", title));
+            for (i, rule) in rules.iter().enumerate() {
+                markdown.push_str(&format!("{}. {}\n", i + 1, rule));
+            }
+        }
+        if !self.params.is_empty() {
+            markdown.push_str("\n## Parameters\n\n| name | value |\n|------|-------|\n");
+            for &(ref name, ref value) in self.params.iter() {
+                markdown.push_str(&format!("| `{}` | {} |\n", name, value));
+            }
+        }
+        markdown
+    }
+}
+
+// Parse a parameter value for GameStrategyConfig::set_param
+pub fn parse_param<T: FromStr>(name: &str, value: &str, param: &mut T) -> Result<(), String> {
+    *param = try!(T::from_str(value).map_err(|_| {
+        format!("Invalid value for parameter {}: {}", name, value)
+    }));
+    Ok(())
+}
+
+// Read parameter overrides from a file with lines of the form `name = value`.
+// Blank lines and lines starting with '#' are ignored.
+pub fn read_params_file(path: &str) -> Result<Vec<(String, String)>, String> {
+    let contents = try!(fs::read_to_string(path).map_err(|err| {
+        format!("Could not read strategy config {}: {}", path, err)
+    }));
+    let mut params = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts = line.splitn(2, '=').map(|part| part.trim()).collect::<Vec<_>>();
+        if parts.len() != 2 || parts[0].is_empty() {
+            return Err(format!("{}:{}: expected `name = value`, got {}", path, i + 1, line));
+        }
+        params.push((parts[0].to_string(), parts[1].to_string()));
+    }
+    Ok(params)
 }
 
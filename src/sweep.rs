@@ -0,0 +1,256 @@
+use rand::{self, Rng, SeedableRng};
+use std::fs;
+
+use simulator::SimResult;
+
+// A study of a strategy's parameters: the configurations to try, each played on the same seeds.
+// Read from a small subset of TOML, with one section saying how to pick the configurations:
+//
+//     # every combination of these values
+//     [grid]
+//     risky_play_threshold = [0.6, 0.75, 0.9]
+//     discard_policy = ["expected-loss", "heuristic"]
+//
+//     # or `samples` configurations, each value drawn independently
+//     [random]
+//     samples = 20
+//     seed = 1
+//     risky_play_threshold = { min = 0.5, max = 1.0 }
+//     ask_play_target = { min = 1, max = 4 }
+//     discard_policy = ["expected-loss", "heuristic"]
+//
+// A single value fixes a parameter.  Ranges are uniform, and integer if both ends are integers.
+// Comments run from a `#` outside of a string to the end of the line.
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum Values {
+    List(Vec<String>),
+    Range(f64, f64),
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum Sampling {
+    Grid,
+    Random { samples: u32, seed: u32 },
+}
+
+#[derive(Debug,Clone)]
+pub struct Sweep {
+    pub sampling: Sampling,
+    pub params: Vec<(String, Values)>,
+}
+impl Sweep {
+    pub fn read(path: &str) -> Result<Sweep, String> {
+        let contents = try!(fs::read_to_string(path).map_err(|err| {
+            format!("Could not read sweep {}: {}", path, err)
+        }));
+        Sweep::parse(&contents).map_err(|err| format!("{}:{}", path, err))
+    }
+
+    // errors start with the line number
+    pub fn parse(contents: &str) -> Result<Sweep, String> {
+        let mut section = None;
+        let mut samples = None;
+        let mut seed = 0;
+        let mut params: Vec<(String, Values)> = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line_num = i + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                if section.is_some() {
+                    return Err(format!("{}: a sweep has a single [grid] or [random] section", line_num));
+                }
+                section = match line[1..line.len() - 1].trim() {
+                    "grid" => Some(false),
+                    "random" => Some(true),
+                    name => return Err(format!("{}: unknown section [{}], expected [grid] or [random]", line_num, name)),
+                };
+                continue;
+            }
+            let is_random = match section {
+                Some(is_random) => is_random,
+                None => return Err(format!("{}: expected [grid] or [random] before any parameters", line_num)),
+            };
+            let parts = line.splitn(2, '=').map(|part| part.trim()).collect::<Vec<_>>();
+            if parts.len() != 2 || parts[0].is_empty() {
+                return Err(format!("{}: expected `name = value`, got {}", line_num, line));
+            }
+            let (name, value) = (parts[0], parts[1]);
+            if is_random && (name == "samples" || name == "seed") {
+                let n = try!(value.parse::<u32>().map_err(|_| {
+                    format!("{}: {} should be a non-negative integer, got {}", line_num, name, value)
+                }));
+                if name == "samples" { samples = Some(n) } else { seed = n }
+                continue;
+            }
+            if params.iter().any(|&(ref other, _)| other == name) {
+                return Err(format!("{}: {} is given twice", line_num, name));
+            }
+            let values = try!(parse_values(value).map_err(|err| format!("{}: {}", line_num, err)));
+            if let Values::Range(..) = values {
+                if !is_random {
+                    return Err(format!("{}: a [grid] needs a list of values for {}, not a range", line_num, name));
+                }
+            }
+            params.push((name.to_string(), values));
+        }
+        let sampling = match section {
+            None => return Err(String::from("1: expected a [grid] or [random] section")),
+            Some(false) => Sampling::Grid,
+            Some(true) => match samples {
+                Some(samples) if samples > 0 => Sampling::Random { samples: samples, seed: seed },
+                _ => return Err(String::from("1: a [random] sweep needs a positive number of samples")),
+            },
+        };
+        Ok(Sweep {
+            sampling: sampling,
+            params: params,
+        })
+    }
+
+    // the parameter overrides of each configuration to play
+    pub fn configurations(&self) -> Vec<Vec<(String, String)>> {
+        match self.sampling {
+            Sampling::Grid => {
+                self.params.iter().fold(vec![Vec::new()], |configs, &(ref name, ref values)| {
+                    let values = match *values {
+                        Values::List(ref values) => values,
+                        Values::Range(..) => panic!("A grid can't have a range"),
+                    };
+                    configs.iter().flat_map(|config| {
+                        values.iter().map(move |value| {
+                            let mut config = config.clone();
+                            config.push((name.clone(), value.clone()));
+                            config
+                        })
+                    }).collect()
+                })
+            }
+            Sampling::Random { samples, seed } => {
+                let mut rng = rand::ChaChaRng::from_seed(&[seed]);
+                (0..samples).map(|_| {
+                    self.params.iter().map(|&(ref name, ref values)| {
+                        let value = match *values {
+                            Values::List(ref values) => rng.choose(values).unwrap().clone(),
+                            Values::Range(min, max) => {
+                                if min.fract() == 0.0 && max.fract() == 0.0 {
+                                    rng.gen_range(min as i64, max as i64 + 1).to_string()
+                                } else {
+                                    format!("{:.4}", rng.gen_range(min, max))
+                                }
+                            }
+                        };
+                        (name.clone(), value)
+                    }).collect()
+                }).collect()
+            }
+        }
+    }
+}
+
+// the line up to any `#` outside of a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// `value`, `"value"`, `[a, b, ...]` or `{ min = a, max = b }`
+fn parse_values(value: &str) -> Result<Values, String> {
+    if value.starts_with('[') {
+        if !value.ends_with(']') {
+            return Err(format!("unterminated list {}", value));
+        }
+        let items = try!(split_items(&value[1..value.len() - 1]));
+        if items.is_empty() {
+            return Err(String::from("an empty list of values"));
+        }
+        return Ok(Values::List(try!(items.iter().map(|item| parse_scalar(item)).collect())));
+    }
+    if value.starts_with('{') {
+        if !value.ends_with('}') {
+            return Err(format!("unterminated table {}", value));
+        }
+        let (mut min, mut max) = (None, None);
+        for item in try!(split_items(&value[1..value.len() - 1])) {
+            let parts = item.splitn(2, '=').map(|part| part.trim()).collect::<Vec<_>>();
+            let bound = match parts.get(1).map(|bound| bound.parse::<f64>()) {
+                Some(Ok(bound)) => bound,
+                _ => return Err(format!("expected `min = NUMBER` or `max = NUMBER`, got {}", item)),
+            };
+            match parts[0] {
+                "min" => min = Some(bound),
+                "max" => max = Some(bound),
+                _ => return Err(format!("expected min or max, got {}", parts[0])),
+            }
+        }
+        return match (min, max) {
+            (Some(min), Some(max)) if min <= max => Ok(Values::Range(min, max)),
+            (Some(min), Some(max)) => Err(format!("empty range from {} to {}", min, max)),
+            _ => Err(format!("a range needs both min and max: {}", value)),
+        };
+    }
+    Ok(Values::List(vec![try!(parse_scalar(value))]))
+}
+
+fn parse_scalar(value: &str) -> Result<String, String> {
+    if value.starts_with('"') {
+        if value.len() < 2 || !value.ends_with('"') {
+            return Err(format!("unterminated string {}", value));
+        }
+        return Ok(value[1..value.len() - 1].to_string());
+    }
+    if value.is_empty() {
+        return Err(String::from("a missing value"));
+    }
+    Ok(value.to_string())
+}
+
+// splits on the commas outside of strings, dropping a trailing comma
+fn split_items(list: &str) -> Result<Vec<String>, String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut in_string = false;
+    for c in list.chars() {
+        match c {
+            '"' => { in_string = !in_string; current.push(c); }
+            ',' if !in_string => { items.push(current.trim().to_string()); current.clear(); }
+            _ => current.push(c),
+        }
+    }
+    if in_string {
+        return Err(format!("unterminated string in {}", list));
+    }
+    if !current.trim().is_empty() {
+        items.push(current.trim().to_string());
+    }
+    Ok(items)
+}
+
+// The configurations, best first, as a table
+pub fn ranked_table(results: &[(Vec<(String, String)>, SimResult)]) -> String {
+    let mut order = (0..results.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        results[b].1.average_score().partial_cmp(&results[a].1.average_score()).unwrap().then(a.cmp(&b))
+    });
+    let mut table = String::from("| rank | score | perfect | parameters |\n|---|---|---|---|\n");
+    for (rank, &i) in order.iter().enumerate() {
+        let (ref params, ref result) = results[i];
+        let params = params.iter().map(|&(ref name, ref value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>();
+        table.push_str(&format!("| {} | {:.4} ± {:.4} | {:.2} ± {:.2} % | {} |\n",
+                                rank + 1, result.average_score(), result.score_stderr(),
+                                result.percent_perfect(), result.percent_perfect_stderr(),
+                                if params.is_empty() { String::from("(defaults)") } else { params.join(" ") }));
+    }
+    table
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::PathBuf;
+
+use game::*;
+use helpers::*;
+use json::Json;
+use observer::GameObserver;
+use version::ArtifactVersion;
+
+// Dumps every decision made in a game as one line of JSON, for training models
+// to imitate the strategy or predict the final score.
+//
+// Each line has the acting player's view of the game: the board, the other players' hands,
+// what is public knowledge about every hand (from hints, and the cards seen leaving hands), and
+// the legal moves, together with the move that was chosen and the game's final score.
+
+fn json_card(card: &Card) -> Json {
+    Json::String(card.to_string())
+}
+
+pub fn json_choice(choice: &TurnChoice) -> Json {
+    match *choice {
+        TurnChoice::Play(index) => Json::object().with("type", "play").with("index", index),
+        TurnChoice::Discard(index) => Json::object().with("type", "discard").with("index", index),
+        TurnChoice::Hint(ref hint) => {
+            let json = Json::object().with("type", "hint").with("player", hint.player);
+            match hint.hinted {
+                Hinted::Color(color) => json.with("color", color.to_string()),
+                Hinted::Value(value) => json.with("value", value),
+            }
+        }
+    }
+}
+
+fn json_knowledge(hand_info: &HandInfo<CardPossibilityTable>) -> Json {
+    Json::Array(hand_info.iter().map(|table| {
+        let mut possibilities = Json::object();
+        for (card, weight) in table.iter_possibilities() {
+            possibilities.insert(&card.to_string(), weight);
+        }
+        possibilities
+    }).collect())
+}
+
+pub struct TrainingDataObserver {
+    dir: PathBuf,
+    seed: u32,
+    // what is public knowledge about each player's hand, once a game has started
+    knowledge: Option<BeliefTracker>,
+    // one record per decision, missing the final score
+    records: Vec<Json>,
+    version: ArtifactVersion,
+}
+impl TrainingDataObserver {
+    // writes one file per game, `<dir>/<seed>.ndjson`
+    pub fn new(dir: &str, version: ArtifactVersion) -> TrainingDataObserver {
+        TrainingDataObserver {
+            dir: PathBuf::from(dir),
+            seed: 0,
+            knowledge: None,
+            records: Vec::new(),
+            version: version,
+        }
+    }
+}
+impl GameObserver for TrainingDataObserver {
+    fn on_game_start(&mut self, seed: u32, game: &GameState) {
+        self.seed = seed;
+        self.knowledge = Some(BeliefTracker::new(&game.board));
+        self.records.clear();
+    }
+
+    fn before_turn(&mut self, game: &GameState) {
+        let board = &game.board;
+        let me = board.player;
+        let fireworks = COLORS.iter().map(|&color| board.get_firework(color).top).collect::<Vec<_>>();
+        let other_hands = game.get_players().filter(|&player| player != me).map(|player| {
+            Json::object()
+                .with("player", player)
+                .with("cards", Json::Array(game.hands[&player].iter().map(json_card).collect()))
+        }).collect::<Vec<_>>();
+        let beliefs = self.knowledge.as_ref().expect("A turn before the game started");
+        let knowledge = game.get_players().map(|player| json_knowledge(beliefs.hand(player))).collect::<Vec<_>>();
+        let legal = game.board.legal_choices(&game.get_view(game.board.player)).map(|choice| json_choice(&choice)).collect::<Vec<_>>();
+        self.records.push(Json::object()
+            .with("version", self.version.to_json())
+            .with("seed", self.seed)
+            .with("turn", board.turn)
+            .with("player", me)
+            .with("hints", board.hints_remaining)
+            .with("lives", board.lives_remaining)
+            .with("deck_size", board.deck_size)
+            .with("fireworks", fireworks)
+            .with("discard", Json::Array(board.discard.cards.iter().map(json_card).collect()))
+            .with("other_hands", other_hands)
+            .with("knowledge", knowledge)
+            .with("legal_moves", legal));
+    }
+
+    fn on_turn(&mut self, turn: &TurnRecord, board: &BoardState) {
+        // turns replayed when resuming a game have no record of the view
+        if let Some(record) = self.records.last_mut() {
+            if record.get("choice").is_none() {
+                record.insert("choice", json_choice(&turn.choice));
+            }
+        }
+        if let Some(ref mut knowledge) = self.knowledge {
+            knowledge.update_for_turn(turn, board);
+        }
+    }
+
+    fn on_game_end(&mut self, game: &GameState) {
+        let mut lines = String::new();
+        for record in self.records.iter_mut() {
+            record.insert("final_score", game.score());
+            lines.push_str(&format!("{}\n", record));
+        }
+        let path = self.dir.join(format!("{}.ndjson", self.seed));
+        fs::write(&path, lines).unwrap_or_else(|err| panic!("Couldn't write {}: {}", path.display(), err));
+    }
+}
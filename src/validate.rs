@@ -0,0 +1,163 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use game::*;
+use helpers::{CardPossibilityTable, HandInfo};
+use json::Json;
+use strategy::*;
+
+// Wraps a strategy with checks that it only uses information it should:
+//  - a player can't look at their own hand (BorrowedGameView::get_hand already panics on this)
+//  - every choice is legal, with a clear message if not
+//  - after every turn, all players agree on what is common knowledge,
+//    as reported by PlayerStrategy::public_info_digest
+
+pub struct ValidatingStrategyConfig {
+    inner: Box<GameStrategyConfig + Sync>,
+}
+impl ValidatingStrategyConfig {
+    pub fn new(inner: Box<GameStrategyConfig + Sync>) -> ValidatingStrategyConfig {
+        ValidatingStrategyConfig {
+            inner: inner,
+        }
+    }
+}
+impl GameStrategyConfig for ValidatingStrategyConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        Box::new(ValidatingStrategy {
+            inner: self.inner.initialize(opts),
+            digests: Rc::new(RefCell::new(DigestLog::new(opts.num_players))),
+        })
+    }
+
+    fn supports(&self, opts: &GameOptions) -> Result<(), String> {
+        self.inner.supports(opts)
+    }
+
+    fn on_game_end(&self, game: &GameState) {
+        self.inner.on_game_end(game)
+    }
+
+    fn version(&self) -> u32 {
+        self.inner.version()
+    }
+
+    fn set_param(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.inner.set_param(name, value)
+    }
+
+    fn conventions(&self) -> Option<Conventions> {
+        self.inner.conventions()
+    }
+}
+
+// The digests reported by each player for the current turn
+struct DigestLog {
+    num_players: u32,
+    turn: u32,
+    digests: Vec<(Player, u64)>,
+}
+impl DigestLog {
+    fn new(num_players: u32) -> DigestLog {
+        DigestLog {
+            num_players: num_players,
+            turn: 0,
+            digests: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, turn: u32, player: Player, digest: u64) {
+        if turn != self.turn {
+            assert!(self.digests.is_empty() || self.digests.len() as u32 == self.num_players,
+                    "Only {} of {} players updated after turn {}",
+                    self.digests.len(), self.num_players, self.turn);
+            self.turn = turn;
+            self.digests.clear();
+        }
+        if let Some(&(other, other_digest)) = self.digests.first() {
+            assert!(digest == other_digest,
+                    "After turn {}, player {} and player {} disagree about public information",
+                    turn, other, player);
+        }
+        self.digests.push((player, digest));
+    }
+}
+
+pub struct ValidatingStrategy {
+    inner: Box<GameStrategy>,
+    digests: Rc<RefCell<DigestLog>>,
+}
+impl GameStrategy for ValidatingStrategy {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(ValidatingPlayerStrategy {
+            inner: self.inner.initialize(context, rng),
+            digests: self.digests.clone(),
+            me: context.player,
+        })
+    }
+
+    fn seat_name(&self, player: Player) -> String {
+        self.inner.seat_name(player)
+    }
+}
+
+pub struct ValidatingPlayerStrategy {
+    inner: Box<PlayerStrategy>,
+    digests: Rc<RefCell<DigestLog>>,
+    me: Player,
+}
+impl ValidatingPlayerStrategy {
+    fn check_digest(&self, view: &BorrowedGameView) {
+        if let Some(digest) = self.inner.public_info_digest() {
+            // the board's turn has already advanced past the turn being processed
+            self.digests.borrow_mut().report(view.board.turn - 1, self.me, digest);
+        }
+    }
+}
+impl PlayerStrategy for ValidatingPlayerStrategy {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        let choice = self.inner.decide(view);
+        if let Err(err) = view.board.check_choice(view, &choice) {
+            panic!("On turn {}, player {} {}: {:?}", view.board.turn, self.me, err, choice);
+        }
+        choice
+    }
+
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.inner.update(turn, view);
+        self.check_digest(view);
+    }
+
+    fn replay(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.inner.replay(turn, view);
+        self.check_digest(view);
+    }
+
+    fn concede(&mut self, view: &BorrowedGameView) -> bool {
+        self.inner.concede(view)
+    }
+
+    fn public_info_digest(&self) -> Option<u64> {
+        self.inner.public_info_digest()
+    }
+
+    fn public_card_probabilities(&self, player: Player) -> Option<Vec<Vec<(Card, f32)>>> {
+        self.inner.public_card_probabilities(player)
+    }
+
+    fn own_hand_knowledge(&self, view: &BorrowedGameView) -> Option<HandInfo<CardPossibilityTable>> {
+        self.inner.own_hand_knowledge(view)
+    }
+
+    fn notes(&self) -> Vec<CardNote> {
+        self.inner.notes()
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, f64)> {
+        self.inner.metrics()
+    }
+
+    fn inspect(&self) -> Option<Json> {
+        self.inner.inspect()
+    }
+}
@@ -0,0 +1,68 @@
+use json::Json;
+
+// Bump when a change to the engine could change the outcome of a game
+// (e.g. how hints are resolved or when the game ends).
+pub const RULES_VERSION: u32 = 1;
+
+// Which rules and conventions produced a saved artifact (replay, training dump, ...),
+// so that artifacts from incompatible versions aren't silently mixed.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ArtifactVersion {
+    pub rules: u32,
+    pub strategy: String,
+    // see GameStrategyConfig::version
+    pub strategy_version: u32,
+}
+impl ArtifactVersion {
+    pub fn new(strategy: &str, strategy_version: u32) -> ArtifactVersion {
+        ArtifactVersion {
+            rules: RULES_VERSION,
+            strategy: strategy.to_string(),
+            strategy_version: strategy_version,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("rules", self.rules)
+            .with("strategy", &self.strategy[..])
+            .with("strategy_version", self.strategy_version)
+    }
+
+    pub fn from_json(json: &Json) -> Result<ArtifactVersion, String> {
+        let number = |key: &str| match json.get(key) {
+            Some(&Json::Number(n)) => Ok(n as u32),
+            _ => Err(format!("Artifact version is missing `{}`: {}", key, json)),
+        };
+        let strategy = match json.get("strategy") {
+            Some(&Json::String(ref s)) => s.clone(),
+            _ => return Err(format!("Artifact version is missing `strategy`: {}", json)),
+        };
+        Ok(ArtifactVersion {
+            rules: try!(number("rules")),
+            strategy: strategy,
+            strategy_version: try!(number("strategy_version")),
+        })
+    }
+
+    // Can an artifact with this version be used alongside `current`?
+    // The error explains what differs and what can still be trusted.
+    pub fn check_compatible(&self, current: &ArtifactVersion) -> Result<(), String> {
+        if self.rules != current.rules {
+            return Err(format!(
+                "Artifact was made with rules version {}, but this build uses rules version {}; \
+                 games may not replay identically", self.rules, current.rules));
+        }
+        if self.strategy != current.strategy {
+            return Err(format!(
+                "Artifact was made by the {} strategy, not {}", self.strategy, current.strategy));
+        }
+        if self.strategy_version != current.strategy_version {
+            return Err(format!(
+                "Artifact was made with version {} of the {} strategy, but this build has version {}; \
+                 the moves are still valid, but the strategy's own decisions may differ",
+                self.strategy_version, self.strategy, current.strategy_version));
+        }
+        Ok(())
+    }
+}
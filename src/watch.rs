@@ -0,0 +1,124 @@
+use std::io::{self, BufRead, Write};
+
+use game::*;
+use helpers::*;
+use html_replay::describe_turn;
+use observer::GameObserver;
+use render::{self, hand_grid, RenderStyle};
+use strategy::{CardNote, PlayerStrategy};
+
+// Shows a game in the terminal as it is played, redrawing the screen before every turn
+// and waiting for Enter to continue.  Uses ANSI escape codes to redraw, and shows cards in
+// the given style (see render::RenderStyle).
+
+// each card sits above the grid of what is known about it (see render::hand_grid)
+const COLUMN_WIDTH: usize = 6;
+
+// pads text to a column, given how wide it appears on screen,
+// since the escape codes take up no room on screen
+fn pad(text: String, visible_width: usize) -> String {
+    format!("{}{}", text, " ".repeat(COLUMN_WIDTH.saturating_sub(visible_width)))
+}
+
+pub struct WatchObserver {
+    style: RenderStyle,
+    // what is public knowledge about each player's hand, once a game has started
+    knowledge: Option<BeliefTracker>,
+    // each player's notes (see PlayerStrategy::notes), as of their last turn
+    notes: Vec<Vec<CardNote>>,
+    last_action: Option<String>,
+}
+impl WatchObserver {
+    pub fn new(style: RenderStyle) -> WatchObserver {
+        WatchObserver {
+            style: style,
+            knowledge: None,
+            notes: Vec::new(),
+            last_action: None,
+        }
+    }
+
+    fn render(&self, game: &GameState) -> String {
+        let board = &game.board;
+        // clear the screen and move to the top left
+        let mut screen = String::from("\x1b[2J\x1b[H");
+        if game.is_over() {
+            screen.push_str(&format!("Game over after {} turns: score {}\n\n", board.turn - 1, game.score()));
+        } else {
+            screen.push_str(&format!("Turn {}, player {} to go\n\n", board.turn, board.player));
+        }
+        screen.push_str(&format!("Hints: {}/{}   Lives: {}/{}   Deck: {}   Score: {}\n",
+                                 board.hints_remaining, board.hints_total,
+                                 board.lives_remaining, board.lives_total,
+                                 board.deck_size, game.score()));
+        let fireworks = COLORS.iter().map(|&color| {
+            render::card_str(&Card::new(color, board.get_firework(color).top), self.style)
+        }).collect::<Vec<_>>();
+        screen.push_str(&format!("Fireworks: {}\n", fireworks.join(" ")));
+        let discards = board.discard.cards.iter().map(|card| render::card_str(card, self.style)).collect::<Vec<_>>();
+        screen.push_str(&format!("Discards: {}\n\n", discards.join(" ")));
+
+        let beliefs = self.knowledge.as_ref().expect("Rendering before the game started");
+        for player in game.get_players() {
+            let marker = if player == board.player && !game.is_over() { ">" } else { " " };
+            let cards = game.hands[&player].iter().map(|card| pad(render::card_str(card, self.style), 2)).collect::<String>();
+            screen.push_str(&format!("{} Player {}:   {}\n", marker, player, cards));
+            // what is public knowledge about each card
+            for line in hand_grid(beliefs.hand(player), self.style).lines() {
+                screen.push_str(&format!("            {}\n", line));
+            }
+            // what the player's notes say each of their own cards is for
+            let flags = board.card_ids(&player).iter().map(|&card_id| {
+                self.notes[player as usize].iter().find(|note| note.card_id == card_id)
+                    .and_then(|note| note.flags.first()).map_or(String::new(), |flag| flag.to_string())
+            }).collect::<Vec<_>>();
+            if flags.iter().any(|flag| !flag.is_empty()) {
+                let flags = flags.into_iter().map(|flag| {
+                    let width = flag.len();
+                    pad(flag, width)
+                }).collect::<String>();
+                screen.push_str(&format!("              {}\n", flags));
+            }
+        }
+
+        if let Some(ref action) = self.last_action {
+            screen.push_str(&format!("\nLast action: {}\n", action));
+        }
+        screen
+    }
+
+    fn show(&self, game: &GameState, prompt: &str) {
+        print!("{}\n{}", self.render(game), prompt);
+        io::stdout().flush().unwrap();
+        let stdin = io::stdin();
+        let mut line = String::new();
+        stdin.lock().read_line(&mut line).unwrap();
+    }
+}
+
+impl GameObserver for WatchObserver {
+    fn on_game_start(&mut self, _seed: u32, game: &GameState) {
+        self.knowledge = Some(BeliefTracker::new(&game.board));
+        self.notes = game.get_players().map(|_| Vec::new()).collect();
+        self.last_action = None;
+    }
+
+    fn before_turn(&mut self, game: &GameState) {
+        self.show(game, "Press Enter for the next turn (Ctrl-C to quit)");
+    }
+
+    fn on_turn(&mut self, turn: &TurnRecord, board: &BoardState) {
+        self.last_action = Some(describe_turn(turn));
+        if let Some(ref mut knowledge) = self.knowledge {
+            knowledge.update_for_turn(turn, board);
+        }
+    }
+
+    fn after_update(&mut self, player: Player, strategy: &PlayerStrategy) {
+        self.notes[player as usize] = strategy.notes();
+    }
+
+    fn on_game_end(&mut self, game: &GameState) {
+        self.show(game, "Press Enter to exit");
+    }
+}
@@ -0,0 +1,58 @@
+extern crate rust_hanabi;
+
+mod common;
+
+use rust_hanabi::game::*;
+use rust_hanabi::helpers::{BeliefTracker, CardInfo, CardPossibilityTable};
+use rust_hanabi::position::PositionBuilder;
+use common::options;
+
+#[test]
+fn probability_holds() {
+    let opts = options(3, 5);
+    let game = PositionBuilder::new(&opts)
+        .hand(0, "r1 y2 g3 b4 w4")
+        .hand(1, "w5 r1 r1 y1 y1")
+        .hand(2, "g1 g1 g2 b2 b3")
+        .build();
+    let mut beliefs = BeliefTracker::new(&game.board);
+    let view = game.get_view(0);
+    let card = |card: &str| Card::new(card.chars().next().unwrap(), card[1..].parse().unwrap());
+
+    // the hands we can see, we know
+    assert_eq!(beliefs.probability_holds(1, &card("w5"), &view), 1.0);
+    assert_eq!(beliefs.probability_holds(2, &card("w5"), &view), 0.0);
+
+    // our own can't hold a card whose every copy we see
+    assert_eq!(beliefs.probability_holds(0, &card("w5"), &view), 0.0);
+    // and is less likely to hold one whose copies we mostly see
+    let r1 = beliefs.probability_holds(0, &card("r1"), &view);
+    let r2 = beliefs.probability_holds(0, &card("r2"), &view);
+    assert!(0.0 < r1 && r1 < r2 && r2 < 1.0, "r1: {}, r2: {}", r1, r2);
+
+    // once a hint shows our first card is a red 1, we surely hold one
+    let hint = Hint { player: 0, hinted: Hinted::Color('r') };
+    beliefs.update_for_hint(&hint, &vec![true, false, false, false, false]);
+    let hint = Hint { player: 0, hinted: Hinted::Value(1) };
+    beliefs.update_for_hint(&hint, &vec![true, false, false, false, false]);
+    assert!((beliefs.probability_holds(0, &card("r1"), &view) - 1.0).abs() < 1e-6);
+}
+
+// Beliefs follow the engine about which cards a hint touches, whether or not it touched them
+#[test]
+fn hints_mark_what_the_engine_matches() {
+    for hinted in Hinted::all() {
+        for &color in COLORS.iter() {
+            for &value in VALUES.iter() {
+                let card = Card::new(color, value);
+                let touched = hinted.matches(&card);
+                let mut table = CardPossibilityTable::new();
+                table.mark_hint(&hinted, touched);
+                assert!(table.is_possible(&card), "{} ruled out by hint {}", card, hinted);
+                for other in table.get_possibilities() {
+                    assert_eq!(hinted.matches(&other), touched, "{} possible after hint {}", other, hinted);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use rust_hanabi::game::*;
+
+// the standard rules, for `num_players` players with `hand_size` cards each
+pub fn options(num_players: u32, hand_size: u32) -> GameOptions {
+    GameOptions {
+        num_players: num_players,
+        hand_size: hand_size,
+        num_hints: 8,
+        starting_hints: 8,
+        discard_half_hints: 2,
+        firework_half_hints: 2,
+        num_lives: 3,
+        allow_empty_hints: false,
+        endgame_rule: EndgameRule::FinalRound,
+        misplay_rule: MisplayRule::Discard,
+        strikeout_scores_zero: false,
+        end_when_settled: false,
+        illegal_moves: IllegalMovePolicy::Panic,
+    }
+}
@@ -0,0 +1,191 @@
+extern crate rust_hanabi;
+
+mod common;
+
+use rust_hanabi::adaptive::AdaptiveStrategyConfig;
+use rust_hanabi::game::*;
+use rust_hanabi::meta::MetaStrategyConfig;
+use rust_hanabi::noisy::NoisyStrategyConfig;
+use rust_hanabi::position::{parse_cards, PositionBuilder, Position};
+use rust_hanabi::simulator::simulate_once;
+use rust_hanabi::strategy::GameStrategyConfig;
+use rust_hanabi::strategies::cheating::{CheatingStrategyConfig, ThrowawayHint};
+use rust_hanabi::strategies::examples::{BeginnerStrategyConfig, RandomStrategyConfig};
+use rust_hanabi::strategies::information::InformationStrategyConfig;
+use rust_hanabi::validate::ValidatingStrategyConfig;
+use common::options;
+
+#[test]
+fn builder_accounts_for_every_card() {
+    let opts = options(2, 5);
+    let game = PositionBuilder::new(&opts)
+        .hand(0, "r1 r1 y2 g3 b4")
+        .hand(1, "w5 w1 w1 y1 y1")
+        .firework('b', 3)
+        .discard("r4 r4 g1")
+        .hints(2)
+        .player_to_move(1)
+        .build();
+    assert_eq!(game.board.deck_size + 10 + 3 + 3, get_total_cards());
+    assert_eq!(game.board.deck_size as usize, game.deck.len());
+    assert_eq!(game.board.highest_attainable('r'), 3);
+    assert_eq!(game.board.max_attainable_score(), 23);
+    assert_eq!(game.board.hints_remaining, 2);
+    assert_eq!(game.board.turn, 2);
+}
+
+#[test]
+fn builder_draws_given_deck_in_order() {
+    let opts = options(2, 5);
+    let mut position = Position::new(
+        PositionBuilder::new(&opts)
+            .hand(0, "r1 y1 g1 b1 w1")
+            .hand(1, "r2 y2 g2 b2 w2")
+            .deck("r5 y5")
+            .build(),
+        CheatingStrategyConfig::new().initialize(&opts));
+    position.apply(TurnChoice::Play(0));
+    assert_eq!(position.game.hands[&0].last(), Some(&Card::new('r', 5)));
+}
+
+#[test]
+#[should_panic(expected = "Too many copies of r5")]
+fn builder_rejects_impossible_positions() {
+    let opts = options(2, 5);
+    PositionBuilder::new(&opts)
+        .hand(0, "r5 r5 y2 g3 b4")
+        .hand(1, "w5 w1 w1 y1 y1")
+        .build();
+}
+
+#[test]
+fn cheater_plays_playable_card() {
+    let opts = options(3, 5);
+    let game = PositionBuilder::new(&opts)
+        .hand(0, "r3 y4 g4 b3 w2")
+        .hand(1, "r1 y3 g5 b4 w3")
+        .hand(2, "y1 y2 g2 b2 w4")
+        .firework('w', 1)
+        .build();
+    let mut position = Position::new(game, CheatingStrategyConfig::new().initialize(&opts));
+    assert_eq!(position.decide(), TurnChoice::Play(4));
+}
+
+// For a partner who reads touched cards as playable, a cheater's throwaway hint touches only
+// playable cards when it can
+#[test]
+fn cheater_throwaway_hint_misleads_no_one() {
+    let opts = options(3, 5);
+    let hands = ["r3 y4 g4 b3 w2", "r2 y3 g5 b4 w1", "y2 g2 b2 w3 r4"];
+    let build = || PositionBuilder::new(&opts).hand(0, hands[0]).hand(1, hands[1]).hand(2, hands[2]).build();
+    let mut config = CheatingStrategyConfig::new();
+    config.throwaway_hint = ThrowawayHint::PlayClueReader;
+    let mut position = Position::new(build(), config.initialize(&opts));
+    match position.decide() {
+        TurnChoice::Hint(hint) => {
+            assert_eq!(hint.player, 1);
+            let touched = parse_cards(hands[1]).into_iter().filter(|card| hint.hinted.matches(card)).collect::<Vec<_>>();
+            assert_eq!(touched, vec![Card::new('w', 1)]);
+        }
+        choice => panic!("Expected a throwaway hint, got {:?}", choice),
+    }
+    // by default, it's the value of the next player's first card, a 2 that can't be played yet
+    let mut position = Position::new(build(), CheatingStrategyConfig::new().initialize(&opts));
+    assert_eq!(position.decide(), TurnChoice::Hint(Hint { player: 1, hinted: Hinted::Value(2) }));
+}
+
+#[test]
+fn info_hint_reveals_ones() {
+    let opts = options(3, 5);
+    let game = PositionBuilder::new(&opts)
+        .hand(0, "r3 y4 g4 b3 w2")
+        .hand(1, "r3 y1 g5 b4 w3")
+        .hand(2, "y3 y2 g2 b2 w4")
+        .build();
+    let mut position = Position::new(game, InformationStrategyConfig::new().initialize(&opts));
+    position.apply(TurnChoice::Hint(Hint { player: 1, hinted: Hinted::Value(1) }));
+
+    let knowledge = position.card_probabilities(1).unwrap();
+    for (index, possibilities) in knowledge.iter().enumerate() {
+        let p_one = possibilities.iter().filter(|&&(ref card, _)| card.value == 1).map(|&(_, p)| p).sum::<f32>();
+        if index == 1 {
+            assert!((p_one - 1.0).abs() < 1e-6, "the hinted card is a 1");
+        } else {
+            assert_eq!(p_one, 0.0, "card {} isn't a 1", index);
+        }
+    }
+    // a 1 is playable on an empty board
+    assert_eq!(position.decide(), TurnChoice::Play(1));
+}
+
+// Hat sums carry log2 of their modulus in bits to each player given part of them, some of which
+// is wasted when their answers ask for less
+#[test]
+fn info_measures_hat_sums() {
+    let opts = options(4, 4);
+    let game = simulate_once(&opts, InformationStrategyConfig::new().initialize(&opts), 0);
+    let metrics = &game.strategy_metrics;
+    assert!(metrics["hat sums"] > 0.0);
+    assert!(metrics["hat bits"] >= metrics["hat bits wasted"]);
+    assert!(metrics["hat bits wasted"] >= 0.0);
+}
+
+// Wrappers pass the metrics of the strategy they wrap through
+#[test]
+fn validated_info_measures_hat_sums() {
+    let opts = options(4, 4);
+    let plain = simulate_once(&opts, InformationStrategyConfig::new().initialize(&opts), 0);
+    let validated = simulate_once(
+        &opts, ValidatingStrategyConfig::new(Box::new(InformationStrategyConfig::new())).initialize(&opts), 0);
+    assert_eq!(validated.strategy_metrics, plain.strategy_metrics);
+}
+
+// With partners who often misplay, info players give up on their conventions rather than panic
+#[test]
+fn adaptive_info_survives_mistakes() {
+    let opts = options(3, 5);
+    let noisy = NoisyStrategyConfig::new(Box::new(InformationStrategyConfig::new()), 0.3, 0, Some(vec![0]));
+    let config = AdaptiveStrategyConfig::new(Box::new(noisy), Box::new(BeginnerStrategyConfig::new()), 0);
+    let fallen_back = (0..10).map(|seed| {
+        let game = simulate_once(&opts, config.initialize(&opts), seed);
+        assert!(game.is_over());
+        game.strategy_metrics["players fallen back"]
+    }).sum::<f64>();
+    assert!(fallen_back > 0.0);
+}
+
+// As in the README: every player misplays now and then, and tolerates a few deviations before
+// giving up on its conventions, finishing every game either way
+#[test]
+fn adaptive_info_tolerates_some_deviations() {
+    let opts = options(3, 5);
+    let adaptive = AdaptiveStrategyConfig::new(Box::new(InformationStrategyConfig::new()),
+                                               Box::new(BeginnerStrategyConfig::new()), 2);
+    let config = NoisyStrategyConfig::new(Box::new(adaptive), 0.05, 0, None);
+    for seed in 0..20 {
+        let game = simulate_once(&opts, config.initialize(&opts), seed);
+        assert!(game.is_over());
+    }
+}
+
+// plays the strategy given for the number of players, and passes parameters on to those that have them
+#[test]
+fn meta_strategy_chooses_by_players() {
+    let random = RandomStrategyConfig {
+        hint_probability: 0.4,
+        play_probability: 0.2,
+    };
+    let mut config = MetaStrategyConfig::new("meta", "info", Box::new(InformationStrategyConfig::new()))
+        .with(2, "cheat", Box::new(CheatingStrategyConfig::new()))
+        .with(3, "random", Box::new(random));
+    for &(num_players, hand_size, name) in &[(2, 5, "cheat"), (3, 5, "random"), (4, 4, "info"), (5, 4, "info")] {
+        let opts = options(num_players, hand_size);
+        assert_eq!(config.initialize(&opts).seat_name(0), name, "{} players", num_players);
+    }
+
+    // only the random strategy has this parameter
+    config.set_param("hint_probability", "0.9").unwrap();
+    let params = config.conventions().unwrap().params;
+    assert!(params.contains(&(String::from("hint_probability"), String::from("0.9"))));
+    assert!(config.set_param("no_such_param", "1").is_err());
+}
@@ -0,0 +1,195 @@
+extern crate rust_hanabi;
+
+mod common;
+
+use rust_hanabi::game::*;
+use rust_hanabi::position::PositionBuilder;
+use rust_hanabi::puzzles::find_puzzles;
+use rust_hanabi::simulator::{new_deck, simulate_once};
+use rust_hanabi::strategy::GameStrategyConfig;
+use rust_hanabi::strategies::cheat_search::Solver;
+use rust_hanabi::strategies::cheating::CheatingStrategyConfig;
+use common::options;
+
+#[test]
+fn fresh_game() {
+    for &(num_players, hand_size, spare_discards) in &[(2, 5, 15), (3, 5, 10), (4, 4, 9), (5, 4, 5)] {
+        let game = GameState::new(&options(num_players, hand_size), new_deck(0));
+        let board = &game.board;
+        assert_eq!(board.spare_discards(), spare_discards);
+        assert_eq!(board.cards_played(), 0);
+        assert_eq!(board.cards_left_to_play(), PERFECT_SCORE);
+        // the final round gives each player one more turn to play
+        assert_eq!(board.pace(), (spare_discards + num_players) as i32);
+        assert!(!board.is_final_round());
+    }
+}
+
+#[test]
+fn lost_cards_lower_max_score() {
+    let opts = options(2, 5);
+    let game = PositionBuilder::new(&opts)
+        .hand(0, "r1 r1 y2 g3 b4")
+        .hand(1, "w5 w1 w1 y1 y1")
+        .firework('b', 3)
+        .discard("r4 r4 g2")
+        .build();
+    let board = &game.board;
+    assert_eq!(board.max_attainable_score(), 23);
+    assert_eq!(board.cards_played(), 3);
+    assert_eq!(board.cards_left_to_play(), 20);
+    assert_eq!(board.pace(), (3 + board.deck_size + 2) as i32 - 23);
+    assert_eq!(board.score_lost_by_discarding(&Card::new('w', 5)), 1);
+    assert_eq!(board.score_lost_by_discarding(&Card::new('g', 2)), 4);
+    assert_eq!(board.score_lost_by_discarding(&Card::new('g', 1)), 0);
+    assert_eq!(board.score_lost_by_discarding(&Card::new('r', 1)), 0);
+    assert_eq!(board.score_lost_by_discarding(&Card::new('b', 2)), 0);
+}
+
+// Every discard uses up a draw, and plays leave the pace as it was
+#[test]
+fn pace_over_a_game() {
+    let opts = options(3, 5);
+    for seed in 0..100 {
+        let mut game = GameState::new(&opts, new_deck(seed));
+        let mut turn = 0;
+        while !game.is_over() {
+            let before = game.board.pace();
+            let loss_before = game.board.discard_loss;
+            let deck_before = game.board.deck_size;
+            let choice = if game.board.hints_remaining > 0 && turn % 3 == 0 {
+                let player = game.board.player_to_left(&game.board.player);
+                let hinted = Hinted::Value(game.hands[&player][0].value);
+                TurnChoice::Hint(Hint { player: player, hinted: hinted })
+            } else if turn % 2 == 0 {
+                TurnChoice::Play(0)
+            } else {
+                TurnChoice::Discard(0)
+            };
+            let record = game.process_choice(choice);
+            let lost = (game.board.discard_loss - loss_before) as i32;
+            let expected = match record.result {
+                TurnResult::Hint(_) => before,
+                TurnResult::Play(_, true) if deck_before > 0 => before,
+                // playing from the last cards in hand scores without using a draw
+                TurnResult::Play(_, true) => before + 1,
+                _ if deck_before > 0 => before - 1 + lost,
+                _ => before + lost,
+            };
+            assert_eq!(game.board.pace(), expected, "seed {}, turn {}: {:?}", seed, game.board.turn - 1, record);
+            assert_eq!(game.board.is_final_round(), game.board.deck_size == 0);
+            turn += 1;
+        }
+    }
+}
+
+#[test]
+fn final_round() {
+    let mut opts = options(2, 5);
+    let game = PositionBuilder::new(&opts)
+        .hand(0, "r1 y1 g1 b1 w1")
+        .hand(1, "r2 y2 g2 b2 w2")
+        .deck("")
+        .build();
+    assert!(game.board.is_final_round());
+    assert_eq!(game.board.min_turns_remaining(), 3);
+
+    opts.endgame_rule = EndgameRule::UntilStuck;
+    let game = PositionBuilder::new(&opts)
+        .hand(0, "r1 y1 g1 b1 w1")
+        .hand(1, "r2 y2 g2 b2 w2")
+        .deck("")
+        .build();
+    assert!(!game.board.is_final_round());
+}
+
+// Under EndgameRule::UntilStuck, a player with no cards left can still hint about a playable card
+#[test]
+fn empty_hands_can_still_hint() {
+    let mut opts = options(3, 1);
+    opts.endgame_rule = EndgameRule::UntilStuck;
+    opts.discard_half_hints = 0;
+    for &(hints, stuck) in &[(2, true), (3, false)] {
+        let mut game = PositionBuilder::new(&opts)
+            .hand(0, "r1")
+            .hand(1, "y5")
+            .hand(2, "w5")
+            .deck("")
+            .hints(hints)
+            .player_to_move(1)
+            .build();
+        game.process_choice(TurnChoice::Discard(0));
+        game.process_choice(TurnChoice::Hint(Hint { player: 0, hinted: Hinted::Color('r') }));
+        game.process_choice(TurnChoice::Hint(Hint { player: 2, hinted: Hinted::Color('w') }));
+        // player 1 has no cards, but the r1 is still playable
+        assert_eq!(game.board.player, 1);
+        assert!(game.hands[&1].is_empty());
+        assert_eq!(game.board.hints_remaining, hints - 2);
+        assert_eq!(game.is_over(), stuck, "{} hints", hints);
+        if stuck {
+            assert_eq!(game.board.end_cause(), Some(EndCause::Stuck));
+            continue;
+        }
+        game.process_choice(TurnChoice::Hint(Hint { player: 0, hinted: Hinted::Value(1) }));
+        game.process_choice(TurnChoice::Discard(0));
+        game.process_choice(TurnChoice::Play(0));
+        assert_eq!(game.board.end_cause(), Some(EndCause::Stuck));
+        assert_eq!(game.score(), 1);
+    }
+}
+
+// A puzzle is the game so far, and a move after which the cheating strategy still reaches the
+// best score left
+// With every card known, the solver finds the best score left, and what each move costs
+#[test]
+fn solver_finds_best_score() {
+    let opts = options(2, 5);
+    let mut game = PositionBuilder::new(&opts)
+        .hand(0, "r4 g1 g1 b1 b1")
+        .hand(1, "r5 y1 y1 w1 w1")
+        .firework('r', 3)
+        .firework('y', 5)
+        .firework('g', 5)
+        .firework('b', 5)
+        .firework('w', 5)
+        .discard("r1 r2 r3 r4 g3 g4 y2 y3 y4 b2 b3 b4 w2 w3 w4")
+        .build();
+    assert_eq!(game.board.deck_size, 2);
+    let before = game.clone();
+    let mut solver = Solver::exact();
+    assert_eq!(solver.best_score(&mut game), 25);
+    assert_eq!(solver.score_after(&mut game, &TurnChoice::Play(0)), 25);
+    // the last red 4
+    assert_eq!(solver.score_after(&mut game, &TurnChoice::Discard(0)), 23);
+    assert_eq!(game, before);
+}
+
+#[test]
+fn puzzles_are_solved_by_their_solution() {
+    let opts = options(3, 5);
+    let config = CheatingStrategyConfig::new();
+    for seed in 0..5 {
+        let game = simulate_once(&opts, config.initialize(&opts), seed);
+        let choices = game.turn_history.iter().map(|turn| turn.choice.clone()).collect::<Vec<_>>();
+        for puzzle in find_puzzles(&opts, seed, &new_deck(seed), &choices, 3) {
+            let turns = puzzle.game.actions.len();
+            assert_eq!(turns as u32 + 1, puzzle.position.board.turn);
+            assert_eq!(&puzzle.position.turn_history[..], &game.turn_history[..turns]);
+            let mut position = puzzle.position.clone();
+            let mut solver = Solver::exact();
+            assert_eq!(solver.best_score(&mut position), puzzle.score);
+            let legal = {
+                let view = position.get_view(position.board.player);
+                position.board.legal_choices(&view).collect::<Vec<_>>()
+            };
+            for choice in legal {
+                let score = solver.score_after(&mut position, &choice);
+                if choice == puzzle.solution.choice {
+                    assert_eq!(score, puzzle.score);
+                } else {
+                    assert!(score < puzzle.score);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,376 @@
+extern crate rust_hanabi;
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use rust_hanabi::clock::SystemClock;
+use rust_hanabi::game::*;
+use rust_hanabi::helpers::catch_quietly;
+use rust_hanabi::observer::LogObserver;
+use rust_hanabi::simulator::{new_deck, resume_once, run_invariant_checks, simulate, simulate_once,
+                             simulate_once_with_shared_deck, stream, Analyses, Comparison, DeckCache,
+                             DiscardRiskStats, PlayStats, SimResult};
+use rust_hanabi::strategy::{GameStrategy, GameStrategyConfig, InitContext, PlayerStrategy, StrategyRng};
+use rust_hanabi::strategies::cheat_search::CheatSearchStrategyConfig;
+use rust_hanabi::strategies::cheating::CheatingStrategyConfig;
+use rust_hanabi::strategies::examples::{BeginnerStrategyConfig, DiscardOldestStrategyConfig, PlayFirstStrategyConfig,
+                                        RandomStrategyConfig};
+use rust_hanabi::strategies::information::InformationStrategyConfig;
+use rust_hanabi::strategies::semi_cheating::SemiCheatingStrategyConfig;
+use rust_hanabi::validate::ValidatingStrategyConfig;
+use common::options;
+
+#[test]
+fn standard_games() {
+    for &(num_players, hand_size) in &[(2, 5), (3, 5), (4, 4), (5, 4), (6, 3)] {
+        run_invariant_checks(&options(num_players, hand_size), 0, 1000);
+    }
+}
+
+#[test]
+fn until_stuck() {
+    let mut opts = options(3, 5);
+    opts.endgame_rule = EndgameRule::UntilStuck;
+    run_invariant_checks(&opts, 0, 1000);
+}
+
+#[test]
+fn misplays_return_to_deck() {
+    let mut opts = options(3, 5);
+    opts.misplay_rule = MisplayRule::ReturnToDeckBottom;
+    // enough lives for several misplays, some of them once the deck has run out
+    opts.num_lives = 10;
+    run_invariant_checks(&opts, 0, 1000);
+    opts.endgame_rule = EndgameRule::UntilStuck;
+    run_invariant_checks(&opts, 0, 1000);
+}
+
+#[test]
+fn unusual_options() {
+    let mut opts = options(4, 6);
+    opts.allow_empty_hints = true;
+    opts.strikeout_scores_zero = true;
+    opts.end_when_settled = true;
+    opts.num_lives = 1;
+    opts.num_hints = 2;
+    opts.starting_hints = 2;
+    run_invariant_checks(&opts, 0, 1000);
+}
+
+#[test]
+fn scarce_hints() {
+    let mut opts = options(3, 5);
+    opts.starting_hints = 3;
+    opts.discard_half_hints = 1;
+    opts.firework_half_hints = 0;
+    run_invariant_checks(&opts, 0, 1000);
+}
+
+// Undoing every turn of a game, one at a time, retraces the game exactly
+#[test]
+fn apply_and_undo() {
+    for &(num_players, hand_size, misplay_rule) in &[(2, 5, MisplayRule::Discard), (4, 4, MisplayRule::Discard),
+                                                     (3, 5, MisplayRule::ReturnToDeckBottom)] {
+        let mut opts = options(num_players, hand_size);
+        opts.misplay_rule = misplay_rule;
+        for seed in 0..200 {
+            let mut game = GameState::new(&opts, new_deck(seed));
+            let mut states = Vec::new();
+            let mut undos = Vec::new();
+            while !game.is_over() {
+                // any legal move will do, as long as the games vary
+                let choices = {
+                    let view = game.get_view(game.board.player);
+                    game.board.legal_choices(&view).collect::<Vec<_>>()
+                };
+                let choice = &choices[(seed as usize * 31 + game.board.turn as usize * 17) % choices.len()];
+                states.push(game.clone());
+                undos.push(game.apply(choice));
+            }
+            while let Some(undo) = undos.pop() {
+                game.undo(undo);
+                assert!(game == states.pop().unwrap(), "Seed {} didn't undo back to turn {}", seed, game.board.turn);
+            }
+        }
+    }
+}
+
+// A cached deck is the one the seed shuffles, handed out once per seed, and plays the same game
+#[test]
+fn deck_cache() {
+    let opts = options(3, 5);
+    let config = CheatingStrategyConfig::new();
+    let decks = DeckCache::new();
+    for seed in 0..20 {
+        let deck = decks.get(seed);
+        assert_eq!(*deck, new_deck(seed));
+        assert!(Arc::ptr_eq(&deck, &decks.get(seed)));
+        let cached = simulate_once_with_shared_deck(&opts, config.initialize(&opts), seed, deck);
+        let shuffled = simulate_once(&opts, config.initialize(&opts), seed);
+        assert_eq!(cached.turn_history, shuffled.turn_history);
+    }
+    assert_eq!(decks.len(), 20);
+}
+
+// Streamed games are the ones simulate_once would play, yielded only as they are asked for
+#[test]
+fn stream_games() {
+    let opts = options(3, 5);
+    let mut config = CheatingStrategyConfig::new();
+    let games = stream(&opts, &mut config, 10..20).take(4).collect::<Vec<_>>();
+    assert_eq!(games.iter().map(|result| result.seed).collect::<Vec<_>>(), vec![10, 11, 12, 13]);
+    for result in games {
+        assert!(result.game.is_over());
+        assert_eq!(result.game, simulate_once(&opts, CheatingStrategyConfig::new().initialize(&opts), result.seed));
+    }
+}
+
+// the strategies the command line offers by name, as it configures them by default
+fn built_in_strategies() -> Vec<(&'static str, Box<GameStrategyConfig>)> {
+    vec![
+        ("random", Box::new(RandomStrategyConfig {
+            hint_probability: 0.4,
+            play_probability: 0.2,
+        }) as Box<GameStrategyConfig>),
+        ("play-first", Box::new(PlayFirstStrategyConfig::new()) as Box<GameStrategyConfig>),
+        ("discard-oldest", Box::new(DiscardOldestStrategyConfig::new()) as Box<GameStrategyConfig>),
+        ("beginner", Box::new(BeginnerStrategyConfig::new()) as Box<GameStrategyConfig>),
+        ("cheat", Box::new(CheatingStrategyConfig::new()) as Box<GameStrategyConfig>),
+        ("semi-cheat", Box::new(SemiCheatingStrategyConfig::new()) as Box<GameStrategyConfig>),
+        ("cheat-search", Box::new(CheatSearchStrategyConfig::new()) as Box<GameStrategyConfig>),
+        ("info", Box::new(InformationStrategyConfig::new()) as Box<GameStrategyConfig>),
+    ]
+}
+
+// A game resumed partway through goes on as the whole game did
+#[test]
+fn resumed_games_match_whole_games() {
+    for (name, config) in built_in_strategies() {
+        for &(num_players, hand_size) in &[(2, 5), (3, 5), (4, 4), (5, 4)] {
+            let opts = options(num_players, hand_size);
+            for seed in 0..10 {
+                let whole = simulate_once(&opts, config.initialize(&opts), seed);
+                let choices = whole.turn_history.iter().map(|turn| turn.choice.clone()).collect::<Vec<_>>();
+                let prefix = &choices[..choices.len() / 2];
+                let resumed = resume_once(&opts, config.initialize(&opts), seed, new_deck(seed), prefix,
+                                          &mut LogObserver).unwrap();
+                assert_eq!(resumed.turn_history, whole.turn_history,
+                           "{} with {} players, seed {}", name, num_players, seed);
+            }
+        }
+    }
+}
+
+#[test]
+fn resume_refuses_illegal_choices() {
+    let opts = options(3, 5);
+    let strategy = CheatingStrategyConfig::new().initialize(&opts);
+    let prefix = vec![TurnChoice::Discard(0), TurnChoice::Play(7)];
+    assert!(resume_once(&opts, strategy, 0, new_deck(0), &prefix, &mut LogObserver).is_err());
+}
+
+// plays like another strategy, but gives up on the given turn
+struct ConcedeOnTurn(Box<GameStrategy>, u32);
+impl GameStrategy for ConcedeOnTurn {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(ConcedingPlayer(self.0.initialize(context, rng), self.1))
+    }
+    fn seat_name(&self, player: Player) -> String {
+        self.0.seat_name(player)
+    }
+}
+struct ConcedingPlayer(Box<PlayerStrategy>, u32);
+impl PlayerStrategy for ConcedingPlayer {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        self.0.decide(view)
+    }
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.0.update(turn, view)
+    }
+    fn concede(&mut self, view: &BorrowedGameView) -> bool {
+        view.board.turn == self.1
+    }
+}
+
+// Conceding ends the game there, with the score so far
+#[test]
+fn concede() {
+    let opts = options(3, 5);
+    let config = CheatingStrategyConfig::new();
+    for seed in 0..20 {
+        let full = simulate_once(&opts, config.initialize(&opts), seed);
+        let conceded = simulate_once(&opts, Box::new(ConcedeOnTurn(config.initialize(&opts), 20)), seed);
+        assert_eq!(conceded.board.end_cause(), Some(EndCause::Conceded));
+        assert_eq!(conceded.board.turn, 20);
+        assert_eq!(&conceded.turn_history[..], &full.turn_history[..19]);
+        let score = full.turn_history[..19].iter().filter(|turn| {
+            match turn.result {
+                TurnResult::Play(_, playable) => playable,
+                _ => false,
+            }
+        }).count();
+        assert_eq!(conceded.score() as usize, score);
+    }
+
+    let mut game = GameState::new(&opts, new_deck(0));
+    game.concede();
+    assert!(game.is_over());
+    assert_eq!(game.score(), 0);
+}
+
+// --validate only checks a strategy, so a strategy that concedes gives up on the same turns with it
+#[test]
+fn validate_keeps_concessions() {
+    let opts = options(3, 5);
+    let conceding = || {
+        let mut config = CheatSearchStrategyConfig::new();
+        config.concede = true;
+        config
+    };
+    for seed in 0..10 {
+        let plain = simulate_once(&opts, conceding().initialize(&opts), seed);
+        let validated = simulate_once(&opts, ValidatingStrategyConfig::new(Box::new(conceding())).initialize(&opts), seed);
+        assert_eq!(validated.board.end_cause(), plain.board.end_cause());
+        assert_eq!(validated.turn_history, plain.turn_history);
+        assert_eq!(validated.score(), plain.score());
+    }
+}
+
+// plays from past the end of the hand, always the same slot, or a new one each time
+struct IllegalPlays {
+    decisions: Arc<Mutex<u32>>,
+    vary: bool,
+}
+impl GameStrategy for IllegalPlays {
+    fn initialize(&self, _: &InitContext, _: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(IllegalPlayer { decisions: self.decisions.clone(), vary: self.vary })
+    }
+    fn seat_name(&self, _: Player) -> String {
+        String::from("illegal")
+    }
+}
+struct IllegalPlayer {
+    decisions: Arc<Mutex<u32>>,
+    vary: bool,
+}
+impl PlayerStrategy for IllegalPlayer {
+    fn decide(&mut self, _: &BorrowedGameView) -> TurnChoice {
+        let mut decisions = self.decisions.lock().unwrap();
+        *decisions += 1;
+        TurnChoice::Play(if self.vary { 10 + *decisions as usize } else { 10 })
+    }
+    fn update(&mut self, _: &TurnRecord, _: &BorrowedGameView) {}
+}
+
+struct IllegalConfig;
+impl GameStrategyConfig for IllegalConfig {
+    fn initialize(&self, _: &GameOptions) -> Box<GameStrategy> {
+        Box::new(IllegalPlays { decisions: Arc::new(Mutex::new(0)), vary: false })
+    }
+}
+
+// catch_quietly catches a panic on one of the threads simulate plays on, and says what it was
+#[test]
+fn catch_quietly_reports_simulation_panics() {
+    let opts = options(3, 5);
+    let result = catch_quietly(|| {
+        simulate(&opts, Box::new(IllegalConfig), Some(0), 4, 1, None, &[], None, None, Analyses::none(),
+                 &SystemClock::shared())
+    });
+    let why = result.err().expect("An illegal move should panic");
+    assert!(why.contains("made an illegal move"), "{}", why);
+}
+
+// Retrying gives a player a few more tries, but not to repeat an illegal move
+#[test]
+fn retry_forfeits_a_repeated_illegal_move() {
+    let mut opts = options(3, 5);
+    opts.illegal_moves = IllegalMovePolicy::Retry;
+    for &(vary, decisions) in &[(false, 2), (true, 4)] {
+        let counter = Arc::new(Mutex::new(0));
+        let game = simulate_once(&opts, Box::new(IllegalPlays { decisions: counter.clone(), vary: vary }), 0);
+        assert_eq!(game.board.end_cause(), Some(EndCause::Forfeited));
+        assert_eq!(*counter.lock().unwrap(), decisions);
+    }
+}
+
+// Games without hints have no plays per hint
+#[test]
+fn play_stats_without_hints() {
+    let opts = options(3, 5);
+    let game = simulate_once(&opts, PlayFirstStrategyConfig::new().initialize(&opts), 0);
+    let stats = PlayStats::from_game(&game);
+    assert_eq!(stats.hints, 0);
+    assert_eq!(stats.plays_per_hint(), None);
+}
+
+// Games without discards lose no last copies
+#[test]
+fn discard_risk_without_discards() {
+    let opts = options(3, 5);
+    let game = simulate_once(&opts, PlayFirstStrategyConfig::new().initialize(&opts), 0);
+    let risk = DiscardRiskStats::from_game(&game);
+    assert_eq!(risk.total(&risk.last_copy), 0);
+    assert_eq!(risk.last_copy_rate(), 0.0);
+}
+
+// Only the analyses asked for are made
+#[test]
+fn analyses_when_asked() {
+    let opts = options(3, 5);
+    let game = simulate_once(&opts, CheatingStrategyConfig::new().initialize(&opts), 0);
+    let mut analyses = Analyses::none();
+    analyses.discard_risk = true;
+    let mut result = SimResult::with_analyses(analyses);
+    result.add_game(0, &game);
+    let risk = DiscardRiskStats::from_game(&game);
+    assert_eq!(result.discard_risk.dead, risk.dead);
+    assert_eq!(result.discard_risk.copy_in_deck, risk.copy_in_deck);
+    assert_eq!(result.play_stats.discards, 0);
+    assert!(result.tempo.lengths.hist.is_empty());
+    assert_eq!(result.hint_stats.total(), 0);
+    assert_eq!(result.scores.total_count, 1);
+}
+
+// A comparison over a single game has no spread to measure, and is no evidence of a difference
+#[test]
+fn compare_one_game() {
+    let opts = options(3, 5);
+    let mut first = SimResult::new();
+    first.add_game(0, &simulate_once(&opts, PlayFirstStrategyConfig::new().initialize(&opts), 0));
+    let mut second = SimResult::new();
+    second.add_game(0, &simulate_once(&opts, CheatingStrategyConfig::new().initialize(&opts), 0));
+    let comparison = Comparison::new(&first, &second);
+    assert_eq!(comparison.n_games, 1);
+    assert!(comparison.mean_score_diff > 0.0);
+    assert_eq!(comparison.score_diff_stderr, 0.0);
+    assert_eq!(comparison.score_p_value, 1.0);
+    assert_eq!(comparison.win_rate_diff_stderr, 0.0);
+}
+
+// plays like the cheating strategy, counting the games that end
+struct CountingConfig {
+    games: Arc<Mutex<u32>>,
+}
+impl GameStrategyConfig for CountingConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        CheatingStrategyConfig::new().initialize(opts)
+    }
+
+    fn on_game_end(&self, _: &GameState) {
+        *self.games.lock().unwrap() += 1;
+    }
+}
+
+// A config hears about the games played on every thread
+#[test]
+fn configs_learn_from_every_thread() {
+    let opts = options(3, 5);
+    let games = Arc::new(Mutex::new(0));
+    let config = Box::new(CountingConfig { games: games.clone() });
+    let result = simulate(&opts, config, Some(0), 40, 4, None, &[], None, None, Analyses::none(),
+                          &SystemClock::shared());
+    assert_eq!(result.scores.total_count, 40);
+    assert_eq!(*games.lock().unwrap(), 40);
+}
@@ -0,0 +1,203 @@
+extern crate rust_hanabi;
+
+mod common;
+
+use std::env;
+use std::fs;
+
+use rust_hanabi::checkpoint::Checkpoint;
+use rust_hanabi::game::*;
+use rust_hanabi::html_replay::{render, GameTrace};
+use rust_hanabi::json::Json;
+use rust_hanabi::json_output::{ActionExport, GameExport, JsonObserver, Transcript, Writer};
+use rust_hanabi::simulator::{new_deck, simulate_once, simulate_once_observed, DealFilter};
+use rust_hanabi::strategy::GameStrategyConfig;
+use rust_hanabi::strategies::examples::RandomStrategyConfig;
+use rust_hanabi::strategies::information::InformationStrategyConfig;
+use rust_hanabi::training::TrainingDataObserver;
+use rust_hanabi::version::ArtifactVersion;
+use common::options;
+
+// plays a game, returning it and its export
+fn play(opts: &GameOptions, seed: u32, empathy: bool) -> (GameState, GameExport) {
+    let config = InformationStrategyConfig::new();
+    let path = env::temp_dir().join(format!("hanabi-json-export-{}-{}.json", seed, empathy));
+    let path = path.to_str().unwrap();
+    let writer = Writer::new(path).unwrap();
+    let mut observer = JsonObserver::new(writer, ArtifactVersion::new("info", config.version()), empathy);
+    let game = simulate_once_observed(opts, config.initialize(opts), seed, &mut observer);
+    let export = observer.export(&game);
+    // the file written at the end of the game is the same export
+    let written = Json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+    fs::remove_file(path).unwrap();
+    assert_eq!(written, export.to_json());
+    (game, export)
+}
+
+// Writing a game and reading it back gives the same game
+#[test]
+fn round_trip() {
+    for &(num_players, hand_size) in &[(2, 5), (3, 5), (5, 4)] {
+        let opts = options(num_players, hand_size);
+        for seed in 0..5 {
+            for &empathy in &[false, true] {
+                let (_, export) = play(&opts, seed, empathy);
+                assert_eq!(export.empathy.is_some(), empathy);
+                // every player's note on every card, most of them blank
+                let notes = export.notes.clone().expect("The information strategy writes notes");
+                assert_eq!(notes.len(), num_players as usize);
+                assert!(notes.iter().all(|player_notes| player_notes.len() == export.deck.len()));
+                let text = export.to_json().to_string();
+                assert_eq!(GameExport::from_json(&Json::parse(&text).unwrap()).unwrap(), export);
+            }
+        }
+    }
+}
+
+// The choices read back from an export replay the game exactly
+#[test]
+fn replays_as_played() {
+    let opts = options(3, 5);
+    for seed in 0..5 {
+        let (game, export) = play(&opts, seed, false);
+        let transcript = Transcript::from_export(export);
+        assert_eq!(transcript.num_players, 3);
+        assert_eq!(transcript.seed, Some(seed));
+        let mut replayed = GameState::new(&opts, transcript.engine_deck());
+        for choice in transcript.choices(opts.hand_size).unwrap() {
+            replayed.process_choice(choice);
+        }
+        assert_eq!(replayed.turn_history, game.turn_history);
+        assert_eq!(replayed.score(), game.score());
+    }
+}
+
+// The format itself, as hanab.live expects it
+#[test]
+fn schema() {
+    let export = GameExport {
+        players: vec![String::from("Player 0"), String::from("Player 1")],
+        deck: vec![Card::new('r', 1), Card::new('w', 5)],
+        actions: vec![
+            ActionExport { action_type: 3, target: 1, value: Some(5) },
+            ActionExport { action_type: 0, target: 0, value: None },
+        ],
+        variant: Some(String::from("No Variant")),
+        seed: Some(String::from("12")),
+        score: Some(1),
+        version: None,
+        empathy: None,
+        notes: None,
+    };
+    let text = "{\"players\":[\"Player 0\",\"Player 1\"],\
+                \"deck\":[{\"suitIndex\":0,\"rank\":1},{\"suitIndex\":4,\"rank\":5}],\
+                \"actions\":[{\"type\":3,\"target\":1,\"value\":5},{\"type\":0,\"target\":0}],\
+                \"options\":{\"variant\":\"No Variant\"},\"seed\":\"12\",\"score\":1}";
+    assert_eq!(export.to_json().to_string(), text);
+    assert_eq!(GameExport::from_json(&Json::parse(text).unwrap()).unwrap(), export);
+
+    // hanab.live's own exports have fields we don't write, and seeds that aren't numbers
+    let hanab_live = "{\"id\":1,\"players\":[\"alice\",\"bob\"],\"deck\":[{\"suitIndex\":0,\"rank\":1}],\
+                      \"actions\":[],\"seed\":\"p2v0s1\",\"notes\":[[],[]]}";
+    let export = GameExport::from_json(&Json::parse(hanab_live).unwrap()).unwrap();
+    assert_eq!(export.players, vec![String::from("alice"), String::from("bob")]);
+    assert_eq!(export.variant, None);
+    assert_eq!(export.notes, Some(vec![Vec::new(), Vec::new()]));
+    assert_eq!(Transcript::from_export(export).seed, None);
+
+    let bad_suit = "{\"players\":[],\"deck\":[{\"suitIndex\":5,\"rank\":1}],\"actions\":[]}";
+    assert!(GameExport::from_json(&Json::parse(bad_suit).unwrap()).is_err());
+    let no_actions = "{\"players\":[],\"deck\":[]}";
+    assert!(GameExport::from_json(&Json::parse(no_actions).unwrap()).is_err());
+}
+
+// A game exported from hanab.live, with one of its seed strings, deals the hands in the order
+// its deck lists them, as the site did, and draws the rest in that order
+#[test]
+fn deals_hanab_live_decks() {
+    let opts = options(2, 5);
+    let mut draw_order = new_deck(7);
+    draw_order.reverse();
+    let export = GameExport {
+        players: vec![String::from("alice"), String::from("bob")],
+        deck: draw_order.clone(),
+        actions: Vec::new(),
+        variant: Some(String::from("No Variant")),
+        seed: Some(String::from("p2v0s123")),
+        score: None,
+        version: None,
+        empathy: None,
+        notes: None,
+    };
+    let transcript = Transcript::from_json(&export.to_json()).unwrap();
+    assert_eq!(transcript.num_players, 2);
+    let mut game = GameState::new(&opts, transcript.engine_deck());
+    assert_eq!(game.hands[&0], draw_order[0..5].to_vec());
+    assert_eq!(game.hands[&1], draw_order[5..10].to_vec());
+    game.process_choice(TurnChoice::Discard(0));
+    assert_eq!(game.hands[&0].last(), Some(&draw_order[10]));
+}
+
+// Misplayed cards that go back into the deck leave a hand without drawing a new card from the
+// deck's count; the training data still knows about every card in every hand
+#[test]
+fn training_data_with_returned_misplays() {
+    let mut opts = options(3, 5);
+    opts.misplay_rule = MisplayRule::ReturnToDeckBottom;
+    opts.num_lives = 10;
+    let config = RandomStrategyConfig { hint_probability: 0.2, play_probability: 0.5 };
+    let dir = env::temp_dir().join("hanabi-training-returned-misplays");
+    fs::create_dir_all(&dir).unwrap();
+    for seed in 0..10 {
+        let mut observer = TrainingDataObserver::new(dir.to_str().unwrap(), ArtifactVersion::new("random", 0));
+        let game = simulate_once_observed(&opts, config.initialize(&opts), seed, &mut observer);
+        let path = dir.join(format!("{}.ndjson", seed));
+        let lines = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(lines.lines().count(), game.turn_history.len());
+        for line in lines.lines() {
+            let record = Json::parse(line).unwrap();
+            let knowledge = record.get("knowledge").and_then(Json::as_array).unwrap();
+            for hand in record.get("other_hands").and_then(Json::as_array).unwrap() {
+                let player = hand.get("player").and_then(Json::as_u32).unwrap();
+                let cards = hand.get("cards").and_then(Json::as_array).unwrap();
+                assert_eq!(knowledge[player as usize].as_array().unwrap().len(), cards.len());
+            }
+        }
+    }
+}
+
+// A checkpoint only resumes the run it was saved from
+#[test]
+fn checkpoint_refuses_other_runs() {
+    let opts = options(3, 5);
+    let version = ArtifactVersion::new("info", InformationStrategyConfig::new().version());
+    let params = vec![(String::from("hint_weight"), String::from("2"))];
+    let filters = vec![DealFilter::OpeningFives(2)];
+    let path = env::temp_dir().join("hanabi-checkpoint-other-runs.json");
+    let path = path.to_str().unwrap();
+    let mut checkpoint = Checkpoint::new(&version, &opts, &params, &filters, 0, 100);
+    checkpoint.next_seed = 40;
+    checkpoint.write(path).unwrap();
+
+    let resumed = Checkpoint::resume(path, &version, &opts, &params, &filters, 0, 100).unwrap();
+    assert_eq!(resumed.next_seed, 40);
+    assert!(Checkpoint::resume(path, &version, &opts, &[], &filters, 0, 100).is_err());
+    let other_params = vec![(String::from("hint_weight"), String::from("3"))];
+    assert!(Checkpoint::resume(path, &version, &opts, &other_params, &filters, 0, 100).is_err());
+    assert!(Checkpoint::resume(path, &version, &opts, &params, &[], 0, 100).is_err());
+    let other_filters = vec![DealFilter::OpeningFives(3)];
+    assert!(Checkpoint::resume(path, &version, &opts, &params, &other_filters, 0, 100).is_err());
+    fs::remove_file(path).unwrap();
+}
+
+// A replay's title is shown as text, whatever it holds
+#[test]
+fn html_replay_escapes_its_title() {
+    let opts = options(2, 5);
+    let game = simulate_once(&opts, InformationStrategyConfig::new().initialize(&opts), 0);
+    let trace = GameTrace::new(&opts, new_deck(0), &game.turn_history);
+    let page = render(&trace, "<script>alert(\"a & b\")</script> STEPS");
+    assert!(!page.contains("<script>alert"));
+    assert!(page.contains("<title>&lt;script&gt;alert(&quot;a &amp; b&quot;)&lt;/script&gt; STEPS</title>"));
+}
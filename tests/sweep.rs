@@ -0,0 +1,61 @@
+extern crate rust_hanabi;
+
+use rust_hanabi::sweep::{Sampling, Sweep, Values};
+
+fn list(values: &[&str]) -> Values {
+    Values::List(values.iter().map(|value| value.to_string()).collect())
+}
+
+#[test]
+fn grid_with_comments() {
+    let sweep = Sweep::parse("# a study\n\
+                              [grid]  # every combination\n\
+                              risky_play_threshold = [0.6, 0.9]  # two values\n\
+                              discard_policy = \"heuristic\"# no space before it\n").unwrap();
+    assert_eq!(sweep.sampling, Sampling::Grid);
+    assert_eq!(sweep.params, vec![
+        (String::from("risky_play_threshold"), list(&["0.6", "0.9"])),
+        (String::from("discard_policy"), list(&["heuristic"])),
+    ]);
+    assert_eq!(sweep.configurations().len(), 2);
+}
+
+#[test]
+fn random_with_comments() {
+    let sweep = Sweep::parse("[random]\n\
+                              samples = 5 # configurations\n\
+                              seed = 3\n\
+                              ask_play_target = { min = 1, max = 4 } # integers\n").unwrap();
+    assert_eq!(sweep.sampling, Sampling::Random { samples: 5, seed: 3 });
+    assert_eq!(sweep.params, vec![(String::from("ask_play_target"), Values::Range(1.0, 4.0))]);
+    for config in sweep.configurations() {
+        let value = config[0].1.parse::<u32>().unwrap();
+        assert!(1 <= value && value <= 4);
+    }
+}
+
+#[test]
+fn quoted_values_keep_their_hashes_and_commas() {
+    let sweep = Sweep::parse("[grid]\n\
+                              name = [\"a # b\", \"c, d\", e] # the comment\n").unwrap();
+    assert_eq!(sweep.params, vec![(String::from("name"), list(&["a # b", "c, d", "e"]))]);
+}
+
+#[test]
+fn bad_sweeps() {
+    let error = |contents: &str| Sweep::parse(contents).unwrap_err();
+    assert!(error("").starts_with("1:"));
+    assert!(error("x = 1\n").starts_with("1:"));
+    assert!(error("[grid]\n[random]\n").starts_with("2:"));
+    assert!(error("[lattice]\n").starts_with("1:"));
+    assert!(error("[grid]\nx\n").starts_with("2:"));
+    assert!(error("[grid]\nx = 1\nx = 2\n").starts_with("3:"));
+    assert!(error("[grid]\nx = \"open\n").starts_with("2:"));
+    assert!(error("[grid]\nx = [1, 2\n").starts_with("2:"));
+    assert!(error("[grid]\nx = []\n").starts_with("2:"));
+    assert!(error("[grid]\nx = # nothing\n").starts_with("2:"));
+    assert!(error("[grid]\nx = { min = 0, max = 1 }\n").starts_with("2:"));
+    assert!(error("[random]\nsamples = 0\n").starts_with("1:"));
+    assert!(error("[random]\nsamples = 2\nx = { min = 2, max = 1 }\n").starts_with("3:"));
+    assert!(error("[random]\nsamples = 2\nx = { min = 2 }\n").starts_with("3:"));
+}
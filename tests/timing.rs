@@ -0,0 +1,99 @@
+extern crate rust_hanabi;
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use rust_hanabi::clock::{Clock, MockClock};
+use rust_hanabi::game::*;
+use rust_hanabi::progress::ProgressBar;
+use rust_hanabi::simulator::{simulate, Analyses};
+use rust_hanabi::strategy::*;
+use rust_hanabi::strategies::cheating::CheatingStrategyConfig;
+use common::options;
+
+// plays like the cheating strategy, taking `think` on the clock to decide each move
+struct SlowConfig {
+    clock: Arc<MockClock>,
+    think: Duration,
+}
+impl GameStrategyConfig for SlowConfig {
+    fn initialize(&self, opts: &GameOptions) -> Box<GameStrategy> {
+        Box::new(Slow {
+            inner: CheatingStrategyConfig::new().initialize(opts),
+            clock: self.clock.clone(),
+            think: self.think,
+        })
+    }
+}
+struct Slow {
+    inner: Box<GameStrategy>,
+    clock: Arc<MockClock>,
+    think: Duration,
+}
+impl GameStrategy for Slow {
+    fn initialize(&self, context: &InitContext, rng: StrategyRng) -> Box<PlayerStrategy> {
+        Box::new(SlowPlayer {
+            inner: self.inner.initialize(context, rng),
+            clock: self.clock.clone(),
+            think: self.think,
+        })
+    }
+    fn seat_name(&self, player: Player) -> String {
+        self.inner.seat_name(player)
+    }
+}
+struct SlowPlayer {
+    inner: Box<PlayerStrategy>,
+    clock: Arc<MockClock>,
+    think: Duration,
+}
+impl PlayerStrategy for SlowPlayer {
+    fn decide(&mut self, view: &BorrowedGameView) -> TurnChoice {
+        self.clock.sleep(self.think);
+        self.inner.decide(view)
+    }
+    fn update(&mut self, turn: &TurnRecord, view: &BorrowedGameView) {
+        self.inner.update(turn, view)
+    }
+}
+
+// Decisions are timed by the clock the simulation is given
+#[test]
+fn decide_times_by_the_given_clock() {
+    let opts = options(3, 5);
+    let clock = Arc::new(MockClock::new());
+    let config = SlowConfig { clock: clock.clone(), think: Duration::from_millis(3) };
+    let shared: Arc<Clock> = clock.clone();
+    // one thread, so that no other game moves the clock in the middle of a decision
+    let result = simulate(&opts, Box::new(config), Some(0), 4, 1, None, &[], None, None, Analyses::none(), &shared);
+    let times = &result.decide_times;
+    assert!(!times.micros.is_empty());
+    assert!(times.micros.iter().all(|&micros| micros == 3000));
+    assert_eq!(times.percentile(1.0), Some(3000));
+    assert!(times.average_by_turn().iter().all(|&micros| micros == 0.0 || micros == 3000.0));
+}
+
+// The bar's rate and ETA come from its clock
+#[test]
+fn progress_rate_and_eta() {
+    let clock = Arc::new(MockClock::new());
+    let bar = ProgressBar::new("cheat 3p", 100, clock.clone());
+    for _ in 0..20 {
+        bar.record(25);
+    }
+    let status = bar.status();
+    assert!(status.contains("20/100 games, 0 games/s, ETA ?"), "{}", status);
+
+    clock.set(Duration::from_secs(10));
+    let status = bar.status();
+    assert!(status.contains("20/100 games, 2 games/s, ETA 0:40, average 25.0000"), "{}", status);
+
+    clock.advance(Duration::from_secs(50));
+    for _ in 0..30 {
+        bar.record(20);
+    }
+    let status = bar.status();
+    assert!(status.contains("50/100 games, 1 games/s, ETA 1:00, average 22.0000"), "{}", status);
+}